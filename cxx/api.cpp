@@ -1,18 +1,31 @@
 #include "api.h"
+#include <algorithm>
+#include <arpa/inet.h>
 #include <chrono>
 #include <cstdio>
+#include <cstring>
 #include <filesystem>
 #include <fstream>
+#include <ifaddrs.h>
 #include <iostream>
+#include <memory>
+#include <mutex>
+#include <net/if.h>
 #include <libtorrent/add_torrent_params.hpp>
 #include <libtorrent/alert.hpp>
 #include <libtorrent/alert_types.hpp>
+#include <libtorrent/announce_entry.hpp>
 #include <libtorrent/download_priority.hpp>
+#include <libtorrent/extensions.hpp>
 #include <libtorrent/file_storage.hpp>
+#include <libtorrent/ip_filter.hpp>
 #include <libtorrent/load_torrent.hpp>
 #include <libtorrent/magnet_uri.hpp>
+#include <libtorrent/peer_connection_handle.hpp>
 #include <libtorrent/read_resume_data.hpp>
 #include <libtorrent/session.hpp>
+#include <libtorrent/session_params.hpp>
+#include <libtorrent/session_stats.hpp>
 #include <libtorrent/settings_pack.hpp>
 #include <libtorrent/torrent_flags.hpp>
 #include <libtorrent/torrent_handle.hpp>
@@ -26,6 +39,37 @@
 using namespace std;
 namespace fs = std::filesystem;
 
+// Disconnects the peers requested. Connections are only touched from the
+// network thread, on the ticks.
+struct PeerDisconnector : lt::torrent_plugin {
+  mutex requests_mutex;
+  vector<lt::tcp::endpoint> requests;
+  vector<lt::peer_connection_handle> connections;
+
+  void request(const lt::tcp::endpoint &endpoint) {
+    lock_guard<mutex> lock(requests_mutex);
+    requests.push_back(endpoint);
+  }
+
+  shared_ptr<lt::peer_plugin>
+  new_connection(const lt::peer_connection_handle &pc) override {
+    connections.push_back(pc);
+    return nullptr;
+  }
+
+  void tick() override {
+    connections.erase(remove_if(connections.begin(), connections.end(),
+                                [](auto &pc) { return pc.expired(); }),
+                      connections.end());
+    lock_guard<mutex> lock(requests_mutex);
+    for (auto &pc : connections)
+      if (find(requests.begin(), requests.end(), pc.remote()) != requests.end())
+        pc.disconnect(boost::asio::error::connection_aborted,
+                      lt::operation_t::bittorrent);
+    requests.clear();
+  }
+};
+
 struct Torrent {
   lt::torrent_handle h;
   lt::add_torrent_params atp;
@@ -33,11 +77,17 @@ struct Torrent {
   string name;
   string save_path;
   vector<lt::peer_info> peers;
+  shared_ptr<PeerDisconnector> disconnector;
 
   Torrent(lt::torrent_handle &h, lt::add_torrent_params &atp, string hash) {
     this->h = h;
     this->atp = atp;
     this->hash = hash;
+    disconnector = make_shared<PeerDisconnector>();
+    auto plugin = disconnector;
+    h.add_extension([plugin](const lt::torrent_handle &, lt::client_data_t) {
+      return plugin;
+    });
   }
 };
 
@@ -45,10 +95,23 @@ struct State {
   lt::session *ses;
   vector<Torrent *> torrents;
   string resume_dir;
+  string session_file;
   bool should_stop = false;
   int pending_save_alerts = 0;
+  vector<pair<int, string>> events;
+  vector<int64_t> stats;
+  vector<LogMessage> logs;
+  lt::alert_category_t log_mask = lt::alert_category::error;
+  long blocked_peers = 0;
 } state;
 
+void log_message(int severity, bool is_alert, const string &message) {
+  char *message_c = new char[message.size() + 1];
+  copy(message.begin(), message.end(), message_c);
+  message_c[message.size()] = '\0';
+  state.logs.push_back({severity, is_alert, message_c});
+}
+
 const char *libtorrent_version() { return lt::version(); }
 
 string to_hex(const string binary_data) {
@@ -87,71 +150,175 @@ const char *read_resume_file(const char *path) {
     return t->hash.c_str();
   }
 
-  printf("Failed to read resume data.\n");
+  log_message(2, false, string("Failed to read resume data: ") + path);
   return "";
 }
 
 void write_resume_file(lt::torrent_handle &h, lt::add_torrent_params &atp) {
   try {
     fs::path resume_file_path = get_resume_file_path(h);
-    printf("Writing resume file: %s\n", resume_file_path.c_str());
     ofstream of(resume_file_path, ios_base::binary);
-    if (!of)
-      printf("Failed to write resume file.\n");
+    if (!of) {
+      log_message(2, false,
+                  "Failed to write resume file: " + resume_file_path.string());
+      return;
+    }
     of.unsetf(ios_base::skipws);
     auto const buf = write_resume_data_buf(atp);
     of.write(buf.data(), int(buf.size()));
     of.close();
-    cout << "Resume file path: " << resume_file_path << endl;
-    // printf("Saved resume file %s.\n", resume_file_path.c_str());
+    log_message(0, false, "Saved resume file: " + resume_file_path.string());
+  } catch (...) {
+    log_message(2, false, "Failed to write resume file.");
+  }
+}
+
+// The DHT node id and routing table and the extensions' state. Settings are
+// applied on every start instead.
+const lt::save_state_flags_t session_state_flags =
+    lt::session_handle::save_dht_state |
+    lt::session_handle::save_extension_state;
+
+lt::session_params read_session_file() {
+  ifstream ifs(state.session_file, ios_base::binary);
+  if (!ifs)
+    return {};
+  ifs.unsetf(ios_base::skipws);
+  vector<char> buf{istream_iterator<char>(ifs), istream_iterator<char>()};
+  try {
+    lt::session_params params =
+        lt::read_session_params(buf, session_state_flags);
+    log_message(0, false, "Loaded session state: " + state.session_file);
+    return params;
+  } catch (...) {
+    log_message(2, false, "Failed to read session state: " + state.session_file);
+    return {};
+  }
+}
+
+void write_session_file() {
+  try {
+    fs::path tmp_path = state.session_file + ".tmp";
+    ofstream of(tmp_path, ios_base::binary);
+    if (!of) {
+      log_message(2, false,
+                  "Failed to write session state: " + state.session_file);
+      return;
+    }
+    auto const buf = lt::write_session_params_buf(
+        state.ses->session_state(session_state_flags), session_state_flags);
+    of.write(buf.data(), int(buf.size()));
+    of.close();
+    // Replaced at once, a crash leaves the previous state
+    fs::rename(tmp_path, state.session_file);
+    log_message(0, false, "Saved session state: " + state.session_file);
   } catch (...) {
-    printf("Failed to write resume file.\n");
+    log_message(2, false, "Failed to write session state.");
+  }
+}
+
+// False if the kind is unknown.
+bool set_proxy_settings(lt::settings_pack &sp, const ProxyConfig *proxy) {
+  bool has_auth = strlen(proxy->username) > 0;
+  lt::settings_pack::proxy_type_t type;
+  switch (proxy->kind) {
+  case 0:
+    type = lt::settings_pack::none;
+    break;
+  case 1:
+    type = lt::settings_pack::socks4;
+    break;
+  case 2:
+    type = has_auth ? lt::settings_pack::socks5_pw : lt::settings_pack::socks5;
+    break;
+  case 3:
+    type = has_auth ? lt::settings_pack::http_pw : lt::settings_pack::http;
+    break;
+  default:
+    return false;
   }
+
+  sp.set_int(sp.proxy_type, type);
+  sp.set_str(sp.proxy_hostname, proxy->host);
+  sp.set_int(sp.proxy_port, proxy->port);
+  sp.set_str(sp.proxy_username, proxy->username);
+  sp.set_str(sp.proxy_password, proxy->password);
+  sp.set_bool(sp.proxy_hostnames, proxy->proxy_hostnames);
+  sp.set_bool(sp.proxy_peer_connections, proxy->proxy_peer_connections);
+  sp.set_bool(sp.proxy_tracker_connections, proxy->proxy_tracker_connections);
+  return true;
 }
 
-void initiate(const char *resume_dir) {
-  state.ses = new lt::session;
+void initiate(const char *resume_dir, const char *session_file,
+              const ProxyConfig *proxy, const char *listen_interfaces,
+              const char *outgoing_interfaces) {
+  state.session_file = string(session_file);
+  lt::session_params params = read_session_file();
 
-  lt::settings_pack sp = lt::default_settings();
+  lt::settings_pack &sp = params.settings;
   sp.set_int(sp.active_downloads, -1);
   sp.set_int(sp.connections_limit, 1000);
   sp.set_int(sp.active_seeds, -1);
   sp.set_int(sp.stop_tracker_timeout, 0);
-  state.ses->apply_settings(sp);
+  // Finished torrents are reported by status alerts, peers refused by the IP
+  // filter by IP block alerts
+  sp.set_int(sp.alert_mask,
+             lt::alert_category::error | lt::alert_category::status |
+                 lt::alert_category::storage | lt::alert_category::ip_block);
+  // Set before the session starts, so that neither the DHT nor the torrents
+  // connect around them
+  if (!set_proxy_settings(sp, proxy))
+    log_message(2, false, "Failed to apply the proxy settings.");
+  sp.set_str(sp.listen_interfaces, listen_interfaces);
+  sp.set_str(sp.outgoing_interfaces, outgoing_interfaces);
+  state.ses = new lt::session(std::move(params));
 
   state.resume_dir = string(resume_dir);
   try {
     for (const auto &entry : fs::directory_iterator(state.resume_dir))
-      read_resume_file(entry.path().c_str());
+      if (entry.path().extension() == ".resume")
+        read_resume_file(entry.path().c_str());
   } catch (const fs::filesystem_error &e) {
-    printf("Failed to read resume files.\n");
+    log_message(2, false,
+                string("Failed to read resume files: ") + e.what());
+  }
+}
+
+void set_add_paused(lt::add_torrent_params &atp, bool paused) {
+  if (paused) {
+    atp.flags &= ~lt::torrent_flags::auto_managed;
+    atp.flags |= lt::torrent_flags::paused;
   }
 }
 
-bool add_file(const char *file_path, const char *save_path) {
+bool add_file(const char *file_path, const char *save_path, bool paused) {
   try {
     lt::add_torrent_params atp = lt::load_torrent_file(file_path);
     atp.save_path = save_path;
+    set_add_paused(atp, paused);
     lt::torrent_handle h = state.ses->add_torrent(atp);
     string hash = get_hash(h);
     Torrent *t = new Torrent(h, atp, hash);
     state.torrents.push_back(t);
     write_resume_file(h, atp);
+    state.events.push_back({0, hash});
     return true;
   } catch (...) {
     return false;
   }
 }
 
-bool add_magnet_url(const char *url, const char *save_path) {
+bool add_magnet_url(const char *url, const char *save_path, bool paused) {
   try {
     lt::add_torrent_params atp = lt::parse_magnet_uri(url);
     atp.save_path = save_path;
+    set_add_paused(atp, paused);
     lt::torrent_handle h = state.ses->add_torrent(atp);
     string hash = get_hash(h);
     Torrent *t = new Torrent(h, atp, hash);
     state.torrents.push_back(t);
     write_resume_file(h, atp);
+    state.events.push_back({0, hash});
     return true;
   } catch (...) {
     return false;
@@ -165,13 +332,33 @@ void handle_alerts() {
   state.ses->pop_alerts(&alerts);
 
   for (lt::alert *alert : alerts) {
+    if (alert->category() & state.log_mask) {
+      int severity = 0;
+      if (alert->category() & lt::alert_category::error)
+        severity = 2;
+      else if (alert->category() & lt::alert_category::performance_warning)
+        severity = 1;
+      log_message(severity, true, alert->message());
+    }
+
     if (auto *at = lt::alert_cast<lt::save_resume_data_alert>(alert)) {
       write_resume_file(at->handle, at->params);
       state.pending_save_alerts--;
     } else if (auto *at =
                    lt::alert_cast<lt::save_resume_data_failed_alert>(alert)) {
-      cout << "Failed to save resume data" << endl;
+      log_message(2, false, "Failed to save resume data: " + at->message());
       state.pending_save_alerts--;
+    } else if (auto *at = lt::alert_cast<lt::torrent_finished_alert>(alert)) {
+      // Torrents loaded already complete also post this alert; only count the
+      // ones which actually downloaded something in this session.
+      if (at->handle.status().total_payload_download > 0)
+        state.events.push_back({1, get_hash(at->handle)});
+    } else if (auto *at = lt::alert_cast<lt::session_stats_alert>(alert)) {
+      auto counters = at->counters();
+      state.stats.assign(counters.begin(), counters.end());
+    } else if (auto *at = lt::alert_cast<lt::peer_blocked_alert>(alert)) {
+      if (at->reason == lt::peer_blocked_alert::ip_filter)
+        state.blocked_peers++;
     }
   }
 }
@@ -205,7 +392,7 @@ bool torrent_resume(int index) {
   }
 }
 
-bool torrent_remove(int index) {
+bool torrent_remove(int index, bool delete_files) {
   try {
     assert(index < state.torrents.size());
 
@@ -214,7 +401,9 @@ bool torrent_remove(int index) {
     std::remove(rf_path.c_str());
 
     // Remove from lt::session
-    state.ses->remove_torrent(state.torrents[index]->h);
+    state.ses->remove_torrent(state.torrents[index]->h,
+                              delete_files ? lt::session::delete_files
+                                           : lt::remove_flags_t{});
 
     // Remove from memory
     delete state.torrents[index];
@@ -226,6 +415,65 @@ bool torrent_remove(int index) {
   }
 }
 
+bool torrent_recheck(int index) {
+  try {
+    assert(index < state.torrents.size());
+
+    state.torrents[index]->h.force_recheck();
+    return true;
+  } catch (...) {
+    return false;
+  }
+}
+
+bool torrent_reannounce(int index) {
+  try {
+    assert(index < state.torrents.size());
+
+    state.torrents[index]->h.force_reannounce();
+    return true;
+  } catch (...) {
+    return false;
+  }
+}
+
+bool torrent_set_limits(int index, int download_limit, int upload_limit) {
+  try {
+    assert(index < state.torrents.size());
+
+    lt::torrent_handle &h = state.torrents[index]->h;
+    h.set_download_limit(download_limit > 0 ? download_limit : -1);
+    h.set_upload_limit(upload_limit > 0 ? upload_limit : -1);
+    return true;
+  } catch (...) {
+    return false;
+  }
+}
+
+bool torrent_queue_move(int index, int direction) {
+  try {
+    assert(index < state.torrents.size());
+
+    lt::torrent_handle &h = state.torrents[index]->h;
+    switch (direction) {
+    case 0:
+      h.queue_position_top();
+      break;
+    case 1:
+      h.queue_position_up();
+      break;
+    case 2:
+      h.queue_position_down();
+      break;
+    default:
+      h.queue_position_bottom();
+    }
+    return true;
+  } catch (...) {
+    return false;
+  }
+}
+
 bool toggle_stream(int index) {
   try {
     assert(index < state.torrents.size());
@@ -259,6 +507,24 @@ bool toggle_stream(int index) {
   }
 }
 
+bool torrent_move_storage(int index, const char *save_path) {
+  try {
+    assert(index < state.torrents.size());
+
+    lt::torrent_handle &h = state.torrents[index]->h;
+    h.move_storage(save_path);
+    return true;
+  } catch (...) {
+    return false;
+  }
+}
+
+const char *get_torrent_hash(int index) {
+  assert(index < state.torrents.size());
+
+  return state.torrents[index]->hash.c_str();
+}
+
 bool change_file_priority(int index, int f_index, int priority) {
   try {
     assert(index < state.torrents.size());
@@ -357,6 +623,19 @@ struct TorrentInfo get_torrent_info(int index) {
   else
     info.eta = -1;
 
+  info.num_files =
+      torrent_info != nullptr ? torrent_info->files().num_files() : 0;
+  info.added_time = status.added_time;
+  info.download_limit = h.download_limit();
+  info.upload_limit = h.upload_limit();
+
+  // Error
+  string error = status.errc ? status.errc.message() : "";
+  char *e = new char[error.size() + 1];
+  copy(error.begin(), error.end(), e);
+  e[error.size()] = '\0';
+  info.error = e;
+
   return info;
 }
 
@@ -364,6 +643,7 @@ void free_torrent_info(TorrentInfo info) {
   delete[] info.pieces;
   delete[] info.hash;
   delete[] info.comment;
+  delete[] info.error;
 }
 
 Peer *get_peers(int index, int *num_peers) {
@@ -373,6 +653,7 @@ Peer *get_peers(int index, int *num_peers) {
   Torrent *t = state.torrents[index];
   lt::torrent_handle &h = t->h;
   h.get_peer_info(t->peers);
+  auto have = h.status(lt::torrent_handle::query_pieces).pieces;
   *num_peers = t->peers.size();
   Peer *peers = new Peer[*num_peers];
   for (int i = 0; i < *num_peers; i++) {
@@ -381,6 +662,55 @@ Peer *get_peers(int index, int *num_peers) {
     peers[i].download_rate = p.down_speed;
     peers[i].upload_rate = p.up_speed;
     peers[i].client = p.client.c_str();
+    peers[i].port = p.ip.port();
+    peers[i].total_download = p.total_download;
+    peers[i].total_upload = p.total_upload;
+    peers[i].rtt = p.rtt;
+
+    if (p.connection_type != lt::peer_info::standard_bittorrent)
+      peers[i].connection_type = 2;
+    else if (p.flags & lt::peer_info::utp_socket)
+      peers[i].connection_type = 1;
+    else
+      peers[i].connection_type = 0;
+    peers[i].is_encrypted =
+        bool(p.flags & (lt::peer_info::rc4_encrypted |
+                        lt::peer_info::plaintext_encrypted));
+
+    const pair<int, lt::peer_source_flags_t> sources[] = {
+        {1, lt::peer_info::tracker}, {2, lt::peer_info::dht},
+        {4, lt::peer_info::pex},     {8, lt::peer_info::lsd},
+        {16, lt::peer_info::resume_data}, {32, lt::peer_info::incoming},
+    };
+    peers[i].source = 0;
+    for (auto &[bit, source] : sources)
+      if (p.source & source)
+        peers[i].source |= bit;
+
+    const pair<int, lt::peer_flags_t> flags[] = {
+        {1, lt::peer_info::interesting},
+        {2, lt::peer_info::choked},
+        {4, lt::peer_info::remote_interested},
+        {8, lt::peer_info::remote_choked},
+        {16, lt::peer_info::snubbed},
+        {32, lt::peer_info::optimistic_unchoke},
+    };
+    peers[i].flags = 0;
+    for (auto &[bit, flag] : flags)
+      if (p.flags & flag)
+        peers[i].flags |= bit;
+
+    int num_missing = 0, num_relevant = 0;
+    int num_pieces = min(have.size(), p.pieces.size());
+    for (int piece = 0; piece < num_pieces; piece++) {
+      if (have[lt::piece_index_t(piece)])
+        continue;
+      num_missing++;
+      if (p.pieces[lt::piece_index_t(piece)])
+        num_relevant++;
+    }
+    peers[i].relevance =
+        num_missing > 0 ? (float)num_relevant / num_missing : 0.0f;
 
     string ip = p.ip.address().to_string();
     char *ip_c = new char[ip.size() + 1];
@@ -398,6 +728,86 @@ void free_peers(Peer *peers, int num_peers) {
   delete[] peers;
 }
 
+bool torrent_disconnect_peer(int index, const char *ip, int port) {
+  try {
+    assert(index < state.torrents.size());
+
+    lt::tcp::endpoint endpoint(lt::make_address(ip), port);
+    state.torrents[index]->disconnector->request(endpoint);
+    return true;
+  } catch (...) {
+    return false;
+  }
+}
+
+bool torrent_connect_peer(int index, const char *ip, int port) {
+  try {
+    assert(index < state.torrents.size());
+
+    lt::tcp::endpoint endpoint(lt::make_address(ip), port);
+    state.torrents[index]->h.connect_peer(endpoint);
+    return true;
+  } catch (...) {
+    return false;
+  }
+}
+
+Tracker *get_trackers(int index, int *num_trackers) {
+  assert(index < state.torrents.size());
+  assert(num_trackers != nullptr);
+
+  auto &h = state.torrents[index]->h;
+  vector<lt::announce_entry> entries = h.trackers();
+  *num_trackers = entries.size();
+  Tracker *trackers = new Tracker[*num_trackers];
+  for (int i = 0; i < *num_trackers; i++) {
+    auto &entry = entries[i];
+    Tracker &tracker = trackers[i];
+    tracker.tier = entry.tier;
+    tracker.status = 0;
+    tracker.num_peers = 0;
+    tracker.num_seeds = 0;
+
+    string message;
+    for (auto &endpoint : entry.endpoints) {
+      for (auto &ih : endpoint.info_hashes) {
+        if (ih.updating)
+          tracker.status = 2;
+        else if (ih.is_working() && tracker.status != 2)
+          tracker.status = 1;
+        else if (ih.fails > 0 && tracker.status == 0)
+          tracker.status = 3;
+        tracker.num_peers = max(tracker.num_peers, ih.scrape_incomplete);
+        tracker.num_seeds = max(tracker.num_seeds, ih.scrape_complete);
+        if (message.empty())
+          message = ih.message.empty() && ih.last_error
+                        ? ih.last_error.message()
+                        : ih.message;
+      }
+    }
+
+    char *url = new char[entry.url.size() + 1];
+    copy(entry.url.begin(), entry.url.end(), url);
+    url[entry.url.size()] = '\0';
+    tracker.url = url;
+
+    char *message_c = new char[message.size() + 1];
+    copy(message.begin(), message.end(), message_c);
+    message_c[message.size()] = '\0';
+    tracker.message = message_c;
+  }
+
+  return trackers;
+}
+
+void free_trackers(Tracker *trackers, int num_trackers) {
+  for (int i = 0; i < num_trackers; i++) {
+    delete[] trackers[i].url;
+    delete[] trackers[i].message;
+  }
+  delete[] trackers;
+}
+
 File *get_files(int index, int *num_files) {
   // Files
   assert(index < state.torrents.size());
@@ -430,9 +840,199 @@ void free_files(File *files, int num_files) {
   delete[] files;
 }
 
+Event *pop_events(int *num_events) {
+  assert(num_events != nullptr);
+
+  *num_events = state.events.size();
+  Event *events = new Event[*num_events];
+  for (int i = 0; i < *num_events; i++) {
+    events[i].kind = state.events[i].first;
+    string &hash = state.events[i].second;
+    char *hash_c = new char[hash.size() + 1];
+    copy(hash.begin(), hash.end(), hash_c);
+    hash_c[hash.size()] = '\0';
+    events[i].hash = hash_c;
+  }
+  state.events.clear();
+
+  return events;
+}
+
+void free_events(Event *events, int num_events) {
+  for (int i = 0; i < num_events; i++)
+    delete[] events[i].hash;
+  delete[] events;
+}
+
+void set_session_limits(int download_limit, int upload_limit) {
+  lt::settings_pack sp;
+  sp.set_int(sp.download_rate_limit, max(download_limit, 0));
+  sp.set_int(sp.upload_rate_limit, max(upload_limit, 0));
+  state.ses->apply_settings(sp);
+}
+
+bool apply_ip_filter(const IpFilterRule *rules, int num_rules) {
+  try {
+    lt::ip_filter filter;
+    for (int i = 0; i < num_rules; i++)
+      filter.add_rule(lt::make_address(rules[i].first),
+                      lt::make_address(rules[i].last), lt::ip_filter::blocked);
+    state.ses->set_ip_filter(filter);
+    log_message(0, false,
+                "Applied IP filter of " + to_string(num_rules) + " ranges.");
+    return true;
+  } catch (...) {
+    return false;
+  }
+}
+
+long get_blocked_peer_count() { return state.blocked_peers; }
+
+bool set_proxy(const ProxyConfig *proxy) {
+  lt::settings_pack sp;
+  if (!set_proxy_settings(sp, proxy))
+    return false;
+  state.ses->apply_settings(sp);
+  return true;
+}
+
+bool set_network_interfaces(const char *listen_interfaces,
+                            const char *outgoing_interfaces) {
+  try {
+    lt::settings_pack sp;
+    sp.set_str(sp.listen_interfaces, listen_interfaces);
+    sp.set_str(sp.outgoing_interfaces, outgoing_interfaces);
+    state.ses->apply_settings(sp);
+    state.ses->reopen_network_sockets();
+    log_message(0, false,
+                string("Listening on ") + listen_interfaces + ".");
+    return true;
+  } catch (...) {
+    return false;
+  }
+}
+
+void session_pause() { state.ses->pause(); }
+
+void session_resume() { state.ses->resume(); }
+
+InterfaceAddress *get_interface_addresses(int *num_addresses) {
+  assert(num_addresses != nullptr);
+
+  vector<InterfaceAddress> addresses;
+  ifaddrs *ifap;
+  if (getifaddrs(&ifap) == 0) {
+    for (ifaddrs *ifa = ifap; ifa != nullptr; ifa = ifa->ifa_next) {
+      if (ifa->ifa_addr == nullptr)
+        continue;
+      int family = ifa->ifa_addr->sa_family;
+      const void *addr;
+      if (family == AF_INET)
+        addr = &((sockaddr_in *)ifa->ifa_addr)->sin_addr;
+      else if (family == AF_INET6)
+        addr = &((sockaddr_in6 *)ifa->ifa_addr)->sin6_addr;
+      else
+        continue;
+      char address[INET6_ADDRSTRLEN];
+      if (inet_ntop(family, addr, address, sizeof(address)) == nullptr)
+        continue;
+
+      char *name_c = new char[strlen(ifa->ifa_name) + 1];
+      strcpy(name_c, ifa->ifa_name);
+      char *address_c = new char[strlen(address) + 1];
+      strcpy(address_c, address);
+      addresses.push_back({name_c, address_c, bool(ifa->ifa_flags & IFF_UP)});
+    }
+    freeifaddrs(ifap);
+  }
+
+  *num_addresses = addresses.size();
+  InterfaceAddress *c_addresses = new InterfaceAddress[*num_addresses];
+  copy(addresses.begin(), addresses.end(), c_addresses);
+  return c_addresses;
+}
+
+void free_interface_addresses(InterfaceAddress *addresses, int num_addresses) {
+  for (int i = 0; i < num_addresses; i++) {
+    delete[] addresses[i].name;
+    delete[] addresses[i].address;
+  }
+  delete[] addresses;
+}
+
+void post_session_stats() { state.ses->post_session_stats(); }
+
+Counter *get_session_stats(int *num_counters) {
+  assert(num_counters != nullptr);
+
+  vector<lt::stats_metric> metrics = lt::session_stats_metrics();
+  if (state.stats.empty()) {
+    *num_counters = 0;
+    return new Counter[0];
+  }
+
+  *num_counters = metrics.size();
+  Counter *counters = new Counter[*num_counters];
+  for (int i = 0; i < *num_counters; i++) {
+    counters[i].name = metrics[i].name;
+    counters[i].value = state.stats[metrics[i].value_index];
+  }
+
+  return counters;
+}
+
+void free_session_stats(Counter *counters, int num_counters) {
+  delete[] counters;
+}
+
+void set_alert_mask(int categories) {
+  const pair<int, lt::alert_category_t> bits[] = {
+      {1, lt::alert_category::error},
+      {2, lt::alert_category::peer},
+      {4, lt::alert_category::port_mapping},
+      {8, lt::alert_category::storage},
+      {16, lt::alert_category::tracker},
+      {32, lt::alert_category::connect},
+      {64, lt::alert_category::status},
+      {128, lt::alert_category::ip_block},
+      {256, lt::alert_category::performance_warning},
+      {512, lt::alert_category::dht},
+  };
+  state.log_mask = {};
+  for (auto &[bit, category] : bits)
+    if (categories & bit)
+      state.log_mask |= category;
+
+  // Finished torrents (status), saved resume data (storage) and blocked peers
+  // (IP block) are handled whether logged or not.
+  lt::settings_pack sp;
+  sp.set_int(sp.alert_mask, state.log_mask | lt::alert_category::error |
+                                lt::alert_category::status |
+                                lt::alert_category::storage |
+                                lt::alert_category::ip_block);
+  state.ses->apply_settings(sp);
+}
+
+LogMessage *pop_log_messages(int *num_messages) {
+  assert(num_messages != nullptr);
+
+  *num_messages = state.logs.size();
+  LogMessage *messages = new LogMessage[*num_messages];
+  copy(state.logs.begin(), state.logs.end(), messages);
+  state.logs.clear();
+
+  return messages;
+}
+
+void free_log_messages(LogMessage *messages, int num_messages) {
+  for (int i = 0; i < num_messages; i++)
+    delete[] messages[i].message;
+  delete[] messages;
+}
+
 void destroy() {
   state.ses->pause();
-  printf("Session paused.\n");
+  log_message(0, false, "Session paused.");
   for (auto &torrent : state.torrents) {
     torrent->h.pause();
     try {
@@ -443,7 +1043,8 @@ void destroy() {
         state.pending_save_alerts++;
       }
     } catch (lt::system_error &e) {
-      printf("Failed to save resume data.\n");
+      log_message(2, false,
+                  string("Failed to save resume data: ") + e.what());
     }
     delete torrent;
   }
@@ -451,8 +1052,9 @@ void destroy() {
     handle_alerts();
     this_thread::sleep_for(chrono::milliseconds(100));
   }
-  printf("Done with saving.\n");
+  write_session_file();
+  log_message(0, false, "Done with saving.");
   state.ses->abort();
   delete state.ses;
-  printf("Deleted session.\n");
+  log_message(0, false, "Deleted session.");
 }
\ No newline at end of file
@@ -33,17 +33,46 @@ struct TorrentInfo {
   long total_ses_download;
   long total_ses_upload;
   long eta;
+  int num_files;
+  long added_time;
+  // Bytes per second, -1 when unlimited.
+  int download_limit, upload_limit;
+  // Empty unless the torrent stopped on an error.
+  const char *error;
 };
 
+// Connection type: 0 -> TCP, 1 -> uTP, 2 -> web seed.
+// Source bits: 1 -> tracker, 2 -> DHT, 4 -> PeX, 8 -> LSD, 16 -> resume data,
+// 32 -> incoming.
+// Flag bits: 1 -> interested, 2 -> choked, 4 -> remote interested,
+// 8 -> remote choked, 16 -> snubbed, 32 -> optimistic unchoke.
 struct Peer {
   const char *region;
   const char *ip_address;
+  int port;
   const char *client;
   float progress;
   long download_rate;
   long upload_rate;
+  int connection_type;
+  bool is_encrypted;
+  int source;
+  int flags;
+  long total_download;
+  long total_upload;
+  // Share of the pieces missing here which the peer has.
+  float relevance;
+  // Round trip time in milliseconds.
+  int rtt;
+};
+
+// Kind: 0 -> added, 1 -> finished.
+struct Event {
+  int kind;
+  const char *hash;
 };
 
+// Status: 0 -> not contacted, 1 -> working, 2 -> updating, 3 -> error.
 struct Tracker {
   int tier;
   const char *url;
@@ -53,25 +82,122 @@ struct Tracker {
   const char *message;
 };
 
+struct Counter {
+  const char *name;
+  long value;
+};
+
+// Severity: 0 -> info, 1 -> warning, 2 -> error.
+struct LogMessage {
+  int severity;
+  // Whether the message is of a libtorrent alert, not of the bridge.
+  bool is_alert;
+  const char *message;
+};
+
+// An address range, both ends included, IPv4 or IPv6 in text.
+struct IpFilterRule {
+  const char *first;
+  const char *last;
+};
+
+// Kind: 0 -> none, 1 -> SOCKS4, 2 -> SOCKS5, 3 -> HTTP. No authentication if
+// the username is empty.
+struct ProxyConfig {
+  int kind;
+  const char *host;
+  int port;
+  const char *username;
+  const char *password;
+  bool proxy_hostnames;
+  bool proxy_peer_connections;
+  bool proxy_tracker_connections;
+};
+
+// An address of a local network interface, IPv4 or IPv6 in text.
+struct InterfaceAddress {
+  const char *name;
+  const char *address;
+  bool is_up;
+};
+
 // Lifecycle
-void initiate(const char *resume_dir);
+// Restores the session state saved by `destroy` in the session file, if any.
+// Connections go through the proxy and the interfaces, as taken by
+// `set_network_interfaces`, from the start.
+void initiate(const char *resume_dir, const char *session_file,
+              const struct ProxyConfig *proxy, const char *listen_interfaces,
+              const char *outgoing_interfaces);
 void destroy();
 
 // Torrent management
-bool add_file(const char *file_path, const char *save_path);
-bool add_magnet_url(const char *url, const char *save_path);
+bool add_file(const char *file_path, const char *save_path, bool paused);
+bool add_magnet_url(const char *url, const char *save_path, bool paused);
 int get_count();
 void handle_alerts();
 struct TorrentInfo get_torrent_info(int index);
 bool torrent_pause(int index);
 bool torrent_resume(int index);
-bool torrent_remove(int index);
+bool torrent_remove(int index, bool delete_files);
 bool toggle_stream(int index);
+bool torrent_move_storage(int index, const char *save_path);
+bool torrent_recheck(int index);
+bool torrent_reannounce(int index);
+// Limits in bytes per second, 0 for unlimited.
+bool torrent_set_limits(int index, int download_limit, int upload_limit);
+// Direction: 0 -> top, 1 -> up, 2 -> down, 3 -> bottom.
+bool torrent_queue_move(int index, int direction);
+const char *get_torrent_hash(int index);
 bool change_file_priority(int, int, int);
 struct File *get_files(int index, int *num_files);
 void free_files(struct File *files, int num_files);
 struct Peer *get_peers(int, int *);
 void free_peers(struct Peer *, int);
+// Disconnects the peer within a second, false if the address is invalid.
+bool torrent_disconnect_peer(int index, const char *ip, int port);
+// Connects to the peer, false if the address is invalid.
+bool torrent_connect_peer(int index, const char *ip, int port);
+struct Tracker *get_trackers(int index, int *num_trackers);
+void free_trackers(struct Tracker *trackers, int num_trackers);
+
+// Events
+struct Event *pop_events(int *num_events);
+void free_events(struct Event *events, int num_events);
+
+// Session settings
+// Bytes per second, 0 for unlimited.
+void set_session_limits(int download_limit, int upload_limit);
+// Replaces the IP filter by one blocking the ranges, none to allow all.
+bool apply_ip_filter(const struct IpFilterRule *rules, int num_rules);
+// Connections refused by the IP filter since the session started.
+long get_blocked_peer_count();
+bool set_proxy(const struct ProxyConfig *proxy);
+// Both in libtorrent's format, e.g. "tun0:6881" and "tun0", empty outgoing
+// interfaces for any. Reopens the sockets.
+bool set_network_interfaces(const char *listen_interfaces,
+                            const char *outgoing_interfaces);
+// Stops and restarts all torrents without changing their own states.
+void session_pause();
+void session_resume();
+
+// Network interfaces
+struct InterfaceAddress *get_interface_addresses(int *num_addresses);
+void free_interface_addresses(struct InterfaceAddress *addresses,
+                              int num_addresses);
+
+// Session statistics
+void post_session_stats();
+// The counters of the last stats posted. The names are static, not freed.
+struct Counter *get_session_stats(int *num_counters);
+void free_session_stats(struct Counter *counters, int num_counters);
+
+// Logging
+// Categories of the alerts logged, bits: 1 -> error, 2 -> peer,
+// 4 -> port mapping, 8 -> storage, 16 -> tracker, 32 -> connect,
+// 64 -> status, 128 -> IP block, 256 -> performance warning, 512 -> DHT.
+void set_alert_mask(int categories);
+struct LogMessage *pop_log_messages(int *num_messages);
+void free_log_messages(struct LogMessage *messages, int num_messages);
 
 // Utilities
 const char *libtorrent_version();
@@ -0,0 +1,163 @@
+use std::{fs, path::Path};
+
+use crate::{controllers::control, models::control::ControlRequest};
+
+/// The request failed, e.g. on an unknown hash.
+pub const EXIT_FAILED: i32 = 1;
+pub const EXIT_USAGE: i32 = 2;
+/// No instance is listening on the control socket.
+pub const EXIT_NOT_RUNNING: i32 = 3;
+
+const COMMANDS: [&str; 7] = [
+    "add",
+    "list",
+    "info",
+    "pause",
+    "resume",
+    "remove",
+    "set-limit",
+];
+
+const USAGE: &str = "Usage:
+  torrenter [--headless]
+  torrenter add <file|magnet> [--category <name>] [--save-path <dir>] [--paused]
+  torrenter list
+  torrenter info <hash>
+  torrenter pause <hash>
+  torrenter resume <hash>
+  torrenter remove <hash> [--delete-files]
+  torrenter set-limit [<hash>] [--download <KB/s>] [--upload <KB/s>]
+
+Limits apply to the torrent, or globally without a hash. 0 is unlimited.";
+
+/// Whether the arguments, without the program name, start with a subcommand.
+pub fn is_command(args: &[String]) -> bool {
+    args.first()
+        .is_some_and(|arg| COMMANDS.contains(&arg.as_str()))
+}
+
+fn parse_limit(value: Option<&String>) -> Result<i32, String> {
+    let value = value.ok_or("Missing limit")?;
+    value
+        .parse::<i32>()
+        .ok()
+        .and_then(|kbs| kbs.checked_mul(1000))
+        .filter(|limit| *limit >= 0)
+        .ok_or_else(|| format!("Invalid limit {}", value))
+}
+
+/// Parses a subcommand and its arguments into the request for the running
+/// instance.
+pub fn parse(args: &[String]) -> Result<ControlRequest, String> {
+    let (command, rest) = args.split_first().ok_or("Missing command")?;
+    let mut positional = vec![];
+    let mut category = "".to_owned();
+    let mut save_path = "".to_owned();
+    let mut paused = false;
+    let mut delete_files = false;
+    let mut download = None;
+    let mut upload = None;
+    let mut rest = rest.iter();
+    while let Some(arg) = rest.next() {
+        match arg.as_str() {
+            "--category" => category = rest.next().ok_or("Missing category")?.clone(),
+            "--save-path" => save_path = rest.next().ok_or("Missing save path")?.clone(),
+            "--paused" => paused = true,
+            "--delete-files" => delete_files = true,
+            "--download" => download = Some(parse_limit(rest.next())?),
+            "--upload" => upload = Some(parse_limit(rest.next())?),
+            option if option.starts_with("--") => {
+                return Err(format!("Unknown option {}", option));
+            }
+            _ => positional.push(arg.clone()),
+        }
+    }
+
+    let num_args = match command.as_str() {
+        "list" => 0,
+        "set-limit" => positional.len().min(1),
+        _ => 1,
+    };
+    if positional.len() != num_args {
+        return Err(format!("Wrong number of arguments for {}", command));
+    }
+    let mut positional = positional.into_iter();
+    let mut arg = || positional.next().unwrap_or_default();
+    let request = match command.as_str() {
+        "add" => ControlRequest::Add {
+            source: arg(),
+            save_path,
+            category,
+            paused,
+        },
+        "list" => ControlRequest::List,
+        "info" => ControlRequest::Info { hash: arg() },
+        "pause" => ControlRequest::Pause { hash: arg() },
+        "resume" => ControlRequest::Resume { hash: arg() },
+        "remove" => ControlRequest::Remove {
+            hash: arg(),
+            delete_files,
+        },
+        "set-limit" => {
+            if download.is_none() && upload.is_none() {
+                return Err("Missing --download or --upload".to_owned());
+            }
+            let hash = arg();
+            ControlRequest::SetLimit {
+                hash: (!hash.is_empty()).then_some(hash),
+                download,
+                upload,
+            }
+        }
+        _ => return Err(format!("Unknown command {}", command)),
+    };
+    Ok(request)
+}
+
+/// Runs the subcommand against the running instance, printing its JSON
+/// response. Returns the exit status.
+pub fn run(args: &[String], data_dir: &Path) -> i32 {
+    let mut request = match parse(args) {
+        Ok(request) => request,
+        Err(e) => {
+            eprintln!("{}\n\n{}", e, USAGE);
+            return EXIT_USAGE;
+        }
+    };
+    // The running instance doesn't share the working directory
+    if let ControlRequest::Add {
+        source, save_path, ..
+    } = &mut request
+    {
+        if !save_path.is_empty() {
+            if let Ok(path) = std::path::absolute(&save_path) {
+                *save_path = path.to_string_lossy().to_string();
+            }
+        }
+        if !source.starts_with("magnet:") {
+            match fs::canonicalize(&source) {
+                Ok(path) => *source = path.to_string_lossy().to_string(),
+                Err(e) => {
+                    eprintln!("{}: {}", source, e);
+                    return EXIT_USAGE;
+                }
+            }
+        }
+    }
+
+    match control::request(data_dir, &request) {
+        Ok(response) => {
+            let json = serde_json::to_string_pretty(&response).expect("Failed to serialize");
+            println!("{}", json);
+            if response.ok {
+                0
+            } else {
+                EXIT_FAILED
+            }
+        }
+        Err(e) => {
+            eprintln!("No running instance of Torrenter: {}", e);
+            EXIT_NOT_RUNNING
+        }
+    }
+}
@@ -9,7 +9,11 @@ use crate::{
     toasts,
 };
 
-pub fn handle_file_drop(dropped_files: &[DroppedFile], channel_tx: &Sender<Message>) {
+pub fn handle_file_drop(
+    dropped_files: &[DroppedFile],
+    category: &str,
+    channel_tx: &Sender<Message>,
+) {
     if let Some(DroppedFile {
         path: Some(file_path),
         mime: _,
@@ -23,12 +27,16 @@ pub fn handle_file_drop(dropped_files: &[DroppedFile], channel_tx: &Sender<Messa
             .expect("Failed to convert path to str")
             .to_string();
         channel_tx
-            .send(Message::AddTorrent(file_path, AddTorrentKind::File))
+            .send(Message::AddTorrent(
+                file_path,
+                AddTorrentKind::File,
+                category.to_owned(),
+            ))
             .unwrap();
     }
 }
 
-pub fn handle_file_add(toasts: &mut Toasts, channel_tx: &Sender<Message>) {
+pub fn handle_file_add(toasts: &mut Toasts, category: &str, channel_tx: &Sender<Message>) {
     let file_path = FileDialog::new()
         .add_filter("torrent", &["torrent"])
         .pick_file();
@@ -42,6 +50,7 @@ pub fn handle_file_add(toasts: &mut Toasts, channel_tx: &Sender<Message>) {
                             .expect("Failed to convert path to str")
                             .to_string(),
                         AddTorrentKind::File,
+                        category.to_owned(),
                     ))
                     .unwrap();
             }
@@ -52,13 +61,21 @@ pub fn handle_file_add(toasts: &mut Toasts, channel_tx: &Sender<Message>) {
     }
 }
 
-pub fn handle_magnet_pastes(ctx: &Context, channel_tx: &Sender<Message>) {
+pub fn handle_magnet_pastes(ctx: &Context, category: &str, channel_tx: &Sender<Message>) {
+    // Pastes into text fields are not meant for us
+    if ctx.memory(|m| m.focused().is_some()) {
+        return;
+    }
     ctx.input(|r| {
         for event in &r.events {
             if let Event::Paste(text) = event {
                 let magnet_url = text.trim().to_string();
                 channel_tx
-                    .send(Message::AddTorrent(magnet_url, AddTorrentKind::MagnetUrl))
+                    .send(Message::AddTorrent(
+                        magnet_url,
+                        AddTorrentKind::MagnetUrl,
+                        category.to_owned(),
+                    ))
                     .unwrap();
             }
         }
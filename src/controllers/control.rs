@@ -0,0 +1,87 @@
+use std::{
+    fs,
+    io::{self, BufRead, BufReader, Write},
+    os::unix::net::{UnixListener, UnixStream},
+    path::{Path, PathBuf},
+    sync::mpsc::{self, Sender},
+    thread,
+};
+
+use crate::models::{
+    control::{ControlRequest, ControlResponse},
+    message::Message,
+};
+
+pub const CONTROL_SOCKET: &str = "control.sock";
+
+pub fn socket_path(data_dir: &Path) -> PathBuf {
+    data_dir.join(CONTROL_SOCKET)
+}
+
+/// Sends a request to the running instance and waits for its response.
+pub fn request(data_dir: &Path, request: &ControlRequest) -> io::Result<ControlResponse> {
+    let mut stream = UnixStream::connect(socket_path(data_dir))?;
+    let json = serde_json::to_string(request).expect("Failed to serialize request");
+    writeln!(stream, "{}", json)?;
+    let mut line = String::new();
+    BufReader::new(stream).read_line(&mut line)?;
+    serde_json::from_str(&line).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+}
+
+/// Answers the requests on one connection, a JSON line for each line.
+fn handle(stream: UnixStream, tx: Sender<Message>) -> io::Result<()> {
+    let mut writer = stream.try_clone()?;
+    for line in BufReader::new(stream).lines() {
+        let line = line?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        let response = match serde_json::from_str::<ControlRequest>(&line) {
+            Ok(request) => {
+                let (reply_tx, reply_rx) = mpsc::channel();
+                if tx.send(Message::Control(request, reply_tx)).is_err() {
+                    break;
+                }
+                reply_rx
+                    .recv()
+                    .unwrap_or_else(|_| ControlResponse::failure("Shutting down"))
+            }
+            Err(e) => ControlResponse::failure(format!("Invalid request: {}", e)),
+        };
+        let json = serde_json::to_string(&response).expect("Failed to serialize response");
+        writeln!(writer, "{}", json)?;
+    }
+    Ok(())
+}
+
+/// Listens on the control socket in the data dir, passing the requests on to
+/// the message controller. Fails if another instance is listening.
+pub fn serve(data_dir: &Path, tx: Sender<Message>) -> io::Result<()> {
+    let path = socket_path(data_dir);
+    if UnixStream::connect(&path).is_ok() {
+        return Err(io::Error::new(
+            io::ErrorKind::AddrInUse,
+            "Another instance is running",
+        ));
+    }
+    // Left behind by an instance which didn't exit cleanly
+    let _ = fs::remove_file(&path);
+    let listener = UnixListener::bind(&path)?;
+
+    thread::spawn(move || {
+        for stream in listener.incoming() {
+            match stream {
+                Ok(stream) => {
+                    let tx = tx.clone();
+                    thread::spawn(move || {
+                        if let Err(e) = handle(stream, tx) {
+                            log::error!("Control connection failed: {}", e);
+                        }
+                    });
+                }
+                Err(e) => log::error!("Failed to accept control connection: {}", e),
+            }
+        }
+    });
+    Ok(())
+}
@@ -0,0 +1,283 @@
+use std::{
+    fs::OpenOptions,
+    io::{self, Read, Write},
+    path::Path,
+    process::{Command, ExitStatus, Stdio},
+    thread::{self, JoinHandle},
+    time::{Duration, Instant, SystemTime, UNIX_EPOCH},
+};
+
+use crate::models::{settings::HookSettings, torrent::Torrent};
+
+pub const HOOKS_LOG_FILE: &str = "hooks.log";
+
+/// Values substituted for the placeholders of a hook command.
+pub struct HookContext {
+    pub name: String,
+    pub hash: String,
+    pub save_path: String,
+    pub content_path: String,
+    pub num_files: i32,
+    pub total_size: i64,
+    pub category: String,
+}
+
+impl HookContext {
+    pub fn from_torrent(torrent: &Torrent) -> Self {
+        let content_path = Path::new(&torrent.save_path)
+            .join(&torrent.name)
+            .to_str()
+            .expect("Failed to convert path to str")
+            .to_string();
+        Self {
+            name: torrent.name.clone(),
+            hash: torrent.hash.clone(),
+            save_path: torrent.save_path.clone(),
+            content_path,
+            num_files: torrent.num_files,
+            total_size: torrent.total_size,
+            category: torrent.category.clone(),
+        }
+    }
+}
+
+pub struct HookOutput {
+    /// Exit status of the program, `None` if it was killed after timing out.
+    pub status: Option<ExitStatus>,
+    pub stdout: String,
+    pub stderr: String,
+}
+
+/// The placeholders, in the order of the positional parameters the shell
+/// gets their values as.
+const PLACEHOLDERS: [char; 7] = ['N', 'I', 'D', 'F', 'C', 'Z', 'L'];
+
+impl HookContext {
+    fn value(&self, placeholder: char) -> Option<String> {
+        match placeholder {
+            'N' => Some(self.name.clone()),
+            'I' => Some(self.hash.clone()),
+            'D' => Some(self.save_path.clone()),
+            'F' => Some(self.content_path.clone()),
+            'C' => Some(self.num_files.to_string()),
+            'Z' => Some(self.total_size.to_string()),
+            'L' => Some(self.category.clone()),
+            _ => None,
+        }
+    }
+}
+
+fn replace(arg: &str, value: impl Fn(char) -> Option<String>) -> String {
+    let mut expanded = String::new();
+    let mut chars = arg.chars();
+    while let Some(c) = chars.next() {
+        if c != '%' {
+            expanded.push(c);
+            continue;
+        }
+        match chars.next() {
+            Some('%') => expanded.push('%'),
+            Some(other) => match value(other) {
+                Some(value) => expanded.push_str(&value),
+                None => {
+                    expanded.push('%');
+                    expanded.push(other);
+                }
+            },
+            None => expanded.push('%'),
+        }
+    }
+    expanded
+}
+
+/// Replaces the placeholders in `arg`:
+/// %N name, %I hash, %D save path, %F content path, %C file count, %Z size,
+/// %L category and %% for a literal percent sign.
+pub fn expand(arg: &str, ctx: &HookContext) -> String {
+    replace(arg, |placeholder| ctx.value(placeholder))
+}
+
+/// The name of the variable holding the value of a placeholder for `cmd`.
+fn variable(index: usize) -> String {
+    format!("TORRENTER_HOOK_{}", index + 1)
+}
+
+/// Replaces the placeholders in a shell command with quoted references to
+/// the values, passed separately so that a torrent name can't run commands.
+pub fn expand_for_shell(command: &str) -> String {
+    replace(command, |placeholder| {
+        let index = PLACEHOLDERS.iter().position(|p| *p == placeholder)?;
+        // Delayed expansion happens after cmd has parsed the command
+        Some(if cfg!(windows) {
+            format!("\"!{}!\"", variable(index))
+        } else {
+            format!("\"${}\"", index + 1)
+        })
+    })
+}
+
+/// Splits a command line into arguments on whitespace. Single or double quotes
+/// group words containing whitespace into one argument.
+pub fn split_args(command: &str) -> Vec<String> {
+    let mut args = vec![];
+    let mut arg = String::new();
+    let mut in_arg = false;
+    let mut quote: Option<char> = None;
+    for c in command.chars() {
+        match quote {
+            Some(q) if c == q => quote = None,
+            Some(_) => arg.push(c),
+            None if c == '"' || c == '\'' => {
+                quote = Some(c);
+                in_arg = true;
+            }
+            None if c.is_whitespace() => {
+                if in_arg {
+                    args.push(std::mem::take(&mut arg));
+                    in_arg = false;
+                }
+            }
+            None => {
+                arg.push(c);
+                in_arg = true;
+            }
+        }
+    }
+    if in_arg {
+        args.push(arg);
+    }
+    args
+}
+
+fn read_pipe<R>(pipe: Option<R>) -> JoinHandle<String>
+where
+    R: Read + Send + 'static,
+{
+    thread::spawn(move || {
+        let mut buf = vec![];
+        if let Some(mut pipe) = pipe {
+            let _ = pipe.read_to_end(&mut buf);
+        }
+        String::from_utf8_lossy(&buf).to_string()
+    })
+}
+
+/// Runs the hook command, killing it once `timeout` has passed. Without
+/// `use_shell` the program is executed directly, with the placeholders
+/// expanded separately in every argument. Through the shell, they refer to
+/// the values as parameters instead.
+pub fn run(
+    command: &str,
+    ctx: &HookContext,
+    use_shell: bool,
+    timeout: Duration,
+) -> io::Result<HookOutput> {
+    let mut cmd = if use_shell {
+        let command = expand_for_shell(command);
+        let values = PLACEHOLDERS.map(|p| ctx.value(p).unwrap_or_default());
+        if cfg!(windows) {
+            let mut cmd = Command::new("cmd");
+            cmd.args(["/V:ON", "/C", &command]);
+            for (index, value) in values.iter().enumerate() {
+                cmd.env(variable(index), value);
+            }
+            cmd
+        } else {
+            let mut cmd = Command::new("sh");
+            cmd.args(["-c", &command, "sh"]).args(values);
+            cmd
+        }
+    } else {
+        let args: Vec<String> = split_args(command)
+            .iter()
+            .map(|arg| expand(arg, ctx))
+            .collect();
+        let (program, args) = args
+            .split_first()
+            .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "Empty command"))?;
+        let mut cmd = Command::new(program);
+        cmd.args(args);
+        cmd
+    };
+    let mut child = cmd
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()?;
+    let stdout = read_pipe(child.stdout.take());
+    let stderr = read_pipe(child.stderr.take());
+
+    let start = Instant::now();
+    loop {
+        if let Some(status) = child.try_wait()? {
+            return Ok(HookOutput {
+                status: Some(status),
+                stdout: stdout.join().unwrap_or_default(),
+                stderr: stderr.join().unwrap_or_default(),
+            });
+        }
+        if start.elapsed() >= timeout {
+            let _ = child.kill();
+            let _ = child.wait();
+            // Processes spawned by the hook may still hold the pipes open, so
+            // the readers are left behind rather than joined.
+            return Ok(HookOutput {
+                status: None,
+                stdout: "".to_owned(),
+                stderr: "".to_owned(),
+            });
+        }
+        thread::sleep(Duration::from_millis(50));
+    }
+}
+
+fn write_log(log_path: &Path, event: &str, command: &str, res: &io::Result<HookOutput>) {
+    let timestamp = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0);
+    let mut entry = format!("[{}] {}: {}\n", timestamp, event, command);
+    match res {
+        Ok(output) => {
+            match output.status {
+                Some(status) => entry.push_str(&format!("exit status: {}\n", status)),
+                None => entry.push_str("timed out, killed\n"),
+            }
+            if !output.stdout.is_empty() {
+                entry.push_str(&format!("stdout:\n{}\n", output.stdout.trim_end()));
+            }
+            if !output.stderr.is_empty() {
+                entry.push_str(&format!("stderr:\n{}\n", output.stderr.trim_end()));
+            }
+        }
+        Err(e) => entry.push_str(&format!("failed to run: {}\n", e)),
+    }
+
+    let file = OpenOptions::new().create(true).append(true).open(log_path);
+    match file {
+        Ok(mut file) => {
+            if file.write_all(entry.as_bytes()).is_err() {
+                log::error!("Failed to write hooks log.");
+            }
+        }
+        Err(_) => log::error!("Failed to open hooks log."),
+    }
+}
+
+/// Runs `command` for `event` and appends the outcome to the log at `log_path`.
+pub fn execute(
+    command: &str,
+    event: &str,
+    ctx: &HookContext,
+    settings: &HookSettings,
+    log_path: &Path,
+) -> io::Result<HookOutput> {
+    let res = run(
+        command,
+        ctx,
+        settings.use_shell,
+        Duration::from_secs(settings.timeout_secs),
+    );
+    write_log(log_path, event, command, &res);
+    res
+}
@@ -0,0 +1,70 @@
+use std::{fs, io::Read, time::Duration};
+
+use flate2::read::MultiGzDecoder;
+
+use crate::models::ip_filter::{self, IpRange};
+
+const HTTP_TIMEOUT: Duration = Duration::from_secs(120);
+const MAX_LIST_SIZE: u64 = 512 * 1024 * 1024;
+const GZIP_MAGIC: [u8; 2] = [0x1f, 0x8b];
+
+fn fetch(url: &str) -> Result<Vec<u8>, String> {
+    let res = ureq::get(url)
+        .timeout(HTTP_TIMEOUT)
+        .call()
+        .map_err(|e| e.to_string())?;
+    let mut buf = vec![];
+    res.into_reader()
+        .take(MAX_LIST_SIZE)
+        .read_to_end(&mut buf)
+        .map_err(|e| e.to_string())?;
+    Ok(buf)
+}
+
+/// The data, decompressed if gzipped.
+pub fn decompress(data: Vec<u8>) -> Result<Vec<u8>, String> {
+    if !data.starts_with(&GZIP_MAGIC) {
+        return Ok(data);
+    }
+    let mut buf = vec![];
+    MultiGzDecoder::new(data.as_slice())
+        .take(MAX_LIST_SIZE)
+        .read_to_end(&mut buf)
+        .map_err(|e| e.to_string())?;
+    Ok(buf)
+}
+
+/// Reads the list at `source`, a URL or a path.
+pub fn load(source: &str) -> Result<Vec<IpRange>, String> {
+    let data = if source.starts_with("http://") || source.starts_with("https://") {
+        fetch(source)?
+    } else {
+        fs::read(source).map_err(|e| e.to_string())?
+    };
+    let text = decompress(data)?;
+    let ranges = ip_filter::parse_list(&String::from_utf8_lossy(&text));
+    if ranges.is_empty() {
+        return Err("No IP ranges found".to_owned());
+    }
+    Ok(ranges)
+}
+
+/// The ranges of the lists and the banned addresses, merged.
+pub fn with_banned(lists: &[IpRange], banned_ips: &[String]) -> Vec<IpRange> {
+    let banned = banned_ips.iter().filter_map(|ip| ip_filter::parse_line(ip));
+    ip_filter::merge(lists.iter().copied().chain(banned).collect())
+}
+
+/// Merges the ranges of the sources, skipping those failing to load, whose
+/// errors are returned.
+pub fn load_all(sources: &[String]) -> (Vec<IpRange>, Vec<String>) {
+    let mut ranges = vec![];
+    let mut errors = vec![];
+    for source in sources.iter().filter(|s| !s.trim().is_empty()) {
+        match load(source.trim()) {
+            Ok(r) => ranges.extend(r),
+            Err(e) => errors.push(format!("{}: {}", source, e)),
+        }
+    }
+    (ip_filter::merge(ranges), errors)
+}
@@ -0,0 +1,140 @@
+use std::{
+    fs::{self, File, OpenOptions},
+    io::{self, Write},
+    path::{Path, PathBuf},
+    sync::{Arc, Mutex},
+};
+
+use log::{LevelFilter, Metadata, Record};
+
+use crate::models::log::{Log, LogEntry, LogLevel, LogSource};
+
+pub const LOG_DIR: &str = "logs";
+const LOG_FILE: &str = "torrenter.log";
+const MAX_FILE_SIZE: u64 = 1024 * 1024;
+const MAX_FILES: usize = 5;
+
+/// Appends lines to `torrenter.log`. Past the size limit the file is renamed
+/// to `torrenter.log.1`, shifting the older ones up and deleting the last.
+pub struct RotatingFile {
+    dir: PathBuf,
+    max_size: u64,
+    max_files: usize,
+    file: Option<File>,
+    size: u64,
+}
+
+impl RotatingFile {
+    pub fn new(dir: &Path, max_size: u64, max_files: usize) -> Self {
+        Self {
+            dir: dir.to_path_buf(),
+            max_size,
+            max_files: max_files.max(1),
+            file: None,
+            size: 0,
+        }
+    }
+
+    pub fn path(&self, generation: usize) -> PathBuf {
+        match generation {
+            0 => self.dir.join(LOG_FILE),
+            n => self.dir.join(format!("{}.{}", LOG_FILE, n)),
+        }
+    }
+
+    fn rotate(&mut self) -> io::Result<()> {
+        self.file = None;
+        let _ = fs::remove_file(self.path(self.max_files - 1));
+        for generation in (0..self.max_files - 1).rev() {
+            let path = self.path(generation);
+            if path.exists() {
+                fs::rename(path, self.path(generation + 1))?;
+            }
+        }
+        self.size = 0;
+        Ok(())
+    }
+
+    pub fn write_line(&mut self, line: &str) -> io::Result<()> {
+        let len = line.len() as u64 + 1;
+        if self.file.is_none() {
+            fs::create_dir_all(&self.dir)?;
+            let file = OpenOptions::new()
+                .create(true)
+                .append(true)
+                .open(self.path(0))?;
+            self.size = file.metadata()?.len();
+            self.file = Some(file);
+        }
+        if self.size > 0 && self.size + len > self.max_size {
+            self.rotate()?;
+            return self.write_line(line);
+        }
+        let file = self.file.as_mut().expect("Log file is open");
+        writeln!(file, "{}", line)?;
+        self.size += len;
+        Ok(())
+    }
+}
+
+/// Keeps the records of the app and libtorrent in the in-memory log and the
+/// log files, passing all records on to `env_logger` for stderr.
+pub struct Logger {
+    log: Arc<Mutex<Log>>,
+    file: Mutex<RotatingFile>,
+    stderr: env_logger::Logger,
+}
+
+impl Logger {
+    fn is_kept(metadata: &Metadata) -> bool {
+        metadata.level() <= log::Level::Debug && LogSource::from_target(metadata.target()).is_some()
+    }
+}
+
+impl log::Log for Logger {
+    fn enabled(&self, metadata: &Metadata) -> bool {
+        Self::is_kept(metadata) || self.stderr.enabled(metadata)
+    }
+
+    fn log(&self, record: &Record) {
+        if self.stderr.matches(record) {
+            self.stderr.log(record);
+        }
+        if !Self::is_kept(record.metadata()) {
+            return;
+        }
+        let source = LogSource::from_target(record.target()).expect("Record is kept");
+        let entry = LogEntry::new(record.level().into(), source, record.args().to_string());
+        // Failures can't be logged from here, and the in-memory log remains.
+        let _ = self.file.lock().unwrap().write_line(&entry.to_string());
+        self.log.lock().unwrap().push(entry);
+    }
+
+    fn flush(&self) {
+        self.stderr.flush();
+    }
+}
+
+/// Installs the logger, writing the log files into `data_dir/logs`.
+pub fn init(data_dir: &Path, log: Arc<Mutex<Log>>) {
+    let stderr = env_logger::Builder::from_default_env().build();
+    let max_level = stderr.filter().max(LevelFilter::Debug);
+    let logger = Logger {
+        log,
+        file: Mutex::new(RotatingFile::new(
+            &data_dir.join(LOG_DIR),
+            MAX_FILE_SIZE,
+            MAX_FILES,
+        )),
+        stderr,
+    };
+    log::set_boxed_logger(Box::new(logger)).expect("Failed to set the logger");
+    log::set_max_level(max_level);
+}
+
+/// Logs the messages of the bridge under their source's target.
+pub fn forward_bridge_messages(messages: Vec<(LogLevel, LogSource, String)>) {
+    for (level, source, message) in messages {
+        log::log!(target: source.target(), log::Level::from(level), "{}", message);
+    }
+}
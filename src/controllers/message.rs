@@ -1,14 +1,51 @@
 use std::{
-    sync::{mpsc::Sender, Arc, Mutex},
-    time::Instant,
+    collections::{HashMap, HashSet},
+    fs,
+    net::SocketAddr,
+    path::{Path, PathBuf},
+    sync::{
+        mpsc::{Receiver, Sender},
+        Arc, Mutex,
+    },
+    thread::{self, JoinHandle},
+    time::{Duration, Instant},
 };
 
 use egui_toast::Toasts;
+use notify::Watcher;
 
-use super::torrent;
-use crate::models::{message::Message, torrent::Torrent};
+use super::{
+    hook::{self, HookContext, HOOKS_LOG_FILE},
+    ip_filter,
+    log::forward_bridge_messages,
+    proxy, rss, torrent, watcher,
+    web::{self, WebContext, WebServer},
+};
+use crate::{
+    models::{
+        category::Category,
+        control::{ControlRequest, ControlResponse, TorrentSummary},
+        event::TorrentEvent,
+        feed::{Feed, FeedItem},
+        history::SpeedHistories,
+        ip_filter::{IpFilterStatus, IpRange},
+        log::{AlertCategory, Log},
+        message::{AddTorrentKind, BatchAction, Message},
+        metadata::TorrentMetadata,
+        network::{self, NetworkStatus},
+        settings::{RssRule, Settings},
+        stats::SessionStats,
+        torrent::{Torrent, TorrentState},
+    },
+    toasts,
+};
 include!("../../bindings.rs");
 
+/// How often the trackers of the torrents not selected are refreshed.
+const TRACKERS_REFRESH_INTERVAL: Duration = Duration::from_secs(5);
+/// How often the watchdog checks the bound interface.
+const NETWORK_CHECK_INTERVAL: Duration = Duration::from_secs(2);
+
 pub struct MessageController {
     pub tx: Sender<Message>,
     pub torrents: Arc<Mutex<Vec<Torrent>>>,
@@ -16,30 +53,258 @@ pub struct MessageController {
     pub can_exit: Arc<Mutex<bool>>,
     pub sel_torrent: Arc<Mutex<Option<usize>>>,
     pub toasts: Arc<Mutex<Toasts>>,
+    pub settings: Arc<Mutex<Settings>>,
+    pub data_dir: PathBuf,
+    pub watchers: Vec<Box<dyn Watcher + Send>>,
+    pub feeds: Arc<Mutex<Vec<Feed>>>,
+    pub rss_history: HashSet<String>,
+    /// GUIDs of the feed items being downloaded.
+    pub rss_downloads: HashSet<String>,
+    pub last_rss_refresh: Option<Instant>,
+    pub categories: Arc<Mutex<Vec<Category>>>,
+    pub metadata: HashMap<String, TorrentMetadata>,
+    pub history: Arc<Mutex<SpeedHistories>>,
+    pub session_stats: Arc<Mutex<SessionStats>>,
+    pub last_stats_fetch: Option<Instant>,
+    pub last_trackers_refresh: Option<Instant>,
+    pub log: Arc<Mutex<Log>>,
+    pub web_server: Option<WebServer>,
+    pub ip_filter: Arc<Mutex<IpFilterStatus>>,
+    pub last_ip_filter_refresh: Option<Instant>,
+    /// Merged ranges of the IP filter lists, without the banned peers.
+    pub ip_filter_lists: Vec<IpRange>,
+    pub network: Arc<Mutex<NetworkStatus>>,
+    pub last_network_check: Option<Instant>,
 }
 
 impl MessageController {
+    /// Sets up the state shared with the views, for the session started by
+    /// `initiate`.
+    pub fn new(
+        tx: Sender<Message>,
+        data_dir: PathBuf,
+        toasts: Toasts,
+        log: Arc<Mutex<Log>>,
+    ) -> Self {
+        let torrents = {
+            let torrents_count = unsafe { get_count() };
+            let mut torrents = Vec::new();
+            for _ in 0..torrents_count {
+                torrents.push(Torrent::new(
+                    "".to_owned(),
+                    dirs::download_dir()
+                        .expect("Failed to get downloads dir.")
+                        .to_str()
+                        .to_owned()
+                        .expect("Failed to get downloads dir.")
+                        .to_owned(),
+                ));
+            }
+            torrents
+        };
+
+        Self {
+            tx,
+            torrents: Arc::new(Mutex::new(torrents)),
+            last_refresh: Box::new(Instant::now().checked_sub(Duration::from_secs(1)).unwrap()),
+            can_exit: Arc::new(Mutex::new(false)),
+            sel_torrent: Arc::new(Mutex::new(None)),
+            toasts: Arc::new(Mutex::new(toasts)),
+            settings: Arc::new(Mutex::new(Settings::load(&data_dir))),
+            watchers: vec![],
+            feeds: Arc::new(Mutex::new(vec![])),
+            rss_history: rss::load_history(&data_dir),
+            rss_downloads: HashSet::new(),
+            last_rss_refresh: None,
+            categories: Arc::new(Mutex::new(Category::load_all(&data_dir))),
+            metadata: TorrentMetadata::load_all(&data_dir.join("resume_files")),
+            history: Arc::new(Mutex::new(SpeedHistories::default())),
+            session_stats: Arc::new(Mutex::new(SessionStats::default())),
+            last_stats_fetch: None,
+            last_trackers_refresh: None,
+            log,
+            web_server: None,
+            ip_filter: Arc::new(Mutex::new(IpFilterStatus::default())),
+            last_ip_filter_refresh: None,
+            ip_filter_lists: vec![],
+            network: Arc::new(Mutex::new(NetworkStatus::default())),
+            last_network_check: None,
+            data_dir,
+        }
+    }
+
+    /// Processes the messages in the background until the session is
+    /// destroyed.
+    pub fn spawn(mut self, rx: Receiver<Message>) -> JoinHandle<()> {
+        self.apply_network_settings();
+        self.start_watchers();
+        self.apply_log_settings();
+        self.apply_speed_limits();
+        self.apply_proxy_settings();
+        self.apply_web_settings();
+        if !self
+            .settings
+            .lock()
+            .unwrap()
+            .ip_filter
+            .banned_ips
+            .is_empty()
+        {
+            self.update_ip_filter();
+        }
+        let can_exit = self.can_exit.clone();
+        thread::spawn(move || loop {
+            let message = rx.recv().unwrap();
+            self.process(message);
+            if *can_exit.lock().unwrap() {
+                break;
+            }
+        })
+    }
+
     pub fn process(&mut self, message: Message) {
         match message {
             Message::Stop => {
-                println!("Stopping.");
+                log::info!("Stopping.");
+                self.web_server = None;
                 unsafe {
                     destroy();
                 };
+                forward_bridge_messages(torrent::fetch_log_messages());
                 *self.can_exit.lock().unwrap() = true;
             }
             Message::Refresh | Message::ForcedRefresh => {
                 let now = Instant::now();
                 let elapsed = now.duration_since(*self.last_refresh).as_secs_f32();
 
-                if elapsed >= 0.9 || message == Message::ForcedRefresh {
+                if elapsed >= 0.9 || matches!(message, Message::ForcedRefresh) {
                     unsafe { handle_alerts() }
                     torrent::refresh(self.torrents.clone());
+                    self.refresh_trackers();
+                    self.apply_metadata();
+                    self.enforce_share_limits();
+                    if elapsed >= 0.9 {
+                        let torrents = self.torrents.lock().unwrap();
+                        self.history.lock().unwrap().record(now, &torrents);
+                    }
                     self.last_refresh = Box::new(now);
+                    forward_bridge_messages(torrent::fetch_log_messages());
+                    for event in torrent::fetch_events() {
+                        self.run_hook(event);
+                    }
+
+                    let rss = self.settings.lock().unwrap().rss.clone();
+                    let rss_interval = Duration::from_secs(rss.refresh_interval_mins * 60);
+                    if !rss.feeds.is_empty()
+                        && self
+                            .last_rss_refresh
+                            .is_none_or(|t| t.elapsed() >= rss_interval)
+                    {
+                        self.refresh_feeds();
+                    }
+
+                    let ip_filter = self.settings.lock().unwrap().ip_filter.clone();
+                    let ip_filter_interval =
+                        Duration::from_secs(ip_filter.refresh_interval_hours * 3600);
+                    if ip_filter.enabled
+                        && self
+                            .last_ip_filter_refresh
+                            .is_none_or(|t| t.elapsed() >= ip_filter_interval)
+                    {
+                        self.refresh_ip_filter();
+                    }
+                    self.ip_filter.lock().unwrap().blocked_peers =
+                        torrent::fetch_blocked_peer_count();
+
+                    if self
+                        .last_network_check
+                        .is_none_or(|t| t.elapsed() >= NETWORK_CHECK_INTERVAL)
+                    {
+                        self.check_network();
+                    }
+                }
+            }
+            Message::RefreshFeeds => self.refresh_feeds(),
+            Message::RefreshIpFilter => self.refresh_ip_filter(),
+            Message::IpFilterLoaded(ranges, errors) => self.apply_ip_filter(ranges, errors),
+            Message::CheckProxy(settings) => {
+                let tx = self.tx.clone();
+                thread::spawn(move || {
+                    let (host, port) = proxy::CHECK_TARGET;
+                    let _ = tx.send(Message::ProxyChecked(proxy::check(&settings, host, port)));
+                });
+            }
+            Message::ProxyChecked(result) => {
+                let mut toasts = self.toasts.lock().unwrap();
+                match result {
+                    Ok(()) => toasts::success(&mut toasts, "Connected through the proxy."),
+                    Err(e) => {
+                        log::error!("Proxy check failed: {}", e);
+                        toasts::error(&mut toasts, format!("Proxy check failed: {}", e));
+                    }
+                }
+            }
+            Message::FeedsFetched(feeds) => {
+                let rules = self.settings.lock().unwrap().rss.rules.clone();
+                for (item, rule) in rss::select_items(&feeds, &rules, &self.rss_history) {
+                    self.add_feed_item(item, rule);
+                }
+                *self.feeds.lock().unwrap() = feeds;
+                self.tx.send(Message::ForcedRefresh).unwrap();
+            }
+            // Into the downloads directory, without a category, started
+            Message::DownloadFeedItem(item) => self.add_feed_item(item, RssRule::default()),
+            Message::FeedItemDownloaded(item, rule, result) => {
+                self.rss_downloads.remove(&item.guid);
+                match result.and_then(|buf| rss::save_torrent(&buf, &self.data_dir)) {
+                    Ok(path) => {
+                        let is_added = self
+                            .add(
+                                path.to_str()
+                                    .expect("Failed to convert path to str")
+                                    .to_string(),
+                                AddTorrentKind::File,
+                                &rule.save_path,
+                                &rule.category,
+                                rule.start_paused,
+                            )
+                            .is_some();
+                        let _ = fs::remove_file(path);
+                        if is_added {
+                            self.record_feed_item(item);
+                        }
+                    }
+                    Err(e) => {
+                        log::error!("Failed to download {}: {}", item.url, e);
+                        toasts::error(
+                            &mut self.toasts.lock().unwrap(),
+                            "Failed to download torrent.",
+                        );
+                    }
                 }
+                self.tx.send(Message::ForcedRefresh).unwrap();
+            }
+            Message::AddTorrent(path, kind, category) => {
+                self.add(path, kind, "", &category, false);
+                self.tx.send(Message::ForcedRefresh).unwrap();
             }
-            Message::AddTorrent(path, kind) => {
-                torrent::add_torrent(path, kind, self.toasts.clone());
+            Message::AddWatchedTorrent(path, folder) => {
+                // Duplicate notifications arrive after the file is consumed
+                if !Path::new(&path).exists() {
+                    return;
+                }
+                let is_added = self
+                    .add(
+                        path.clone(),
+                        AddTorrentKind::File,
+                        &folder.save_path,
+                        &folder.category,
+                        folder.start_paused,
+                    )
+                    .is_some();
+                if let Err(e) = watcher::finish(Path::new(&path), &folder, is_added) {
+                    log::error!("Failed to move away watched file {}: {}", path, e);
+                }
                 self.tx.send(Message::ForcedRefresh).unwrap();
             }
             Message::UpdateState(state, index) => {
@@ -47,7 +312,12 @@ impl MessageController {
                 self.tx.send(Message::ForcedRefresh).unwrap();
             }
             Message::RemoveTorrent(index) => {
-                torrent::remove(index, self.toasts.clone());
+                let hash = self.torrents.lock().unwrap()[index].hash.clone();
+                if torrent::remove(index, self.toasts.clone()) {
+                    self.metadata.remove(&hash);
+                    TorrentMetadata::remove(&self.resume_dir(), &hash);
+                    *self.sel_torrent.lock().unwrap() = None;
+                }
                 self.tx.send(Message::ForcedRefresh).unwrap();
             }
             Message::ToggleStreamMode(index) => {
@@ -58,6 +328,20 @@ impl MessageController {
                 torrent::set_file_priority(index, f_index, priority, self.toasts.clone());
                 self.tx.send(Message::ForcedRefresh).unwrap();
             }
+            Message::FetchSessionStats => {
+                // The stats are posted asynchronously, so what's read here
+                // was posted the previous time.
+                let now = Instant::now();
+                if self
+                    .last_stats_fetch
+                    .is_none_or(|last| now.duration_since(last) >= Duration::from_secs(1))
+                {
+                    let counters = torrent::fetch_session_stats();
+                    self.session_stats.lock().unwrap().update(now, counters);
+                    unsafe { post_session_stats() };
+                    self.last_stats_fetch = Some(now);
+                }
+            }
             Message::FetchFiles(index) => {
                 let torrents = self.torrents.clone();
                 torrent::fetch_files(index, torrents);
@@ -66,8 +350,797 @@ impl MessageController {
                 let torrents = self.torrents.clone();
                 torrent::fetch_peers(index, torrents);
             }
+            Message::DisconnectPeer(index, ip, port) => {
+                torrent::disconnect_peer(index, &ip, port, self.toasts.clone());
+            }
+            Message::BanPeer(ip) => self.ban_peer(ip),
+            Message::UnbanPeer(ip) => self.unban_peer(&ip),
+            Message::AddPeers(index, endpoints) => self.add_peers(index, endpoints),
             Message::OpenDir(dir) => open::that(&dir).expect("Failed to open directory"),
             Message::UpdateSelTorrent(new_sel) => *self.sel_torrent.lock().unwrap() = new_sel,
+            Message::UpdateSettings(mut settings) => {
+                // Bans are made and lifted outside of the settings window
+                settings.ip_filter.banned_ips =
+                    self.settings.lock().unwrap().ip_filter.banned_ips.clone();
+                {
+                    let mut toasts = self.toasts.lock().unwrap();
+                    if settings.save(&self.data_dir) {
+                        toasts::success(&mut toasts, "Saved settings.");
+                    } else {
+                        toasts::error(&mut toasts, "Failed to save settings.");
+                    }
+                }
+                let old_ip_filter = self.settings.lock().unwrap().ip_filter.clone();
+                let ip_filter = settings.ip_filter.clone();
+                *self.settings.lock().unwrap() = *settings;
+                if old_ip_filter.enabled != ip_filter.enabled
+                    || old_ip_filter.sources != ip_filter.sources
+                {
+                    self.refresh_ip_filter();
+                }
+                self.start_watchers();
+                self.apply_log_settings();
+                self.apply_speed_limits();
+                self.apply_proxy_settings();
+                self.apply_network_settings();
+                self.apply_web_settings();
+                self.last_rss_refresh = None;
+            }
+            Message::SaveCategory(old_name, category) => {
+                self.save_category(old_name, category);
+                self.tx.send(Message::ForcedRefresh).unwrap();
+            }
+            Message::RemoveCategory(name) => {
+                self.categories.lock().unwrap().retain(|c| c.name != name);
+                self.persist_categories();
+                let hashes = self.hashes_in_category(&name);
+                for hash in hashes {
+                    self.set_category(&hash, "");
+                }
+                self.tx.send(Message::ForcedRefresh).unwrap();
+            }
+            Message::SetCategory(index, name) => {
+                let hash = self.torrents.lock().unwrap()[index].hash.clone();
+                self.set_category(&hash, &name);
+                let save_path = self.category_save_path(&name);
+                if !save_path.is_empty() {
+                    torrent::move_storage(index, &save_path, self.toasts.clone());
+                }
+                self.tx.send(Message::ForcedRefresh).unwrap();
+            }
+            Message::Batch(indices, action) => {
+                self.batch(indices, action);
+                self.tx.send(Message::ForcedRefresh).unwrap();
+            }
+            Message::SetTags(index, tags) => {
+                let hash = self.torrents.lock().unwrap()[index].hash.clone();
+                self.update_metadata(&hash, |m| m.tags = tags);
+                self.tx.send(Message::ForcedRefresh).unwrap();
+            }
+            Message::Control(request, reply_tx) => {
+                let response = self.control(request);
+                let _ = reply_tx.send(response);
+                self.tx.send(Message::ForcedRefresh).unwrap();
+            }
         }
     }
+
+    fn control(&mut self, request: ControlRequest) -> ControlResponse {
+        // Indices must match the session, which earlier requests may have changed
+        torrent::refresh(self.torrents.clone());
+        self.apply_metadata();
+        let find = |hash: &str| {
+            self.torrents
+                .lock()
+                .unwrap()
+                .iter()
+                .position(|t| t.hash.eq_ignore_ascii_case(hash.trim()))
+        };
+
+        match request {
+            ControlRequest::List => {
+                let torrents = self.torrents.lock().unwrap();
+                let summaries: Vec<TorrentSummary> =
+                    torrents.iter().map(TorrentSummary::from).collect();
+                ControlResponse::success(serde_json::json!(summaries))
+            }
+            ControlRequest::Info { hash } => {
+                let Some(index) = find(&hash) else {
+                    return ControlResponse::failure("No such torrent");
+                };
+                let trackers: Vec<String> = torrent::fetch_trackers(index)
+                    .into_iter()
+                    .map(|t| t.url)
+                    .collect();
+                let torrents = self.torrents.lock().unwrap();
+                let t = &torrents[index];
+                let mut info = serde_json::json!(TorrentSummary::from(t));
+                info["comment"] = t.comment.clone().into();
+                info["num_files"] = t.num_files.into();
+                info["added_time"] = t.added_time.into();
+                info["total_download"] = t.total_download.into();
+                info["total_upload"] = t.total_upload.into();
+                info["download_limit"] = t.download_limit.into();
+                info["upload_limit"] = t.upload_limit.into();
+                info["trackers"] = trackers.into();
+                ControlResponse::success(info)
+            }
+            ControlRequest::Files { hash } => {
+                let Some(index) = find(&hash) else {
+                    return ControlResponse::failure("No such torrent");
+                };
+                torrent::fetch_files(index, self.torrents.clone());
+                let torrents = self.torrents.lock().unwrap();
+                ControlResponse::success(serde_json::json!(torrents[index].files))
+            }
+            ControlRequest::Peers { hash } => {
+                let Some(index) = find(&hash) else {
+                    return ControlResponse::failure("No such torrent");
+                };
+                torrent::fetch_peers(index, self.torrents.clone());
+                let torrents = self.torrents.lock().unwrap();
+                ControlResponse::success(serde_json::json!(torrents[index].peers))
+            }
+            ControlRequest::Trackers { hash } => {
+                let Some(index) = find(&hash) else {
+                    return ControlResponse::failure("No such torrent");
+                };
+                ControlResponse::success(serde_json::json!(torrent::fetch_trackers(index)))
+            }
+            ControlRequest::Session => {
+                let limits = self.settings.lock().unwrap().speed_limits.clone();
+                ControlResponse::success(serde_json::json!({
+                    "save_path": torrent::default_save_path(),
+                    "download_limit": limits.download,
+                    "upload_limit": limits.upload,
+                }))
+            }
+            ControlRequest::Categories => {
+                let categories = self.categories.lock().unwrap();
+                ControlResponse::success(serde_json::json!(*categories))
+            }
+            ControlRequest::SetFilePriority {
+                hash,
+                index: f_index,
+                priority,
+            } => {
+                let Some(index) = find(&hash) else {
+                    return ControlResponse::failure("No such torrent");
+                };
+                let num_files = self.torrents.lock().unwrap()[index].num_files;
+                if f_index >= num_files.max(0) as usize {
+                    return ControlResponse::failure("No such file");
+                }
+                if torrent::set_file_priority(index, f_index, priority, self.toasts.clone()) {
+                    ControlResponse::success(serde_json::Value::Null)
+                } else {
+                    ControlResponse::failure("Failed to change priority")
+                }
+            }
+            ControlRequest::Add {
+                source,
+                save_path,
+                category,
+                paused,
+            } => {
+                let kind = if source.starts_with("magnet:") {
+                    AddTorrentKind::MagnetUrl
+                } else if Path::new(&source).is_file() {
+                    AddTorrentKind::File
+                } else {
+                    return ControlResponse::failure("Not a magnet link or a file");
+                };
+                match self.add(source, kind, &save_path, &category, paused) {
+                    Some(hash) => ControlResponse::success(serde_json::json!({ "hash": hash })),
+                    None => ControlResponse::failure("Failed to add torrent"),
+                }
+            }
+            ControlRequest::Open { sources } => {
+                for source in sources {
+                    let kind = if source.starts_with("magnet:") {
+                        AddTorrentKind::MagnetUrl
+                    } else {
+                        AddTorrentKind::File
+                    };
+                    self.tx
+                        .send(Message::AddTorrent(source, kind, "".to_owned()))
+                        .unwrap();
+                }
+                ControlResponse::success(serde_json::Value::Null)
+            }
+            ControlRequest::SetLimit {
+                hash: None,
+                download,
+                upload,
+            } => {
+                let mut settings = self.settings.lock().unwrap().clone();
+                let limits = &mut settings.speed_limits;
+                limits.download = download.unwrap_or(limits.download).max(0);
+                limits.upload = upload.unwrap_or(limits.upload).max(0);
+                if !settings.save(&self.data_dir) {
+                    log::error!("Failed to save settings.");
+                }
+                *self.settings.lock().unwrap() = settings;
+                self.apply_speed_limits();
+                ControlResponse::success(serde_json::Value::Null)
+            }
+            ControlRequest::SetLimit {
+                hash: Some(hash),
+                download,
+                upload,
+            } => {
+                let Some(index) = find(&hash) else {
+                    return ControlResponse::failure("No such torrent");
+                };
+                // The torrent reports unlimited as -1
+                let (current_download, current_upload) = {
+                    let torrents = self.torrents.lock().unwrap();
+                    let t = &torrents[index];
+                    (t.download_limit.max(0), t.upload_limit.max(0))
+                };
+                let action = BatchAction::SetLimits(
+                    download.unwrap_or(current_download),
+                    upload.unwrap_or(current_upload),
+                );
+                if self.batch(vec![index], action) {
+                    ControlResponse::success(serde_json::Value::Null)
+                } else {
+                    ControlResponse::failure("Failed to update torrent")
+                }
+            }
+            ControlRequest::Pause { ref hash }
+            | ControlRequest::Resume { ref hash }
+            | ControlRequest::Remove { ref hash, .. }
+            | ControlRequest::Recheck { ref hash }
+            | ControlRequest::Reannounce { ref hash }
+            | ControlRequest::Move { ref hash, .. }
+            | ControlRequest::SetCategory { ref hash, .. }
+            | ControlRequest::AddTag { ref hash, .. }
+            | ControlRequest::RemoveTag { ref hash, .. }
+            | ControlRequest::Queue { ref hash, .. } => {
+                let Some(index) = find(hash) else {
+                    return ControlResponse::failure("No such torrent");
+                };
+                let action = match request {
+                    ControlRequest::Pause { .. } => BatchAction::Pause,
+                    ControlRequest::Resume { .. } => BatchAction::Resume,
+                    ControlRequest::Remove { delete_files, .. } => {
+                        BatchAction::Remove { delete_files }
+                    }
+                    ControlRequest::Recheck { .. } => BatchAction::Recheck,
+                    ControlRequest::Reannounce { .. } => BatchAction::Reannounce,
+                    ControlRequest::Move { path, .. } => BatchAction::MoveStorage(path),
+                    ControlRequest::SetCategory { category, .. } => {
+                        BatchAction::SetCategory(category)
+                    }
+                    ControlRequest::AddTag { tag, .. } => BatchAction::AddTag(tag),
+                    ControlRequest::RemoveTag { tag, .. } => BatchAction::RemoveTag(tag),
+                    ControlRequest::Queue { to, .. } => BatchAction::Queue(to),
+                    _ => unreachable!(),
+                };
+                if self.batch(vec![index], action) {
+                    ControlResponse::success(serde_json::Value::Null)
+                } else {
+                    ControlResponse::failure("Failed to update torrent")
+                }
+            }
+        }
+    }
+
+    /// Returns whether the action succeeded on all the torrents.
+    fn batch(&mut self, indices: Vec<usize>, action: BatchAction) -> bool {
+        let hashes: Vec<String> = {
+            let torrents = self.torrents.lock().unwrap();
+            indices.iter().map(|i| torrents[*i].hash.clone()).collect()
+        };
+        match &action {
+            BatchAction::SetCategory(name) => {
+                for hash in &hashes {
+                    self.set_category(hash, name);
+                }
+                let save_path = self.category_save_path(name);
+                save_path.is_empty()
+                    || torrent::batch(
+                        &indices,
+                        &BatchAction::MoveStorage(save_path),
+                        self.toasts.clone(),
+                    )
+                    .len()
+                        == indices.len()
+            }
+            BatchAction::AddTag(tag) => {
+                for hash in &hashes {
+                    self.update_metadata(hash, |m| {
+                        if !m.tags.contains(tag) {
+                            m.tags.push(tag.clone());
+                            m.tags.sort();
+                        }
+                    });
+                }
+                true
+            }
+            BatchAction::RemoveTag(tag) => {
+                for hash in &hashes {
+                    self.update_metadata(hash, |m| m.tags.retain(|t| t != tag));
+                }
+                true
+            }
+            BatchAction::Remove { .. } => {
+                let removed = torrent::batch(&indices, &action, self.toasts.clone());
+                for (index, hash) in indices.iter().zip(&hashes) {
+                    if removed.contains(index) {
+                        self.metadata.remove(hash);
+                        TorrentMetadata::remove(&self.resume_dir(), hash);
+                    }
+                }
+                *self.sel_torrent.lock().unwrap() = None;
+                removed.len() == indices.len()
+            }
+            _ => torrent::batch(&indices, &action, self.toasts.clone()).len() == indices.len(),
+        }
+    }
+
+    fn resume_dir(&self) -> PathBuf {
+        self.data_dir.join("resume_files")
+    }
+
+    fn category_save_path(&self, name: &str) -> String {
+        self.categories
+            .lock()
+            .unwrap()
+            .iter()
+            .find(|c| c.name == name)
+            .map(|c| c.save_path.clone())
+            .unwrap_or_default()
+    }
+
+    /// Adds a torrent, saving it into `save_path` or, if empty, the category's
+    /// save path or the downloads directory.
+    fn add(
+        &mut self,
+        path: String,
+        kind: AddTorrentKind,
+        save_path: &str,
+        category: &str,
+        paused: bool,
+    ) -> Option<String> {
+        let save_path = if !save_path.trim().is_empty() {
+            save_path.to_owned()
+        } else {
+            let category_save_path = self.category_save_path(category);
+            if category_save_path.is_empty() {
+                torrent::default_save_path()
+            } else {
+                category_save_path
+            }
+        };
+        let hash = torrent::add_torrent(path, kind, save_path, paused, self.toasts.clone())?;
+        if !category.is_empty() {
+            self.set_category(&hash, category);
+        }
+        Some(hash)
+    }
+
+    fn update_metadata<F>(&mut self, hash: &str, f: F)
+    where
+        F: FnOnce(&mut TorrentMetadata),
+    {
+        let resume_dir = self.resume_dir();
+        let metadata = self.metadata.entry(hash.to_owned()).or_default();
+        f(metadata);
+        if !metadata.save(&resume_dir, hash) {
+            log::error!("Failed to save metadata of {}.", hash);
+        }
+    }
+
+    fn set_category(&mut self, hash: &str, name: &str) {
+        self.update_metadata(hash, |m| m.category = name.to_owned());
+    }
+
+    fn hashes_in_category(&self, name: &str) -> Vec<String> {
+        self.metadata
+            .iter()
+            .filter(|(_, m)| m.category == name)
+            .map(|(hash, _)| hash.clone())
+            .collect()
+    }
+
+    fn persist_categories(&self) {
+        let categories = self.categories.lock().unwrap();
+        if !Category::save_all(&self.data_dir, &categories) {
+            toasts::error(
+                &mut self.toasts.lock().unwrap(),
+                "Failed to save categories.",
+            );
+        }
+    }
+
+    fn save_category(&mut self, old_name: Option<String>, category: Category) {
+        let old = old_name.and_then(|old_name| {
+            let mut categories = self.categories.lock().unwrap();
+            let index = categories.iter().position(|c| c.name == old_name)?;
+            Some(categories.remove(index))
+        });
+        {
+            let mut categories = self.categories.lock().unwrap();
+            categories.retain(|c| c.name != category.name);
+            categories.push(category.clone());
+            categories.sort_by(|a, b| a.name.cmp(&b.name));
+        }
+        self.persist_categories();
+
+        let old = match old {
+            Some(old) => old,
+            None => return,
+        };
+        let hashes = self.hashes_in_category(&old.name);
+        if old.name != category.name {
+            for hash in &hashes {
+                self.set_category(hash, &category.name);
+            }
+        }
+        if old.save_path != category.save_path {
+            let save_path = if category.save_path.is_empty() {
+                torrent::default_save_path()
+            } else {
+                category.save_path.clone()
+            };
+            let indices: Vec<usize> = {
+                let torrents = self.torrents.lock().unwrap();
+                (0..torrents.len())
+                    .filter(|i| hashes.contains(&torrents[*i].hash))
+                    .collect()
+            };
+            for index in indices {
+                torrent::move_storage(index, &save_path, self.toasts.clone());
+            }
+        }
+    }
+
+    fn apply_metadata(&self) {
+        let mut torrents = self.torrents.lock().unwrap();
+        for torrent in torrents.iter_mut() {
+            let metadata = self
+                .metadata
+                .get(&torrent.hash)
+                .cloned()
+                .unwrap_or_default();
+            torrent.category = metadata.category;
+            torrent.tags = metadata.tags;
+        }
+    }
+
+    /// Refreshes the trackers of the selected torrent, shown in its tab, and
+    /// every few seconds those of the others, for the tracker filter.
+    fn refresh_trackers(&mut self) {
+        let is_due = self
+            .last_trackers_refresh
+            .is_none_or(|t| t.elapsed() >= TRACKERS_REFRESH_INTERVAL);
+        if is_due {
+            self.last_trackers_refresh = Some(Instant::now());
+        }
+        let selected = self.sel_torrent.lock().unwrap().map(|index| index - 1);
+        let mut torrents = self.torrents.lock().unwrap();
+        for (index, t) in torrents.iter_mut().enumerate() {
+            if is_due || selected == Some(index) {
+                t.trackers = torrent::fetch_trackers(index);
+            }
+        }
+    }
+
+    /// Pauses the seeding torrents which reached their category's share limits.
+    fn enforce_share_limits(&self) {
+        // Released before the torrents and toasts, which the UI locks first
+        let categories = self.categories.lock().unwrap().clone();
+        let torrents = self.torrents.lock().unwrap().clone();
+        for (index, t) in torrents.iter().enumerate() {
+            if t.state != TorrentState::Seeding && t.state != TorrentState::Finished {
+                continue;
+            }
+            let category = categories.iter().find(|c| c.name == t.category);
+            if category.is_some_and(|c| c.is_share_limit_reached(t)) {
+                torrent::toggle_state(index, t.state.clone(), self.toasts.clone());
+            }
+        }
+    }
+
+    fn refresh_feeds(&mut self) {
+        self.last_rss_refresh = Some(Instant::now());
+        let urls = self.settings.lock().unwrap().rss.feeds.clone();
+        let tx = self.tx.clone();
+        thread::spawn(move || {
+            let feeds = urls.iter().map(|url| rss::fetch_feed(url)).collect();
+            let _ = tx.send(Message::FeedsFetched(feeds));
+        });
+    }
+
+    /// Loads the IP filter lists in the background, or clears the filter if
+    /// disabled.
+    fn refresh_ip_filter(&mut self) {
+        self.last_ip_filter_refresh = Some(Instant::now());
+        let settings = self.settings.lock().unwrap().ip_filter.clone();
+        if !settings.enabled {
+            self.apply_ip_filter(vec![], vec![]);
+            return;
+        }
+        let tx = self.tx.clone();
+        thread::spawn(move || {
+            let (ranges, errors) = ip_filter::load_all(&settings.sources);
+            let _ = tx.send(Message::IpFilterLoaded(ranges, errors));
+        });
+    }
+
+    fn apply_ip_filter(&mut self, ranges: Vec<IpRange>, errors: Vec<String>) {
+        // Lists loading while the filter got disabled are dropped
+        self.ip_filter_lists = match self.settings.lock().unwrap().ip_filter.enabled {
+            true => ranges,
+            false => vec![],
+        };
+        for e in &errors {
+            log::error!("Failed to load IP filter list {}", e);
+        }
+        self.ip_filter.lock().unwrap().errors = errors;
+        self.update_ip_filter();
+    }
+
+    /// Blocks the ranges of the lists and the banned peers.
+    fn update_ip_filter(&self) {
+        let banned_ips = self.settings.lock().unwrap().ip_filter.banned_ips.clone();
+        let ranges = ip_filter::with_banned(&self.ip_filter_lists, &banned_ips);
+        if torrent::set_ip_filter(&ranges) {
+            self.ip_filter.lock().unwrap().num_ranges = ranges.len();
+        } else {
+            log::error!("Failed to apply the IP filter.");
+            toasts::error(
+                &mut self.toasts.lock().unwrap(),
+                "Failed to apply the IP filter.",
+            );
+        }
+    }
+
+    fn ban_peer(&mut self, ip: String) {
+        let mut settings = self.settings.lock().unwrap();
+        if !settings.ip_filter.banned_ips.contains(&ip) {
+            settings.ip_filter.banned_ips.push(ip);
+        }
+        if !settings.save(&self.data_dir) {
+            log::error!("Failed to save settings.");
+        }
+        drop(settings);
+        // Connected peers in the filter are disconnected
+        self.update_ip_filter();
+        toasts::success(&mut self.toasts.lock().unwrap(), "Banned peer.");
+    }
+
+    fn unban_peer(&mut self, ip: &str) {
+        let mut settings = self.settings.lock().unwrap();
+        settings.ip_filter.banned_ips.retain(|banned| banned != ip);
+        if !settings.save(&self.data_dir) {
+            log::error!("Failed to save settings.");
+        }
+        drop(settings);
+        self.update_ip_filter();
+        toasts::success(&mut self.toasts.lock().unwrap(), "Unbanned peer.");
+    }
+
+    fn add_peers(&self, index: usize, endpoints: Vec<SocketAddr>) {
+        let failed = torrent::connect_peers(index, &endpoints);
+        let accepted: Vec<String> = endpoints
+            .iter()
+            .filter(|e| !failed.contains(e))
+            .map(|e| e.to_string())
+            .collect();
+        if !accepted.is_empty() {
+            log::info!("Connecting to peers: {}", accepted.join(", "));
+        }
+        if failed.is_empty() {
+            toasts::success(
+                &mut self.toasts.lock().unwrap(),
+                format!("Added {} peers.", endpoints.len()),
+            );
+        } else {
+            let failed: Vec<String> = failed.iter().map(|e| e.to_string()).collect();
+            log::error!("Failed to add peers: {}", failed.join(", "));
+            toasts::error(
+                &mut self.toasts.lock().unwrap(),
+                format!("Added {} of {} peers.", accepted.len(), endpoints.len()),
+            );
+        }
+    }
+
+    /// Adds a magnet link right away. A .torrent file is downloaded in the
+    /// background and added on `Message::FeedItemDownloaded`.
+    fn add_feed_item(&mut self, item: FeedItem, rule: RssRule) {
+        if item.url.starts_with("magnet:") {
+            let is_added = self
+                .add(
+                    item.url.clone(),
+                    AddTorrentKind::MagnetUrl,
+                    &rule.save_path,
+                    &rule.category,
+                    rule.start_paused,
+                )
+                .is_some();
+            if is_added {
+                self.record_feed_item(item);
+            }
+            return;
+        }
+
+        if !self.rss_downloads.insert(item.guid.clone()) {
+            return;
+        }
+        let tx = self.tx.clone();
+        thread::spawn(move || {
+            let result = rss::fetch_torrent(&item.url);
+            let _ = tx.send(Message::FeedItemDownloaded(item, Box::new(rule), result));
+        });
+    }
+
+    /// Keeps the item from being downloaded again.
+    fn record_feed_item(&mut self, item: FeedItem) {
+        self.rss_history.insert(item.guid);
+        if !rss::save_history(&self.data_dir, &self.rss_history) {
+            log::error!("Failed to save RSS history.");
+        }
+    }
+
+    fn start_watchers(&mut self) {
+        let folders = self.settings.lock().unwrap().watched_folders.clone();
+        self.watchers = watcher::watch(&folders, &self.tx);
+    }
+
+    fn apply_speed_limits(&self) {
+        let limits = self.settings.lock().unwrap().speed_limits.clone();
+        unsafe { set_session_limits(limits.download, limits.upload) };
+    }
+
+    fn apply_proxy_settings(&self) {
+        let settings = self.settings.lock().unwrap().proxy.clone();
+        if !torrent::apply_proxy(&settings) {
+            toasts::error(
+                &mut self.toasts.lock().unwrap(),
+                "Failed to apply the proxy settings.",
+            );
+        }
+    }
+
+    /// Binds the session to the interface of the settings, checking it right
+    /// away so that nothing leaks if it's down.
+    fn apply_network_settings(&mut self) {
+        let interface = self.settings.lock().unwrap().network.interface.clone();
+        let interface = interface.trim();
+        if self.network.lock().unwrap().interface != interface || self.last_network_check.is_none()
+        {
+            self.network.lock().unwrap().interface = interface.to_owned();
+            if !torrent::bind_interface(interface) {
+                log::error!("Failed to bind to the network interface {}.", interface);
+                toasts::error(
+                    &mut self.toasts.lock().unwrap(),
+                    "Failed to bind to the network interface.",
+                );
+            }
+        }
+        self.check_network();
+    }
+
+    /// Pauses the session while the bound interface is down or has no
+    /// address, resuming it once it's back.
+    fn check_network(&mut self) {
+        self.last_network_check = Some(Instant::now());
+        let interfaces = torrent::fetch_interfaces();
+        // The UI locks the toasts before the network status, so it's
+        // released before toasting
+        let (interface, is_down, was_paused, has_changed) = {
+            let mut status = self.network.lock().unwrap();
+            let addresses = network::addresses(&interfaces, &status.interface);
+            let has_changed = status.addresses != addresses;
+            status.interfaces = interfaces;
+            status.addresses = addresses;
+            let was_paused = status.is_paused;
+            status.is_paused = status.is_down();
+            (
+                status.interface.clone(),
+                status.is_down(),
+                was_paused,
+                has_changed,
+            )
+        };
+        match (is_down, was_paused) {
+            (true, false) => {
+                torrent::pause_session();
+                log::warn!(
+                    "Paused the session, the network interface {} is down.",
+                    interface
+                );
+                toasts::error(
+                    &mut self.toasts.lock().unwrap(),
+                    "Paused the session, the network interface is down.",
+                );
+            }
+            (false, true) => {
+                torrent::bind_interface(&interface);
+                torrent::resume_session();
+                log::info!("Resumed the session, the network interface is back.");
+                toasts::success(
+                    &mut self.toasts.lock().unwrap(),
+                    "Resumed the session, the network interface is back.",
+                );
+            }
+            (false, false) if has_changed && !interface.is_empty() => {
+                // Listen on the new addresses
+                torrent::bind_interface(&interface);
+            }
+            _ => {}
+        }
+    }
+
+    /// Restarts the web server if its settings changed.
+    fn apply_web_settings(&mut self) {
+        let settings = self.settings.lock().unwrap().web.clone();
+        let is_applied = match &self.web_server {
+            Some(server) => server.settings == settings,
+            None => !settings.enabled,
+        };
+        if is_applied {
+            return;
+        }
+        // Stops the old server before binding again
+        self.web_server = None;
+        if !settings.enabled {
+            return;
+        }
+        let ctx = WebContext::new(
+            settings,
+            self.data_dir.clone(),
+            self.tx.clone(),
+            self.torrents.clone(),
+            self.session_stats.clone(),
+            self.log.clone(),
+        );
+        match web::serve(ctx) {
+            Ok(server) => {
+                if let Some(addr) = server.addr() {
+                    log::info!("Serving the web UI on {}.", addr);
+                }
+                self.web_server = Some(server);
+            }
+            Err(e) => {
+                log::error!("Failed to start the web server: {}", e);
+                toasts::error(
+                    &mut self.toasts.lock().unwrap(),
+                    "Failed to start the web server.",
+                );
+            }
+        }
+    }
+
+    fn apply_log_settings(&self) {
+        let settings = self.settings.lock().unwrap().log.clone();
+        self.log.lock().unwrap().set_capacity(settings.max_entries);
+        unsafe { set_alert_mask(AlertCategory::mask(&settings.alert_categories)) };
+    }
+
+    fn run_hook(&self, event: TorrentEvent) {
+        let settings = self.settings.lock().unwrap().hooks.clone();
+        let (hash, command, event_name) = match event {
+            TorrentEvent::Added(hash) => (hash, settings.on_added.clone(), "added"),
+            TorrentEvent::Finished(hash) => (hash, settings.on_finished.clone(), "finished"),
+        };
+        if command.trim().is_empty() {
+            return;
+        }
+
+        let ctx = {
+            let torrents = self.torrents.lock().unwrap();
+            match torrents.iter().find(|t| t.hash == hash) {
+                Some(torrent) => HookContext::from_torrent(torrent),
+                None => return,
+            }
+        };
+        let log_path = self.data_dir.join(HOOKS_LOG_FILE);
+        thread::spawn(move || {
+            let _ = hook::execute(&command, event_name, &ctx, &settings, &log_path);
+        });
+    }
 }
@@ -0,0 +1,228 @@
+use std::fmt::{Display, Write};
+
+use super::web::{WebContext, WebResponse};
+use crate::models::{
+    log::{Log, LogLevel, LogSource},
+    message::Message,
+    stats::SessionStats,
+    torrent::{Torrent, TorrentState},
+};
+
+pub const METRICS_PATH: &str = "/metrics";
+const CONTENT_TYPE: &str = "text/plain; version=0.0.4; charset=utf-8";
+
+/// Text in Prometheus' exposition format.
+#[derive(Default)]
+struct Exposition {
+    text: String,
+}
+
+impl Exposition {
+    fn family(&mut self, name: &str, kind: &str, help: &str) {
+        let _ = writeln!(self.text, "# HELP torrenter_{} {}", name, help);
+        let _ = writeln!(self.text, "# TYPE torrenter_{} {}", name, kind);
+    }
+
+    fn sample(&mut self, name: &str, labels: &[(&str, &str)], value: impl Display) {
+        let _ = write!(self.text, "torrenter_{}", name);
+        if !labels.is_empty() {
+            let labels: Vec<String> = labels
+                .iter()
+                .map(|(label, value)| format!("{}=\"{}\"", label, escape(value)))
+                .collect();
+            let _ = write!(self.text, "{{{}}}", labels.join(","));
+        }
+        let _ = writeln!(self.text, " {}", value);
+    }
+
+    /// A family of a single unlabeled sample.
+    fn single(&mut self, name: &str, kind: &str, help: &str, value: impl Display) {
+        self.family(name, kind, help);
+        self.sample(name, &[], value);
+    }
+
+    /// A family of a sample per torrent.
+    fn per_torrent<T: Display>(
+        &mut self,
+        torrents: &[Torrent],
+        name: &str,
+        kind: &str,
+        help: &str,
+        value: impl Fn(&Torrent) -> T,
+    ) {
+        self.family(name, kind, help);
+        for t in torrents {
+            self.sample(name, &[("hash", &t.hash), ("name", &t.name)], value(t));
+        }
+    }
+}
+
+fn escape(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n")
+}
+
+fn state_label(state: &TorrentState) -> &'static str {
+    match state {
+        TorrentState::QueuedForChecking => "queued_for_checking",
+        TorrentState::CheckingFiles => "checking_files",
+        TorrentState::DownloadingMetaData => "downloading_metadata",
+        TorrentState::Downloading => "downloading",
+        TorrentState::Finished => "finished",
+        TorrentState::Seeding => "seeding",
+        TorrentState::Allocating => "allocating",
+        TorrentState::CheckingResumeData => "checking_resume_data",
+        TorrentState::Paused => "paused",
+    }
+}
+
+const STATES: [TorrentState; 9] = [
+    TorrentState::QueuedForChecking,
+    TorrentState::CheckingFiles,
+    TorrentState::DownloadingMetaData,
+    TorrentState::Downloading,
+    TorrentState::Finished,
+    TorrentState::Seeding,
+    TorrentState::Allocating,
+    TorrentState::CheckingResumeData,
+    TorrentState::Paused,
+];
+
+/// The metrics of the torrents as of the last refresh, the session counters
+/// and the log. Counters missing from the session stats are left out.
+pub fn render(torrents: &[Torrent], stats: &SessionStats, log: &Log) -> String {
+    let mut e = Exposition::default();
+
+    let download_rate: i64 = torrents.iter().map(|t| t.download_rate).sum();
+    let upload_rate: i64 = torrents.iter().map(|t| t.upload_rate).sum();
+    e.single(
+        "download_rate_bytes",
+        "gauge",
+        "Payload download rate of the session in bytes per second.",
+        download_rate,
+    );
+    e.single(
+        "upload_rate_bytes",
+        "gauge",
+        "Payload upload rate of the session in bytes per second.",
+        upload_rate,
+    );
+    let counters = [
+        (
+            "downloaded_bytes_total",
+            "counter",
+            "Payload downloaded by the session in bytes.",
+            "net.recv_payload_bytes",
+        ),
+        (
+            "uploaded_bytes_total",
+            "counter",
+            "Payload uploaded by the session in bytes.",
+            "net.sent_payload_bytes",
+        ),
+        (
+            "peers_connected",
+            "gauge",
+            "Peers connected to the session.",
+            "peer.num_peers_connected",
+        ),
+        (
+            "dht_nodes",
+            "gauge",
+            "Nodes in the DHT routing table.",
+            "dht.dht_nodes",
+        ),
+    ];
+    for (name, kind, help, counter) in counters {
+        if let Some(value) = stats.get(counter) {
+            e.single(name, kind, help, value);
+        }
+    }
+
+    e.family("torrents", "gauge", "Torrents by state.");
+    for state in &STATES {
+        let count = torrents.iter().filter(|t| t.state == *state).count();
+        e.sample("torrents", &[("state", state_label(state))], count);
+    }
+    e.single(
+        "torrents_errored",
+        "gauge",
+        "Torrents with an error.",
+        torrents.iter().filter(|t| !t.error.is_empty()).count(),
+    );
+
+    e.per_torrent(
+        torrents,
+        "torrent_progress",
+        "gauge",
+        "Downloaded part of the torrent, from 0 to 1.",
+        |t| t.progress,
+    );
+    e.per_torrent(
+        torrents,
+        "torrent_download_rate_bytes",
+        "gauge",
+        "Payload download rate of the torrent in bytes per second.",
+        |t| t.download_rate,
+    );
+    e.per_torrent(
+        torrents,
+        "torrent_upload_rate_bytes",
+        "gauge",
+        "Payload upload rate of the torrent in bytes per second.",
+        |t| t.upload_rate,
+    );
+    e.per_torrent(
+        torrents,
+        "torrent_peers",
+        "gauge",
+        "Peers connected to the torrent, seeds included.",
+        |t| t.num_peers,
+    );
+    e.per_torrent(
+        torrents,
+        "torrent_seeds",
+        "gauge",
+        "Seeds connected to the torrent.",
+        |t| t.num_seeds,
+    );
+    e.per_torrent(
+        torrents,
+        "torrent_ratio",
+        "gauge",
+        "Uploaded over downloaded payload of the torrent.",
+        |t| t.ratio(),
+    );
+
+    e.family(
+        "log_messages_total",
+        "counter",
+        "Log messages by source and level, libtorrent alerts included.",
+    );
+    for source in LogSource::ALL {
+        for level in [LogLevel::Warning, LogLevel::Error] {
+            let source_label = source.label().to_lowercase();
+            let level_label = level.label().to_lowercase();
+            let labels = [
+                ("source", source_label.as_str()),
+                ("level", level_label.as_str()),
+            ];
+            e.sample("log_messages_total", &labels, log.count(source, level));
+        }
+    }
+    e.text
+}
+
+/// Answers a scrape. The session stats are posted asynchronously, so each
+/// scrape gets those requested by the previous one.
+pub fn handle(ctx: &WebContext) -> WebResponse {
+    let _ = ctx.tx.send(Message::FetchSessionStats);
+    let text = render(
+        &ctx.torrents.lock().unwrap(),
+        &ctx.session_stats.lock().unwrap(),
+        &ctx.log.lock().unwrap(),
+    );
+    WebResponse::new(200, CONTENT_TYPE, text.into_bytes())
+}
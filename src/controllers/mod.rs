@@ -1,3 +1,15 @@
 pub mod add_torrent;
+#[cfg(unix)]
+pub mod control;
+pub mod hook;
+pub mod ip_filter;
+pub mod log;
 pub mod message;
+pub mod metrics;
+pub mod proxy;
+pub mod qbittorrent;
+pub mod rss;
 pub mod torrent;
+pub mod transmission;
+pub mod watcher;
+pub mod web;
@@ -0,0 +1,196 @@
+use std::{
+    io::{self, Read, Write},
+    net::{IpAddr, TcpStream, ToSocketAddrs},
+    time::Duration,
+};
+
+use base64::{engine::general_purpose::STANDARD, Engine};
+
+use crate::models::settings::{ProxyKind, ProxySettings};
+
+/// Reached through the proxy by the check of the settings.
+pub const CHECK_TARGET: (&str, u16) = ("example.com", 443);
+const TIMEOUT: Duration = Duration::from_secs(10);
+const MAX_HTTP_HEADER: usize = 8192;
+
+fn refused(reason: &str) -> io::Error {
+    io::Error::other(reason.to_owned())
+}
+
+/// The IPv4 address of the host, resolved here.
+fn resolve_v4(host: &str, port: u16) -> io::Result<[u8; 4]> {
+    (host, port)
+        .to_socket_addrs()?
+        .find_map(|addr| match addr.ip() {
+            IpAddr::V4(ip) => Some(ip.octets()),
+            IpAddr::V6(_) => None,
+        })
+        .ok_or_else(|| refused("No IPv4 address for the host"))
+}
+
+/// SOCKS4a when the proxy resolves host names.
+fn socks4(
+    stream: &mut TcpStream,
+    settings: &ProxySettings,
+    host: &str,
+    port: u16,
+) -> io::Result<()> {
+    let mut req = vec![4, 1];
+    req.extend(port.to_be_bytes());
+    let domain = match host.parse::<IpAddr>() {
+        Ok(IpAddr::V4(ip)) => {
+            req.extend(ip.octets());
+            None
+        }
+        Ok(IpAddr::V6(_)) => return Err(refused("SOCKS4 doesn't support IPv6")),
+        Err(_) if settings.proxy_hostnames => {
+            req.extend([0, 0, 0, 1]);
+            Some(host)
+        }
+        Err(_) => {
+            req.extend(resolve_v4(host, port)?);
+            None
+        }
+    };
+    req.extend(settings.username.as_bytes());
+    req.push(0);
+    if let Some(domain) = domain {
+        req.extend(domain.as_bytes());
+        req.push(0);
+    }
+    stream.write_all(&req)?;
+
+    let mut reply = [0; 8];
+    stream.read_exact(&mut reply)?;
+    match reply[1] {
+        0x5a => Ok(()),
+        0x5c | 0x5d => Err(refused("Proxy refused the user ID")),
+        _ => Err(refused("Proxy refused the connection")),
+    }
+}
+
+fn socks5(
+    stream: &mut TcpStream,
+    settings: &ProxySettings,
+    host: &str,
+    port: u16,
+) -> io::Result<()> {
+    let has_auth = !settings.username.is_empty();
+    let method = if has_auth { 2 } else { 0 };
+    stream.write_all(&[5, 1, method])?;
+    let mut reply = [0; 2];
+    stream.read_exact(&mut reply)?;
+    if reply != [5, method] {
+        return Err(refused("Proxy refused the authentication method"));
+    }
+
+    if has_auth {
+        let (username, password) = (settings.username.as_bytes(), settings.password.as_bytes());
+        if username.len() > 255 || password.len() > 255 {
+            return Err(refused("Username or password too long"));
+        }
+        let mut req = vec![1, username.len() as u8];
+        req.extend(username);
+        req.push(password.len() as u8);
+        req.extend(password);
+        stream.write_all(&req)?;
+        stream.read_exact(&mut reply)?;
+        if reply[1] != 0 {
+            return Err(refused("Proxy refused the credentials"));
+        }
+    }
+
+    let mut req = vec![5, 1, 0];
+    match host.parse::<IpAddr>() {
+        Ok(IpAddr::V4(ip)) => {
+            req.push(1);
+            req.extend(ip.octets());
+        }
+        Ok(IpAddr::V6(ip)) => {
+            req.push(4);
+            req.extend(ip.octets());
+        }
+        Err(_) if settings.proxy_hostnames && host.len() <= 255 => {
+            req.push(3);
+            req.push(host.len() as u8);
+            req.extend(host.as_bytes());
+        }
+        Err(_) => {
+            req.push(1);
+            req.extend(resolve_v4(host, port)?);
+        }
+    }
+    req.extend(port.to_be_bytes());
+    stream.write_all(&req)?;
+
+    // The bound address following the header is left unread
+    let mut reply = [0; 4];
+    stream.read_exact(&mut reply)?;
+    match reply[1] {
+        0 => Ok(()),
+        code => Err(refused(&format!(
+            "Proxy refused the connection, code {}",
+            code
+        ))),
+    }
+}
+
+fn http(stream: &mut TcpStream, settings: &ProxySettings, host: &str, port: u16) -> io::Result<()> {
+    let authority = match host.parse::<IpAddr>() {
+        Ok(IpAddr::V6(ip)) => format!("[{}]:{}", ip, port),
+        _ => format!("{}:{}", host, port),
+    };
+    let mut req = format!("CONNECT {0} HTTP/1.1\r\nHost: {0}\r\n", authority);
+    if !settings.username.is_empty() {
+        let credentials = format!("{}:{}", settings.username, settings.password);
+        req.push_str(&format!(
+            "Proxy-Authorization: Basic {}\r\n",
+            STANDARD.encode(credentials)
+        ));
+    }
+    req.push_str("\r\n");
+    stream.write_all(req.as_bytes())?;
+
+    let mut header = vec![];
+    let mut byte = [0; 1];
+    while !header.ends_with(b"\r\n\r\n") {
+        if header.len() >= MAX_HTTP_HEADER {
+            return Err(refused("Proxy response too long"));
+        }
+        stream.read_exact(&mut byte)?;
+        header.push(byte[0]);
+    }
+    let header = String::from_utf8_lossy(&header);
+    let status = header.split_whitespace().nth(1).unwrap_or("");
+    match status {
+        "200" => Ok(()),
+        "407" => Err(refused("Proxy refused the credentials")),
+        _ => Err(refused(&format!(
+            "Proxy refused the connection: {}",
+            header.lines().next().unwrap_or("")
+        ))),
+    }
+}
+
+/// Connects to `host:port` through the proxy, checking that it accepts the
+/// credentials and the connection.
+pub fn check(settings: &ProxySettings, host: &str, port: u16) -> Result<(), String> {
+    if !settings.is_enabled() {
+        return Err("No proxy configured".to_owned());
+    }
+    let addr = (settings.host.trim(), settings.port)
+        .to_socket_addrs()
+        .map_err(|e| e.to_string())?
+        .next()
+        .ok_or("Proxy host not found")?;
+    let mut stream = TcpStream::connect_timeout(&addr, TIMEOUT).map_err(|e| e.to_string())?;
+    let _ = stream.set_read_timeout(Some(TIMEOUT));
+    let _ = stream.set_write_timeout(Some(TIMEOUT));
+    match settings.kind {
+        ProxyKind::None => unreachable!(),
+        ProxyKind::Socks4 => socks4(&mut stream, settings, host, port),
+        ProxyKind::Socks5 => socks5(&mut stream, settings, host, port),
+        ProxyKind::Http => http(&mut stream, settings, host, port),
+    }
+    .map_err(|e| e.to_string())
+}
@@ -0,0 +1,483 @@
+use std::{
+    collections::HashMap,
+    sync::Mutex,
+    time::{Duration, Instant},
+};
+
+use serde_json::Value;
+
+use super::{
+    rss,
+    web::{self, FormField, WebContext, WebRequest, WebResponse},
+};
+use crate::models::{
+    category::Category,
+    control::{ControlRequest, ControlResponse},
+    message::Message,
+    torrent::{Torrent, TorrentState},
+};
+
+pub const API_PATH: &str = "/api/v2/";
+/// The versions clients check for, of the qBittorrent release whose API is
+/// implemented.
+const APP_VERSION: &str = "v4.6.0";
+const API_VERSION: &str = "2.9.3";
+const SESSION_COOKIE: &str = "SID";
+/// qBittorrent's default, since the last request of the session.
+const SESSION_TIMEOUT: Duration = Duration::from_secs(3600);
+/// Beyond it, logging in ends the least recently used session.
+pub const MAX_SESSIONS: usize = 100;
+/// Endpoints changing state, which qBittorrent only accepts as POST.
+const POST_ENDPOINTS: [&str; 6] = [
+    "auth/login",
+    "auth/logout",
+    "torrents/add",
+    "torrents/delete",
+    "torrents/setCategory",
+    "torrents/createCategory",
+];
+/// qBittorrent's ETA of torrents not progressing.
+const INFINITE_ETA: i64 = 8640000;
+
+/// The sessions of the clients which logged in, with when they were last
+/// used.
+#[derive(Default)]
+pub struct QbtState {
+    sessions: Mutex<HashMap<String, Instant>>,
+}
+
+impl QbtState {
+    fn new_session(&self) -> String {
+        let sid = web::random_token();
+        let mut sessions = self.sessions.lock().unwrap();
+        sessions.retain(|_, last_used| last_used.elapsed() < SESSION_TIMEOUT);
+        if sessions.len() >= MAX_SESSIONS {
+            let oldest = sessions
+                .iter()
+                .min_by_key(|(_, last_used)| **last_used)
+                .map(|(sid, _)| sid.clone());
+            if let Some(oldest) = oldest {
+                sessions.remove(&oldest);
+            }
+        }
+        sessions.insert(sid.clone(), Instant::now());
+        sid
+    }
+
+    /// Whether the session is current, extending it if so.
+    fn use_session(&self, sid: &str) -> bool {
+        let mut sessions = self.sessions.lock().unwrap();
+        match sessions.get_mut(sid) {
+            Some(last_used) if last_used.elapsed() < SESSION_TIMEOUT => {
+                *last_used = Instant::now();
+                true
+            }
+            Some(_) => {
+                sessions.remove(sid);
+                false
+            }
+            None => false,
+        }
+    }
+}
+
+fn session_cookie(request: &WebRequest) -> Option<&str> {
+    request
+        .header("cookie")?
+        .split(';')
+        .find_map(|c| c.trim().strip_prefix(SESSION_COOKIE)?.strip_prefix('='))
+}
+
+/// Only the session cookie counts, as browsers send basic authentication
+/// along with requests of other sites.
+fn is_logged_in(ctx: &WebContext, request: &WebRequest) -> bool {
+    session_cookie(request).is_some_and(|sid| ctx.qbittorrent.use_session(sid))
+}
+
+/// The state as qBittorrent names it.
+fn state(t: &Torrent) -> &'static str {
+    if !t.error.is_empty() {
+        return "error";
+    }
+    let is_complete = t.progress >= 1.0;
+    match t.state {
+        TorrentState::Paused if is_complete => "pausedUP",
+        TorrentState::Paused => "pausedDL",
+        TorrentState::QueuedForChecking | TorrentState::CheckingFiles if is_complete => {
+            "checkingUP"
+        }
+        TorrentState::QueuedForChecking | TorrentState::CheckingFiles => "checkingDL",
+        TorrentState::CheckingResumeData => "checkingResumeData",
+        TorrentState::DownloadingMetaData => "metaDL",
+        TorrentState::Allocating => "allocating",
+        TorrentState::Downloading if t.is_stalled() => "stalledDL",
+        TorrentState::Downloading => "downloading",
+        TorrentState::Finished | TorrentState::Seeding if t.is_stalled() => "stalledUP",
+        TorrentState::Finished | TorrentState::Seeding => "uploading",
+    }
+}
+
+fn eta(t: &Torrent) -> i64 {
+    if t.eta < 0 {
+        INFINITE_ETA
+    } else {
+        t.eta
+    }
+}
+
+fn amount_left(t: &Torrent) -> i64 {
+    ((1.0 - t.progress as f64) * t.total_size as f64).round() as i64
+}
+
+fn content_path(t: &Torrent) -> String {
+    format!("{}/{}", t.save_path.trim_end_matches(['/', '\\']), t.name)
+}
+
+fn torrent_info(t: &Torrent) -> Value {
+    serde_json::json!({
+        "hash": t.hash,
+        "name": t.name,
+        "size": t.total_size,
+        "total_size": t.total_size,
+        "progress": t.progress,
+        "dlspeed": t.download_rate,
+        "upspeed": t.upload_rate,
+        "num_seeds": t.num_seeds,
+        "num_leechs": t.num_peers - t.num_seeds,
+        "ratio": t.ratio(),
+        "eta": eta(t),
+        "state": state(t),
+        "category": t.category,
+        "tags": t.tags.join(","),
+        "save_path": t.save_path,
+        "content_path": content_path(t),
+        "added_on": t.added_time,
+        "amount_left": amount_left(t),
+        "completed": t.total_size - amount_left(t),
+        "downloaded": t.total_download,
+        "uploaded": t.total_upload,
+        "dl_limit": t.download_limit,
+        "up_limit": t.upload_limit,
+        "seeding_time": t.seeding_duration,
+        "time_active": t.active_duration,
+        "priority": 0,
+    })
+}
+
+fn torrent_properties(t: &Torrent) -> Value {
+    serde_json::json!({
+        "save_path": t.save_path,
+        "comment": t.comment,
+        "piece_size": t.piece_len,
+        "pieces_have": t.pieces_downloaded,
+        "pieces_num": t.pieces.len(),
+        "total_size": t.total_size,
+        "total_downloaded": t.total_download,
+        "total_downloaded_session": t.total_ses_download,
+        "total_uploaded": t.total_upload,
+        "total_uploaded_session": t.total_ses_upload,
+        "dl_speed": t.download_rate,
+        "up_speed": t.upload_rate,
+        "dl_limit": t.download_limit,
+        "up_limit": t.upload_limit,
+        "time_elapsed": t.active_duration,
+        "seeding_time": t.seeding_duration,
+        "nb_connections": t.num_peers,
+        "seeds": t.num_seeds,
+        "peers": t.num_peers - t.num_seeds,
+        "share_ratio": t.ratio(),
+        "addition_date": t.added_time,
+        "eta": eta(t),
+        "reannounce": t.next_announce,
+    })
+}
+
+/// Whether the torrent is listed by a `filter` of `torrents/info`.
+fn matches_filter(t: &Torrent, filter: &str) -> bool {
+    match filter {
+        "downloading" => matches!(
+            t.state,
+            TorrentState::Downloading | TorrentState::DownloadingMetaData
+        ),
+        "seeding" => matches!(t.state, TorrentState::Seeding | TorrentState::Finished),
+        "completed" => t.progress >= 1.0,
+        "paused" | "stopped" => t.state == TorrentState::Paused,
+        "resumed" | "running" => t.state != TorrentState::Paused,
+        "active" => t.download_rate > 0 || t.upload_rate > 0,
+        "inactive" => t.download_rate == 0 && t.upload_rate == 0,
+        "stalled" => t.is_stalled(),
+        "errored" => !t.error.is_empty(),
+        _ => true,
+    }
+}
+
+/// The torrents of the `hashes` parameter, separated by `|` or `all`.
+fn select(ctx: &WebContext, hashes: &str) -> Vec<Torrent> {
+    let hashes: Vec<&str> = hashes.split('|').map(str::trim).collect();
+    ctx.torrents
+        .lock()
+        .unwrap()
+        .iter()
+        .filter(|t| !t.hash.is_empty())
+        .filter(|t| hashes == ["all"] || hashes.iter().any(|h| h.eq_ignore_ascii_case(&t.hash)))
+        .cloned()
+        .collect()
+}
+
+fn find(ctx: &WebContext, hash: &str) -> Option<Torrent> {
+    select(ctx, hash).into_iter().next()
+}
+
+fn text_field(form: &[FormField], name: &str) -> String {
+    form.iter()
+        .find(|f| f.name == name)
+        .map(|f| f.text())
+        .unwrap_or_default()
+}
+
+fn categories(ctx: &WebContext) -> Vec<Category> {
+    ctx.control(ControlRequest::Categories)
+        .data
+        .as_array()
+        .map(|categories| {
+            categories
+                .iter()
+                .filter_map(|c| serde_json::from_value(c.clone()).ok())
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+fn ok() -> WebResponse {
+    WebResponse::text(200, "Ok.")
+}
+
+fn not_found() -> WebResponse {
+    WebResponse::text(404, "Torrent hash was not found")
+}
+
+fn login(ctx: &WebContext, request: &WebRequest) -> WebResponse {
+    let form = request.form();
+    let settings = &ctx.settings;
+    let is_valid = settings.username.is_empty()
+        || (text_field(&form, "username") == settings.username
+            && text_field(&form, "password") == settings.password);
+    if !is_valid {
+        return WebResponse::text(200, "Fails.");
+    }
+    let sid = ctx.qbittorrent.new_session();
+    ok().with_header(
+        "Set-Cookie",
+        &format!(
+            "{}={}; HttpOnly; SameSite=Strict; path=/",
+            SESSION_COOKIE, sid
+        ),
+    )
+}
+
+/// Adds the links of `urls` and the files of `torrents`.
+fn add(ctx: &WebContext, form: &[FormField]) -> WebResponse {
+    let save_path = text_field(form, "savepath");
+    let category = text_field(form, "category");
+    let paused = ["paused", "stopped"]
+        .iter()
+        .any(|name| text_field(form, name) == "true");
+    let add_url = |url: &str| -> ControlResponse {
+        if url.starts_with("magnet:") {
+            ctx.control(ControlRequest::Add {
+                source: url.to_owned(),
+                save_path: save_path.clone(),
+                category: category.clone(),
+                paused,
+            })
+        } else {
+            match rss::fetch_torrent(url) {
+                Ok(data) => {
+                    web::add_torrent_data(ctx, &data, save_path.clone(), category.clone(), paused)
+                }
+                Err(e) => ControlResponse::failure(e),
+            }
+        }
+    };
+
+    let mut responses: Vec<ControlResponse> = text_field(form, "urls")
+        .lines()
+        .map(str::trim)
+        .filter(|url| !url.is_empty())
+        .map(add_url)
+        .collect();
+    for file in form
+        .iter()
+        .filter(|f| f.name == "torrents" && f.filename.is_some())
+    {
+        responses.push(web::add_torrent_data(
+            ctx,
+            &file.data,
+            save_path.clone(),
+            category.clone(),
+            paused,
+        ));
+    }
+    if responses.iter().any(|r| r.ok) {
+        ok()
+    } else {
+        WebResponse::text(200, "Fails.")
+    }
+}
+
+fn torrents_info(ctx: &WebContext, request: &WebRequest) -> WebResponse {
+    let query = &request.query;
+    let hashes = query.get("hashes").map_or("all", |h| h.as_str());
+    let torrents: Vec<Value> = select(ctx, hashes)
+        .iter()
+        .filter(|t| matches_filter(t, query.get("filter").map_or("all", |f| f.as_str())))
+        .filter(|t| query.get("category").is_none_or(|c| *c == t.category))
+        .filter(|t| query.get("tag").is_none_or(|tag| t.tags.contains(tag)))
+        .map(torrent_info)
+        .collect();
+    WebResponse::json(200, &Value::Array(torrents))
+}
+
+fn torrent_files(ctx: &WebContext, hash: &str) -> WebResponse {
+    let Some(t) = find(ctx, hash) else {
+        return not_found();
+    };
+    let response = ctx.control(ControlRequest::Files { hash: t.hash });
+    let files: Vec<Value> = response
+        .data
+        .as_array()
+        .cloned()
+        .unwrap_or_default()
+        .iter()
+        .enumerate()
+        .map(|(index, file)| {
+            // Do not download, normal and maximal priority
+            let priority = match file["priority"].as_str() {
+                Some("skip") => 0,
+                Some("high") => 7,
+                _ => 1,
+            };
+            serde_json::json!({"index": index, "name": file["path"], "priority": priority})
+        })
+        .collect();
+    WebResponse::json(200, &Value::Array(files))
+}
+
+fn set_category(ctx: &WebContext, form: &[FormField]) -> WebResponse {
+    let category = text_field(form, "category");
+    if !category.is_empty() && !categories(ctx).iter().any(|c| c.name == category) {
+        return WebResponse::text(409, "Incorrect category name");
+    }
+    for t in select(ctx, &text_field(form, "hashes")) {
+        ctx.control(ControlRequest::SetCategory {
+            hash: t.hash,
+            category: category.clone(),
+        });
+    }
+    ok()
+}
+
+fn create_category(ctx: &WebContext, form: &[FormField]) -> WebResponse {
+    let name = text_field(form, "category").trim().to_owned();
+    if name.is_empty() {
+        return WebResponse::text(400, "Category name is empty");
+    }
+    if categories(ctx).iter().any(|c| c.name == name) {
+        return WebResponse::text(409, "Category name is already in use");
+    }
+    let category = Category {
+        name,
+        save_path: text_field(form, "savePath"),
+        ..Default::default()
+    };
+    if ctx.tx.send(Message::SaveCategory(None, category)).is_err() {
+        return WebResponse::text(500, "Shutting down");
+    }
+    ok()
+}
+
+/// Answers a request of the qBittorrent Web API.
+pub fn handle(ctx: &WebContext, request: &WebRequest) -> WebResponse {
+    let endpoint = request.path.strip_prefix(API_PATH).unwrap_or("");
+    // Forms of other sites can POST too, so like qBittorrent's CSRF
+    // protection, requests from their pages are refused
+    if !request.is_same_origin() {
+        return WebResponse::text(401, "Unauthorized");
+    }
+    if POST_ENDPOINTS.contains(&endpoint) && request.method != "POST" {
+        return WebResponse::text(405, "Method Not Allowed").with_header("Allow", "POST");
+    }
+    if endpoint == "auth/login" {
+        return login(ctx, request);
+    }
+    if !is_logged_in(ctx, request) {
+        return WebResponse::text(403, "Forbidden");
+    }
+
+    let form = request.form();
+    // Parameters come in the query string or the form
+    let param = |name: &str| {
+        request
+            .query
+            .get(name)
+            .cloned()
+            .unwrap_or_else(|| text_field(&form, name))
+    };
+    match endpoint {
+        "auth/logout" => {
+            if let Some(sid) = session_cookie(request) {
+                ctx.qbittorrent.sessions.lock().unwrap().remove(sid);
+            }
+            ok()
+        }
+        "app/version" => WebResponse::text(200, APP_VERSION),
+        "app/webapiVersion" => WebResponse::text(200, API_VERSION),
+        "app/preferences" => {
+            let session = ctx.control(ControlRequest::Session).data;
+            WebResponse::json(
+                200,
+                &serde_json::json!({
+                    "save_path": session["save_path"],
+                    "dl_limit": session["download_limit"],
+                    "up_limit": session["upload_limit"],
+                    "max_ratio_enabled": false,
+                    "max_ratio": -1,
+                    "max_seeding_time_enabled": false,
+                    "max_seeding_time": -1,
+                    "queueing_enabled": false,
+                    "dht": true,
+                }),
+            )
+        }
+        "torrents/info" => torrents_info(ctx, request),
+        "torrents/properties" => match find(ctx, &param("hash")) {
+            Some(t) => WebResponse::json(200, &torrent_properties(&t)),
+            None => not_found(),
+        },
+        "torrents/files" => torrent_files(ctx, &param("hash")),
+        "torrents/add" => add(ctx, &form),
+        "torrents/delete" => {
+            let delete_files = param("deleteFiles") == "true";
+            for t in select(ctx, &param("hashes")) {
+                ctx.control(ControlRequest::Remove {
+                    hash: t.hash,
+                    delete_files,
+                });
+            }
+            ok()
+        }
+        "torrents/setCategory" => set_category(ctx, &form),
+        "torrents/createCategory" => create_category(ctx, &form),
+        "torrents/categories" => {
+            let categories: serde_json::Map<String, Value> = categories(ctx)
+                .into_iter()
+                .map(|c| {
+                    let value = serde_json::json!({"name": c.name, "savePath": c.save_path});
+                    (c.name, value)
+                })
+                .collect();
+            WebResponse::json(200, &Value::Object(categories))
+        }
+        _ => WebResponse::text(404, "Not found"),
+    }
+}
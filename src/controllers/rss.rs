@@ -0,0 +1,361 @@
+use std::{
+    collections::HashSet,
+    fs,
+    io::Read,
+    path::{Path, PathBuf},
+    sync::LazyLock,
+    time::Duration,
+};
+
+use quick_xml::{
+    events::{BytesStart, Event},
+    Reader,
+};
+use regex::{Regex, RegexBuilder};
+
+use crate::models::{
+    feed::{Feed, FeedItem},
+    settings::RssRule,
+};
+
+pub const RSS_HISTORY_FILE: &str = "rss_history.json";
+const HTTP_TIMEOUT: Duration = Duration::from_secs(30);
+const MAX_TORRENT_FILE_SIZE: u64 = 50 * 1024 * 1024;
+
+/// `S01E05` or `1x05` in a title.
+static EPISODE_RE: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"(?i)\bS(\d{1,2})\s?E(\d{1,3})|\b(\d{1,2})x(\d{2,3})\b").unwrap());
+/// `S01E05` or `S01` in an episode filter.
+static EPISODE_REF_RE: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"(?i)^S(\d{1,2})(?:E(\d{1,3}))?$").unwrap());
+
+fn local_name(e: &BytesStart) -> String {
+    String::from_utf8_lossy(e.local_name().as_ref()).to_lowercase()
+}
+
+fn attribute(e: &BytesStart, name: &str) -> Option<String> {
+    e.try_get_attribute(name)
+        .ok()
+        .flatten()
+        .and_then(|a| a.unescape_value().ok())
+        .map(|v| v.to_string())
+}
+
+struct ItemBuilder {
+    item: FeedItem,
+    link: String,
+    enclosure: String,
+}
+
+impl ItemBuilder {
+    fn attributes(&mut self, name: &str, e: &BytesStart) {
+        match name {
+            // RSS
+            "enclosure" => {
+                if let Some(url) = attribute(e, "url") {
+                    self.enclosure = url;
+                }
+                if let Some(size) = attribute(e, "length").and_then(|l| l.parse().ok()) {
+                    self.item.size = Some(size);
+                }
+            }
+            // Atom
+            "link" => {
+                if let Some(href) = attribute(e, "href") {
+                    if attribute(e, "rel").as_deref() == Some("enclosure") {
+                        self.enclosure = href;
+                        if let Some(size) = attribute(e, "length").and_then(|l| l.parse().ok()) {
+                            self.item.size = Some(size);
+                        }
+                    } else if self.link.is_empty() {
+                        self.link = href;
+                    }
+                }
+            }
+            // Torznab/Newznab
+            "attr" if attribute(e, "name").as_deref() == Some("size") => {
+                if let Some(size) = attribute(e, "value").and_then(|v| v.parse().ok()) {
+                    self.item.size = Some(size);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    fn text(&mut self, name: &str, text: String) {
+        match name {
+            "title" => self.item.title = text,
+            "link" => self.link = text,
+            "guid" | "id" => self.item.guid = text,
+            "size" | "contentlength" => {
+                if let Ok(size) = text.parse() {
+                    self.item.size = Some(size);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    fn build(self) -> FeedItem {
+        let mut item = self.item;
+        item.url = if self.enclosure.is_empty() {
+            self.link
+        } else {
+            self.enclosure
+        };
+        if item.guid.is_empty() {
+            item.guid = item.url.clone();
+        }
+        item
+    }
+}
+
+/// Parses an RSS or Atom document into the feed's title and its items.
+pub fn parse_feed(xml: &str) -> Result<(String, Vec<FeedItem>), String> {
+    let mut reader = Reader::from_str(xml);
+    reader.config_mut().trim_text(true);
+
+    let mut path: Vec<String> = vec![];
+    let mut title = String::new();
+    let mut items = vec![];
+    let mut builder: Option<ItemBuilder> = None;
+    let mut is_feed = false;
+    loop {
+        let event = reader.read_event().map_err(|e| e.to_string())?;
+        let text = match event {
+            Event::Start(e) => {
+                let name = local_name(&e);
+                if path.is_empty() {
+                    is_feed = name == "rss" || name == "feed" || name == "rdf";
+                }
+                if name == "item" || name == "entry" {
+                    builder = Some(ItemBuilder {
+                        item: FeedItem::default(),
+                        link: "".to_owned(),
+                        enclosure: "".to_owned(),
+                    });
+                } else if let Some(builder) = &mut builder {
+                    builder.attributes(&name, &e);
+                }
+                path.push(name);
+                continue;
+            }
+            Event::Empty(e) => {
+                if let Some(builder) = &mut builder {
+                    builder.attributes(&local_name(&e), &e);
+                }
+                continue;
+            }
+            Event::End(_) => {
+                let name = path.pop();
+                if name.as_deref() == Some("item") || name.as_deref() == Some("entry") {
+                    if let Some(builder) = builder.take() {
+                        items.push(builder.build());
+                    }
+                }
+                continue;
+            }
+            Event::Text(t) => t.unescape().map_err(|e| e.to_string())?.to_string(),
+            Event::CData(c) => String::from_utf8_lossy(&c.into_inner()).to_string(),
+            Event::Eof => break,
+            _ => continue,
+        };
+
+        let name = path.last().map(String::as_str).unwrap_or("");
+        match &mut builder {
+            Some(builder) => builder.text(name, text),
+            None => {
+                if name == "title" && title.is_empty() {
+                    title = text;
+                }
+            }
+        }
+    }
+
+    if !is_feed {
+        return Err("Not an RSS or Atom feed".to_owned());
+    }
+    Ok((title, items))
+}
+
+pub fn fetch_feed(url: &str) -> Feed {
+    let res = ureq::get(url)
+        .timeout(HTTP_TIMEOUT)
+        .call()
+        .map_err(|e| e.to_string())
+        .and_then(|res| res.into_string().map_err(|e| e.to_string()))
+        .and_then(|xml| parse_feed(&xml));
+    match res {
+        Ok((title, items)) => Feed {
+            url: url.to_owned(),
+            title,
+            items,
+            error: None,
+        },
+        Err(e) => Feed {
+            url: url.to_owned(),
+            error: Some(e),
+            ..Default::default()
+        },
+    }
+}
+
+/// Downloads the contents of a .torrent file.
+pub fn fetch_torrent(url: &str) -> Result<Vec<u8>, String> {
+    let res = ureq::get(url)
+        .timeout(HTTP_TIMEOUT)
+        .call()
+        .map_err(|e| e.to_string())?;
+    let mut buf = vec![];
+    res.into_reader()
+        .take(MAX_TORRENT_FILE_SIZE)
+        .read_to_end(&mut buf)
+        .map_err(|e| e.to_string())?;
+    Ok(buf)
+}
+
+/// Writes a downloaded .torrent file into `dir`, replacing the previous
+/// download.
+pub fn save_torrent(buf: &[u8], dir: &Path) -> Result<PathBuf, String> {
+    fs::create_dir_all(dir).map_err(|e| e.to_string())?;
+    let path = dir.join("rss_download.torrent");
+    fs::write(&path, buf).map_err(|e| e.to_string())?;
+    Ok(path)
+}
+
+/// Finds the season and episode numbers in a title, e.g. `S01E05` or `1x05`.
+pub fn parse_episode(title: &str) -> Option<(u32, u32)> {
+    let caps = EPISODE_RE.captures(title)?;
+    let season = caps.get(1).or(caps.get(3))?.as_str().parse().ok()?;
+    let episode = caps.get(2).or(caps.get(4))?.as_str().parse().ok()?;
+    Some((season, episode))
+}
+
+/// Parses `S01E05` into `(1, Some(5))` or a whole season `S01` into `(1, None)`.
+fn parse_episode_ref(s: &str) -> Option<(u32, Option<u32>)> {
+    let caps = EPISODE_REF_RE.captures(s.trim())?;
+    let season = caps.get(1)?.as_str().parse().ok()?;
+    let episode = match caps.get(2) {
+        Some(e) => Some(e.as_str().parse().ok()?),
+        None => None,
+    };
+    Some((season, episode))
+}
+
+fn episode_term_matches(term: &str, episode: (u32, u32)) -> bool {
+    match term.split_once('-') {
+        None => match parse_episode_ref(term) {
+            Some((season, Some(e))) => episode == (season, e),
+            Some((season, None)) => episode.0 == season,
+            None => false,
+        },
+        Some((from, to)) => {
+            let from = match parse_episode_ref(from) {
+                Some((season, e)) => (season, e.unwrap_or(0)),
+                None => return false,
+            };
+            let to = if to.trim().is_empty() {
+                (u32::MAX, u32::MAX)
+            } else {
+                match parse_episode_ref(to) {
+                    Some((season, e)) => (season, e.unwrap_or(u32::MAX)),
+                    None => return false,
+                }
+            };
+            from <= episode && episode <= to
+        }
+    }
+}
+
+/// Checks the episode in `title` against a filter of `;` separated terms,
+/// each a single episode (`S01E05`), a season (`S01`), a range
+/// (`S01E05-S02E03`) or an open range (`S01E05-`).
+pub fn episode_matches(filter: &str, title: &str) -> bool {
+    if filter.trim().is_empty() {
+        return true;
+    }
+    let episode = match parse_episode(title) {
+        Some(episode) => episode,
+        None => return false,
+    };
+    filter
+        .split(';')
+        .map(str::trim)
+        .filter(|term| !term.is_empty())
+        .any(|term| episode_term_matches(term, episode))
+}
+
+fn regex_matches(pattern: &str, text: &str) -> Option<bool> {
+    match RegexBuilder::new(pattern).case_insensitive(true).build() {
+        Ok(re) => Some(re.is_match(text)),
+        Err(e) => {
+            log::error!("Invalid RSS rule expression {}: {}", pattern, e);
+            None
+        }
+    }
+}
+
+pub fn rule_matches(rule: &RssRule, feed_url: &str, item: &FeedItem) -> bool {
+    if !rule.enabled || (!rule.feeds.is_empty() && !rule.feeds.iter().any(|f| f == feed_url)) {
+        return false;
+    }
+    if !rule.include.trim().is_empty() && regex_matches(&rule.include, &item.title) != Some(true) {
+        return false;
+    }
+    if !rule.exclude.trim().is_empty() && regex_matches(&rule.exclude, &item.title) != Some(false) {
+        return false;
+    }
+    if !episode_matches(&rule.episode_filter, &item.title) {
+        return false;
+    }
+    if rule.min_size_mb > 0 || rule.max_size_mb > 0 {
+        // Items of unknown size can't satisfy the bounds
+        let size_mb = match item.size {
+            Some(size) => size as f64 / 1_000_000.0,
+            None => return false,
+        };
+        if size_mb < rule.min_size_mb as f64
+            || (rule.max_size_mb > 0 && size_mb > rule.max_size_mb as f64)
+        {
+            return false;
+        }
+    }
+    true
+}
+
+/// Picks the items not downloaded before which match a rule, together with
+/// the first rule they match.
+pub fn select_items(
+    feeds: &[Feed],
+    rules: &[RssRule],
+    history: &HashSet<String>,
+) -> Vec<(FeedItem, RssRule)> {
+    let mut seen = HashSet::new();
+    let mut selected = vec![];
+    for feed in feeds {
+        for item in &feed.items {
+            if item.url.is_empty() || history.contains(&item.guid) || seen.contains(&item.guid) {
+                continue;
+            }
+            if let Some(rule) = rules.iter().find(|r| rule_matches(r, &feed.url, item)) {
+                seen.insert(item.guid.clone());
+                selected.push((item.clone(), rule.clone()));
+            }
+        }
+    }
+    selected
+}
+
+pub fn load_history(data_dir: &Path) -> HashSet<String> {
+    fs::read_to_string(data_dir.join(RSS_HISTORY_FILE))
+        .ok()
+        .and_then(|contents| serde_json::from_str(&contents).ok())
+        .unwrap_or_default()
+}
+
+pub fn save_history(data_dir: &Path, history: &HashSet<String>) -> bool {
+    let mut guids: Vec<&String> = history.iter().collect();
+    guids.sort();
+    let contents = serde_json::to_string_pretty(&guids).expect("Failed to serialize history");
+    fs::write(data_dir.join(RSS_HISTORY_FILE), contents).is_ok()
+}
@@ -2,15 +2,23 @@ use egui_toast::Toasts;
 
 use crate::{
     models::{
+        event::TorrentEvent,
         file,
-        message::AddTorrentKind,
+        ip_filter::IpRange,
+        log::{LogLevel, LogSource},
+        message::{AddTorrentKind, BatchAction, QueueMove},
+        network::{self, NetInterface},
         peer,
+        settings::{ProxyKind, ProxySettings, Settings},
         torrent::{Torrent, TorrentFilePriority, TorrentPieceState, TorrentState},
+        tracker::{self, TrackerStatus},
     },
     toasts,
 };
 use std::{
+    collections::HashMap,
     ffi::{c_int, CStr, CString},
+    net::SocketAddr,
     sync::{Arc, Mutex},
 };
 include!("../../bindings.rs");
@@ -20,6 +28,8 @@ const trnt_remove_success_msg: &str = "Removed torrent.";
 const trnt_remove_fail_msg: &str = "Failed to remove torrent.";
 const trnt_set_file_priority_fail_msg: &str = "Failed to change priority.";
 const trnt_set_state_fail_msg: &str = "Failed to pause/resume torrent state.";
+const trnt_move_storage_fail_msg: &str = "Failed to move torrent.";
+const trnt_disconnect_peer_fail_msg: &str = "Failed to disconnect peer.";
 
 pub fn refresh(torrents: Arc<Mutex<Vec<Torrent>>>) {
     let torrents_count = unsafe { get_count() as usize };
@@ -77,12 +87,18 @@ pub fn refresh(torrents: Arc<Mutex<Vec<Torrent>>>) {
                 .expect("Failed to process C str")
                 .to_string()
         };
-        torrent.hash = unsafe {
+        let hash = unsafe {
             CStr::from_ptr(info.hash)
                 .to_str()
                 .expect("Failed to process C str")
                 .to_string()
         };
+        // The torrent is new at this index, the trackers of the previous one
+        // are replaced right away
+        if torrent.hash != hash {
+            torrent.trackers = fetch_trackers(index);
+            torrent.hash = hash;
+        }
         torrent.comment = unsafe {
             CStr::from_ptr(info.comment)
                 .to_str()
@@ -103,49 +119,158 @@ pub fn refresh(torrents: Arc<Mutex<Vec<Torrent>>>) {
         torrent.total_ses_upload = info.total_ses_upload;
 
         torrent.eta = info.eta;
-
+        torrent.num_files = info.num_files;
+        torrent.added_time = info.added_time;
+        torrent.download_limit = info.download_limit;
+        torrent.upload_limit = info.upload_limit;
+        torrent.error = unsafe {
+            CStr::from_ptr(info.error)
+                .to_str()
+                .expect("Failed to process C str")
+                .to_string()
+        };
         unsafe {
             free_torrent_info(info);
         }
     }
 }
 
-pub fn add_torrent(path: String, kind: AddTorrentKind, toasts: Arc<Mutex<Toasts>>) {
-    let downloads_dir = dirs::download_dir()
+pub fn default_save_path() -> String {
+    dirs::download_dir()
         .expect("Failed to get downloads dir.")
         .to_str()
         .expect("Failed to convert to string")
-        .to_owned();
-    let downloads_dir_cstr = CString::new(downloads_dir.clone()).expect("Failed to create CString");
+        .to_owned()
+}
+
+pub fn add_torrent(
+    path: String,
+    kind: AddTorrentKind,
+    save_path: String,
+    paused: bool,
+    toasts: Arc<Mutex<Toasts>>,
+) -> Option<String> {
+    let save_path_cstr = CString::new(save_path).expect("Failed to create CString");
     let path_cstr = CString::new(path).expect("Failed to create CString");
     let mut toasts = toasts.lock().unwrap();
 
     let res = match kind {
         AddTorrentKind::MagnetUrl => {
             let magnet_url_cstr = path_cstr;
-            unsafe { add_magnet_url(magnet_url_cstr.as_ptr(), downloads_dir_cstr.as_ptr()) }
+            unsafe { add_magnet_url(magnet_url_cstr.as_ptr(), save_path_cstr.as_ptr(), paused) }
         }
         AddTorrentKind::File => {
             let file_path_cstr = path_cstr;
-            unsafe { add_file(file_path_cstr.as_ptr(), downloads_dir_cstr.as_ptr()) }
+            unsafe { add_file(file_path_cstr.as_ptr(), save_path_cstr.as_ptr(), paused) }
         }
     };
 
     if res {
         toasts::success(&mut toasts, trnt_add_success_msg);
+        let hash = unsafe {
+            let index = get_count() - 1;
+            CStr::from_ptr(get_torrent_hash(index))
+                .to_str()
+                .expect("Failed to process C str")
+                .to_string()
+        };
+        Some(hash)
     } else {
         toasts::error(&mut toasts, trnt_add_fail_msg);
+        None
     }
 }
 
-pub fn remove(index: usize, toasts: Arc<Mutex<Toasts>>) {
+pub fn remove(index: usize, toasts: Arc<Mutex<Toasts>>) -> bool {
     let mut toasts = toasts.lock().unwrap();
-    let res = unsafe { torrent_remove(index as c_int) };
+    let res = unsafe { torrent_remove(index as c_int, false) };
     if res {
         toasts::success(&mut toasts, trnt_remove_success_msg);
     } else {
         toasts::error(&mut toasts, trnt_remove_fail_msg);
     }
+    res
+}
+
+/// Applies the action to the torrents, toasting once for the whole batch.
+/// Returns the indices the action succeeded on.
+pub fn batch(indices: &[usize], action: &BatchAction, toasts: Arc<Mutex<Toasts>>) -> Vec<usize> {
+    let num_torrents = indices.len();
+    let mut indices = indices.to_vec();
+    if let BatchAction::Remove { .. } = action {
+        // Removing shifts the torrents after the removed one
+        indices.sort_unstable_by(|a, b| b.cmp(a));
+    }
+    let save_path_cstr = match action {
+        BatchAction::MoveStorage(save_path) => {
+            Some(CString::new(save_path.as_str()).expect("Failed to create CString"))
+        }
+        _ => None,
+    };
+
+    let mut succeeded = vec![];
+    for index in indices {
+        let index_c = index as c_int;
+        let res = unsafe {
+            match action {
+                BatchAction::Pause => torrent_pause(index_c),
+                BatchAction::Resume => torrent_resume(index_c),
+                BatchAction::Remove { delete_files } => torrent_remove(index_c, *delete_files),
+                BatchAction::Recheck => torrent_recheck(index_c),
+                BatchAction::Reannounce => torrent_reannounce(index_c),
+                BatchAction::MoveStorage(_) => torrent_move_storage(
+                    index_c,
+                    save_path_cstr.as_ref().expect("Missing save path").as_ptr(),
+                ),
+                BatchAction::SetLimits(download_limit, upload_limit) => {
+                    torrent_set_limits(index_c, *download_limit, *upload_limit)
+                }
+                BatchAction::Queue(direction) => torrent_queue_move(
+                    index_c,
+                    match direction {
+                        QueueMove::Top => 0,
+                        QueueMove::Up => 1,
+                        QueueMove::Down => 2,
+                        QueueMove::Bottom => 3,
+                    },
+                ),
+                // Torrenter's own metadata, not libtorrent's
+                BatchAction::SetCategory(_)
+                | BatchAction::AddTag(_)
+                | BatchAction::RemoveTag(_) => true,
+            }
+        };
+        if res {
+            succeeded.push(index);
+        }
+    }
+
+    let num_failed = num_torrents - succeeded.len();
+    let mut toasts = toasts.lock().unwrap();
+    if num_failed > 0 {
+        toasts::error(
+            &mut toasts,
+            format!(
+                "Failed to update {} of {} torrents.",
+                num_failed, num_torrents
+            ),
+        );
+    } else if let BatchAction::Remove { .. } = action {
+        toasts::success(
+            &mut toasts,
+            format!("Removed {} torrents.", succeeded.len()),
+        );
+    }
+    succeeded
+}
+
+pub fn move_storage(index: usize, save_path: &str, toasts: Arc<Mutex<Toasts>>) {
+    let mut toasts = toasts.lock().unwrap();
+    let save_path_cstr = CString::new(save_path).expect("Failed to create CString");
+    let res = unsafe { torrent_move_storage(index as c_int, save_path_cstr.as_ptr()) };
+    if !res {
+        toasts::error(&mut toasts, trnt_move_storage_fail_msg);
+    }
 }
 
 pub fn toggle_stream_mode(index: usize, toasts: Arc<Mutex<Toasts>>) {
@@ -161,7 +286,7 @@ pub fn set_file_priority(
     f_index: usize,
     priority: TorrentFilePriority,
     toasts: Arc<Mutex<Toasts>>,
-) {
+) -> bool {
     let mut toasts = toasts.lock().unwrap();
     let lt_download_priority = match priority {
         TorrentFilePriority::Skip => 0,
@@ -179,6 +304,7 @@ pub fn set_file_priority(
     if !res {
         toasts::error(&mut toasts, trnt_set_file_priority_fail_msg);
     }
+    res
 }
 
 pub fn toggle_state(index: usize, state: TorrentState, toasts: Arc<Mutex<Toasts>>) {
@@ -214,12 +340,26 @@ pub fn fetch_peers(index: usize, torrents: Arc<Mutex<Vec<Torrent>>>) {
             let download_rate = c_peer.download_rate;
             let upload_rate = c_peer.upload_rate;
             let progress = c_peer.progress;
+            let connection = match c_peer.connection_type {
+                1 => peer::ConnectionType::Utp,
+                2 => peer::ConnectionType::WebSeed,
+                _ => peer::ConnectionType::Tcp,
+            };
             let peer = peer::Peer {
                 ip_address,
+                port: c_peer.port as u16,
                 progress,
                 client,
                 download_rate,
                 upload_rate,
+                connection,
+                is_encrypted: c_peer.is_encrypted,
+                sources: peer::PeerSource::from_bits(c_peer.source),
+                flags: peer::PeerFlags::from_bits(c_peer.flags),
+                total_download: c_peer.total_download,
+                total_upload: c_peer.total_upload,
+                relevance: c_peer.relevance,
+                rtt: c_peer.rtt,
             };
             peers.push(peer);
         }
@@ -227,6 +367,65 @@ pub fn fetch_peers(index: usize, torrents: Arc<Mutex<Vec<Torrent>>>) {
     }
 }
 
+pub fn disconnect_peer(index: usize, ip: &str, port: u16, toasts: Arc<Mutex<Toasts>>) {
+    let mut toasts = toasts.lock().unwrap();
+    let ip_cstr = CString::new(ip).expect("Failed to create CString");
+    let res = unsafe { torrent_disconnect_peer(index as c_int, ip_cstr.as_ptr(), port as c_int) };
+    if !res {
+        toasts::error(&mut toasts, trnt_disconnect_peer_fail_msg);
+    }
+}
+
+/// Connects to the peers, returning those which failed.
+pub fn connect_peers(index: usize, endpoints: &[SocketAddr]) -> Vec<SocketAddr> {
+    endpoints
+        .iter()
+        .filter(|endpoint| {
+            let ip_cstr =
+                CString::new(endpoint.ip().to_string()).expect("Failed to create CString");
+            let port = endpoint.port() as c_int;
+            !unsafe { torrent_connect_peer(index as c_int, ip_cstr.as_ptr(), port) }
+        })
+        .copied()
+        .collect()
+}
+
+pub fn fetch_trackers(index: usize) -> Vec<tracker::Tracker> {
+    let mut num_trackers: c_int = 0;
+    let num_trackers_ptr = &mut num_trackers;
+    let mut trackers = vec![];
+    unsafe {
+        let c_trackers = get_trackers(index as c_int, num_trackers_ptr);
+        for i in 0..num_trackers {
+            let c_tracker = *c_trackers.add(i as usize);
+            let url = CStr::from_ptr(c_tracker.url)
+                .to_str()
+                .expect("Failed to process C str")
+                .to_string();
+            let message = CStr::from_ptr(c_tracker.message)
+                .to_str()
+                .expect("Failed to process C str")
+                .to_string();
+            let status = match c_tracker.status {
+                1 => TrackerStatus::Working,
+                2 => TrackerStatus::Updating,
+                3 => TrackerStatus::Error,
+                _ => TrackerStatus::NotContacted,
+            };
+            trackers.push(tracker::Tracker {
+                tier: c_tracker.tier,
+                url,
+                status,
+                num_peers: c_tracker.num_peers,
+                num_seeds: c_tracker.num_seeds,
+                message,
+            });
+        }
+        free_trackers(c_trackers, num_trackers);
+    }
+    trackers
+}
+
 pub fn fetch_files(index: usize, torrents: Arc<Mutex<Vec<Torrent>>>) {
     let mut num_files: c_int = 0;
     let num_files_ptr = &mut num_files;
@@ -254,3 +453,195 @@ pub fn fetch_files(index: usize, torrents: Arc<Mutex<Vec<Torrent>>>) {
         free_files(c_files, num_files);
     }
 }
+
+/// Takes the messages of the bridge and the logged alerts.
+pub fn fetch_log_messages() -> Vec<(LogLevel, LogSource, String)> {
+    let mut num_messages: c_int = 0;
+    let num_messages_ptr = &mut num_messages;
+    let mut messages = vec![];
+    unsafe {
+        let c_messages = pop_log_messages(num_messages_ptr);
+        for i in 0..num_messages {
+            let c_message = *c_messages.add(i as usize);
+            let message = CStr::from_ptr(c_message.message)
+                .to_string_lossy()
+                .to_string();
+            let level = match c_message.severity {
+                0 => LogLevel::Info,
+                1 => LogLevel::Warning,
+                _ => LogLevel::Error,
+            };
+            let source = if c_message.is_alert {
+                LogSource::Alert
+            } else {
+                LogSource::Session
+            };
+            messages.push((level, source, message));
+        }
+        free_log_messages(c_messages, num_messages);
+    }
+    messages
+}
+
+/// Replaces the session's IP filter by one blocking the ranges.
+pub fn set_ip_filter(ranges: &[IpRange]) -> bool {
+    let addrs: Vec<(CString, CString)> = ranges
+        .iter()
+        .map(|r| {
+            (
+                CString::new(r.first.to_string()).expect("Failed to create CString"),
+                CString::new(r.last.to_string()).expect("Failed to create CString"),
+            )
+        })
+        .collect();
+    let rules: Vec<IpFilterRule> = addrs
+        .iter()
+        .map(|(first, last)| IpFilterRule {
+            first: first.as_ptr(),
+            last: last.as_ptr(),
+        })
+        .collect();
+    unsafe { apply_ip_filter(rules.as_ptr(), rules.len() as c_int) }
+}
+
+pub fn fetch_blocked_peer_count() -> i64 {
+    unsafe { get_blocked_peer_count() as i64 }
+}
+
+/// Listens and connects on the interface, by name or address, or on any if
+/// empty.
+pub fn bind_interface(interface: &str) -> bool {
+    let listen_interfaces =
+        CString::new(network::listen_interfaces(interface)).expect("Failed to create CString");
+    let outgoing_interfaces = CString::new(interface.trim()).expect("Failed to create CString");
+    unsafe { set_network_interfaces(listen_interfaces.as_ptr(), outgoing_interfaces.as_ptr()) }
+}
+
+pub fn pause_session() {
+    unsafe { session_pause() }
+}
+
+pub fn resume_session() {
+    unsafe { session_resume() }
+}
+
+pub fn fetch_interfaces() -> Vec<NetInterface> {
+    let mut num_addresses: c_int = 0;
+    let mut interfaces = vec![];
+    unsafe {
+        let c_addresses = get_interface_addresses(&mut num_addresses);
+        for i in 0..num_addresses {
+            let c_address = *c_addresses.add(i as usize);
+            let name = CStr::from_ptr(c_address.name)
+                .to_str()
+                .expect("Failed to process C str")
+                .to_string();
+            let address = CStr::from_ptr(c_address.address)
+                .to_str()
+                .expect("Failed to process C str");
+            // Scoped IPv6 addresses, e.g. `fe80::1%eth0`, are left out
+            if let Ok(address) = address.parse() {
+                interfaces.push(NetInterface {
+                    name,
+                    address,
+                    is_up: c_address.is_up,
+                });
+            }
+        }
+        free_interface_addresses(c_addresses, num_addresses);
+    }
+    interfaces
+}
+
+/// Reads the counters of the last session stats alert, by name.
+pub fn fetch_session_stats() -> HashMap<String, i64> {
+    let mut num_counters: c_int = 0;
+    let num_counters_ptr = &mut num_counters;
+    let mut counters = HashMap::new();
+    unsafe {
+        let c_counters = get_session_stats(num_counters_ptr);
+        for i in 0..num_counters {
+            let c_counter = *c_counters.add(i as usize);
+            let name = CStr::from_ptr(c_counter.name)
+                .to_str()
+                .expect("Failed to process C str")
+                .to_string();
+            counters.insert(name, c_counter.value);
+        }
+        free_session_stats(c_counters, num_counters);
+    }
+    counters
+}
+
+pub fn fetch_events() -> Vec<TorrentEvent> {
+    let mut num_events: c_int = 0;
+    let num_events_ptr = &mut num_events;
+    let mut events = vec![];
+    unsafe {
+        let c_events = pop_events(num_events_ptr);
+        for i in 0..num_events {
+            let c_event = *c_events.add(i as usize);
+            let hash = CStr::from_ptr(c_event.hash)
+                .to_str()
+                .expect("Failed to process C str")
+                .to_string();
+            events.push(match c_event.kind {
+                0 => TorrentEvent::Added(hash),
+                _ => TorrentEvent::Finished(hash),
+            });
+        }
+        free_events(c_events, num_events);
+    }
+    events
+}
+
+/// Calls `f` with the proxy settings as the bridge takes them.
+fn with_proxy_config<T>(settings: &ProxySettings, f: impl FnOnce(&ProxyConfig) -> T) -> T {
+    let kind = match settings.kind {
+        _ if !settings.is_enabled() => 0,
+        ProxyKind::None => 0,
+        ProxyKind::Socks4 => 1,
+        ProxyKind::Socks5 => 2,
+        ProxyKind::Http => 3,
+    };
+    let cstr = |s: &str| CString::new(s.trim()).expect("Failed to create CString");
+    let host = cstr(&settings.host);
+    let username = cstr(&settings.username);
+    let password = cstr(&settings.password);
+    f(&ProxyConfig {
+        kind,
+        host: host.as_ptr(),
+        port: settings.port as c_int,
+        username: username.as_ptr(),
+        password: password.as_ptr(),
+        proxy_hostnames: settings.proxy_hostnames,
+        proxy_peer_connections: settings.proxy_peer_connections,
+        proxy_tracker_connections: settings.proxy_tracker_connections,
+    })
+}
+
+/// Routes the session's connections through the proxy, or directly if it's
+/// disabled.
+pub fn apply_proxy(settings: &ProxySettings) -> bool {
+    with_proxy_config(settings, |proxy| unsafe { set_proxy(proxy) })
+}
+
+/// Starts the session with the torrents of the resume files, already
+/// behind the proxy and bound to the interface of the settings.
+pub fn start_session(resume_dir: &str, session_file: &str, settings: &Settings) {
+    let cstr = |s: &str| CString::new(s).expect("Failed to create CString");
+    let resume_dir = cstr(resume_dir);
+    let session_file = cstr(session_file);
+    let interface = &settings.network.interface;
+    let listen_interfaces = cstr(&network::listen_interfaces(interface));
+    let outgoing_interfaces = cstr(interface.trim());
+    with_proxy_config(&settings.proxy, |proxy| unsafe {
+        initiate(
+            resume_dir.as_ptr(),
+            session_file.as_ptr(),
+            proxy,
+            listen_interfaces.as_ptr(),
+            outgoing_interfaces.as_ptr(),
+        )
+    })
+}
@@ -0,0 +1,326 @@
+use std::sync::Mutex;
+
+use base64::{engine::general_purpose::STANDARD, Engine};
+use serde_json::{Map, Value};
+
+use super::{
+    rss,
+    web::{self, WebContext, WebRequest, WebResponse},
+};
+use crate::models::{
+    control::{ControlRequest, ControlResponse},
+    torrent::{Torrent, TorrentState},
+};
+
+pub const RPC_PATH: &str = "/transmission/rpc";
+const SESSION_ID_HEADER: &str = "X-Transmission-Session-Id";
+const RPC_VERSION: i64 = 17;
+const RPC_VERSION_MINIMUM: i64 = 14;
+
+/// What the RPC requests share.
+pub struct RpcState {
+    /// Clients must send it back in a header, so that other sites can't make
+    /// browsers send requests.
+    pub session_id: String,
+    /// Hashes of the torrents, whose ids are their positions plus 1. Ids
+    /// outlive removed torrents, unlike indices in the session.
+    hashes: Mutex<Vec<String>>,
+}
+
+impl Default for RpcState {
+    fn default() -> Self {
+        Self {
+            session_id: web::random_token(),
+            hashes: Mutex::new(vec![]),
+        }
+    }
+}
+
+impl RpcState {
+    pub fn id(&self, hash: &str) -> i64 {
+        let mut hashes = self.hashes.lock().unwrap();
+        let index = match hashes.iter().position(|h| h == hash) {
+            Some(index) => index,
+            None => {
+                hashes.push(hash.to_owned());
+                hashes.len() - 1
+            }
+        };
+        index as i64 + 1
+    }
+}
+
+/// Transmission's `tr_torrent_activity`.
+fn status(torrent: &Torrent) -> i64 {
+    match torrent.state {
+        TorrentState::Paused => 0,
+        TorrentState::QueuedForChecking | TorrentState::CheckingResumeData => 1,
+        TorrentState::CheckingFiles => 2,
+        TorrentState::Downloading
+        | TorrentState::DownloadingMetaData
+        | TorrentState::Allocating => 4,
+        TorrentState::Finished | TorrentState::Seeding => 6,
+    }
+}
+
+/// A field of `torrent-get`, `None` if unsupported.
+fn field(torrent: &Torrent, id: i64, name: &str) -> Option<Value> {
+    let t = torrent;
+    let left = ((1.0 - t.progress as f64) * t.total_size as f64).round() as i64;
+    let value = match name {
+        "id" => id.into(),
+        "hashString" => t.hash.clone().into(),
+        "name" => t.name.clone().into(),
+        "status" => status(t).into(),
+        "percentDone" => t.progress.into(),
+        "totalSize" | "sizeWhenDone" => t.total_size.into(),
+        "leftUntilDone" => left.into(),
+        "haveValid" => (t.total_size - left).into(),
+        "rateDownload" => t.download_rate.into(),
+        "rateUpload" => t.upload_rate.into(),
+        "peersConnected" => t.num_peers.into(),
+        "peersSendingToUs" => t.num_seeds.into(),
+        "eta" => t.eta.max(-1).into(),
+        "uploadRatio" => t.ratio().into(),
+        "downloadDir" => t.save_path.clone().into(),
+        // TR_STAT_LOCAL_ERROR
+        "error" => if t.error.is_empty() { 0 } else { 3 }.into(),
+        "errorString" => t.error.clone().into(),
+        "addedDate" => t.added_time.into(),
+        "uploadedEver" => t.total_upload.into(),
+        "downloadedEver" => t.total_download.into(),
+        "isFinished" => (t.state == TorrentState::Paused && t.progress >= 1.0).into(),
+        "isStalled" => t.is_stalled().into(),
+        "labels" => t.tags.clone().into(),
+        "comment" => t.comment.clone().into(),
+        "downloadLimit" => (t.download_limit.max(0) / 1000).into(),
+        "downloadLimited" => (t.download_limit > 0).into(),
+        "uploadLimit" => (t.upload_limit.max(0) / 1000).into(),
+        "uploadLimited" => (t.upload_limit > 0).into(),
+        "secondsSeeding" => t.seeding_duration.into(),
+        "secondsDownloading" => (t.active_duration - t.seeding_duration).max(0).into(),
+        "metadataPercentComplete" => {
+            if t.state == TorrentState::DownloadingMetaData { 0 } else { 1 }.into()
+        }
+        "trackers" => t
+            .trackers
+            .iter()
+            .enumerate()
+            .map(|(i, tracker)| {
+                serde_json::json!({"id": i, "announce": tracker.url, "tier": tracker.tier})
+            })
+            .collect::<Vec<_>>()
+            .into(),
+        _ => return None,
+    };
+    Some(value)
+}
+
+fn is_selected(state: &RpcState, torrent: &Torrent, id: &Value) -> bool {
+    match id {
+        Value::Number(n) => n.as_i64() == Some(state.id(&torrent.hash)),
+        Value::String(hash) => hash.eq_ignore_ascii_case(&torrent.hash),
+        _ => false,
+    }
+}
+
+/// The torrents the `ids` argument refers to, all of them if missing.
+fn select(ctx: &WebContext, ids: &Value) -> Vec<Torrent> {
+    let state = &ctx.transmission;
+    ctx.torrents
+        .lock()
+        .unwrap()
+        .iter()
+        .filter(|t| !t.hash.is_empty())
+        .filter(|t| match ids {
+            Value::Null => true,
+            Value::String(s) if s == "recently-active" => t.download_rate > 0 || t.upload_rate > 0,
+            Value::Array(ids) => ids.iter().any(|id| is_selected(state, t, id)),
+            id => is_selected(state, t, id),
+        })
+        .cloned()
+        .collect()
+}
+
+fn into_result(response: ControlResponse) -> Result<Value, String> {
+    if response.ok {
+        Ok(response.data)
+    } else {
+        Err(response.error.unwrap_or_default())
+    }
+}
+
+fn torrent_get(ctx: &WebContext, args: &Value) -> Result<Value, String> {
+    let fields: Vec<&str> = args["fields"]
+        .as_array()
+        .ok_or("Missing fields")?
+        .iter()
+        .filter_map(|f| f.as_str())
+        .collect();
+    let torrents: Vec<Value> = select(ctx, &args["ids"])
+        .iter()
+        .map(|t| {
+            let id = ctx.transmission.id(&t.hash);
+            let map: Map<String, Value> = fields
+                .iter()
+                .filter_map(|name| Some((name.to_string(), field(t, id, name)?)))
+                .collect();
+            Value::Object(map)
+        })
+        .collect();
+    Ok(serde_json::json!({ "torrents": torrents }))
+}
+
+fn torrent_add(ctx: &WebContext, args: &Value) -> Result<Value, String> {
+    let save_path = args["download-dir"].as_str().unwrap_or("").to_owned();
+    let paused = args["paused"].as_bool().unwrap_or(false);
+    let response = if let Some(metainfo) = args["metainfo"].as_str() {
+        let metainfo: String = metainfo.split_whitespace().collect();
+        let data = STANDARD
+            .decode(metainfo)
+            .map_err(|_| "Invalid metainfo".to_owned())?;
+        web::add_torrent_data(ctx, &data, save_path, "".to_owned(), paused)
+    } else {
+        let filename = args["filename"].as_str().unwrap_or("").trim();
+        if filename.starts_with("magnet:") {
+            ctx.control(ControlRequest::Add {
+                source: filename.to_owned(),
+                save_path,
+                category: "".to_owned(),
+                paused,
+            })
+        } else if filename.starts_with("http://") || filename.starts_with("https://") {
+            let data = rss::fetch_torrent(filename)?;
+            web::add_torrent_data(ctx, &data, save_path, "".to_owned(), paused)
+        } else {
+            return Err("Not a magnet link or a URL".to_owned());
+        }
+    };
+
+    let data = into_result(response)?;
+    let hash = data["hash"].as_str().unwrap_or("").to_owned();
+    // The torrent shows up in the list on the next refresh
+    let name = ctx
+        .torrents
+        .lock()
+        .unwrap()
+        .iter()
+        .find(|t| t.hash == hash)
+        .map_or_else(|| hash.clone(), |t| t.name.clone());
+    Ok(serde_json::json!({
+        "torrent-added": {
+            "id": ctx.transmission.id(&hash),
+            "name": name,
+            "hashString": hash,
+        }
+    }))
+}
+
+/// Sends the request for each selected torrent.
+fn for_each<F>(ctx: &WebContext, args: &Value, request: F) -> Result<Value, String>
+where
+    F: Fn(String) -> ControlRequest,
+{
+    for torrent in select(ctx, &args["ids"]) {
+        into_result(ctx.control(request(torrent.hash)))?;
+    }
+    Ok(serde_json::json!({}))
+}
+
+fn session_get(ctx: &WebContext) -> Result<Value, String> {
+    let session = into_result(ctx.control(ControlRequest::Session))?;
+    let download_limit = session["download_limit"].as_i64().unwrap_or(0);
+    let upload_limit = session["upload_limit"].as_i64().unwrap_or(0);
+    Ok(serde_json::json!({
+        "version": concat!("Torrenter ", env!("CARGO_PKG_VERSION")),
+        "rpc-version": RPC_VERSION,
+        "rpc-version-minimum": RPC_VERSION_MINIMUM,
+        "session-id": ctx.transmission.session_id,
+        "download-dir": session["save_path"],
+        "speed-limit-down": download_limit / 1000,
+        "speed-limit-down-enabled": download_limit > 0,
+        "speed-limit-up": upload_limit / 1000,
+        "speed-limit-up-enabled": upload_limit > 0,
+        "units": {
+            "speed-units": ["kB/s", "MB/s", "GB/s", "TB/s"],
+            "speed-bytes": 1000,
+            "size-units": ["kB", "MB", "GB", "TB"],
+            "size-bytes": 1000,
+            "memory-units": ["KiB", "MiB", "GiB", "TiB"],
+            "memory-bytes": 1024,
+        },
+    }))
+}
+
+fn session_stats(ctx: &WebContext) -> Value {
+    let torrents = select(ctx, &Value::Null);
+    let sum = |f: fn(&Torrent) -> i64| torrents.iter().map(f).sum::<i64>();
+    let seconds_active = torrents
+        .iter()
+        .map(|t| t.active_duration)
+        .max()
+        .unwrap_or(0);
+    let stats = |uploaded: i64, downloaded: i64| {
+        serde_json::json!({
+            "uploadedBytes": uploaded,
+            "downloadedBytes": downloaded,
+            "filesAdded": sum(|t| t.num_files as i64),
+            "sessionCount": 1,
+            "secondsActive": seconds_active,
+        })
+    };
+    serde_json::json!({
+        "activeTorrentCount": torrents
+            .iter()
+            .filter(|t| t.download_rate > 0 || t.upload_rate > 0)
+            .count(),
+        "pausedTorrentCount": torrents
+            .iter()
+            .filter(|t| t.state == TorrentState::Paused)
+            .count(),
+        "torrentCount": torrents.len(),
+        "downloadSpeed": sum(|t| t.download_rate),
+        "uploadSpeed": sum(|t| t.upload_rate),
+        "cumulative-stats": stats(sum(|t| t.total_upload), sum(|t| t.total_download)),
+        "current-stats": stats(sum(|t| t.total_ses_upload), sum(|t| t.total_ses_download)),
+    })
+}
+
+/// Answers a request of the Transmission RPC protocol.
+pub fn handle(ctx: &WebContext, request: &WebRequest) -> WebResponse {
+    let session_id = &ctx.transmission.session_id;
+    if request.header(&SESSION_ID_HEADER.to_lowercase()) != Some(session_id) {
+        return WebResponse::text(409, &format!("{}: {}", SESSION_ID_HEADER, session_id))
+            .with_header(SESSION_ID_HEADER, session_id);
+    }
+    let body: Value = match serde_json::from_slice(&request.body) {
+        Ok(body) => body,
+        Err(e) => return WebResponse::text(400, &format!("Invalid JSON: {}", e)),
+    };
+
+    let args = &body["arguments"];
+    let result = match body["method"].as_str().unwrap_or("") {
+        "torrent-get" => torrent_get(ctx, args),
+        "torrent-add" => torrent_add(ctx, args),
+        "torrent-start" => for_each(ctx, args, |hash| ControlRequest::Resume { hash }),
+        "torrent-stop" => for_each(ctx, args, |hash| ControlRequest::Pause { hash }),
+        "torrent-remove" => {
+            let delete_files = args["delete-local-data"].as_bool().unwrap_or(false);
+            for_each(ctx, args, |hash| ControlRequest::Remove {
+                hash,
+                delete_files,
+            })
+        }
+        "session-get" => session_get(ctx),
+        "session-stats" => Ok(session_stats(ctx)),
+        _ => Err("method name not recognized".to_owned()),
+    };
+    let mut response = match result {
+        Ok(arguments) => serde_json::json!({"result": "success", "arguments": arguments}),
+        Err(e) => serde_json::json!({"result": e, "arguments": {}}),
+    };
+    if !body["tag"].is_null() {
+        response["tag"] = body["tag"].clone();
+    }
+    WebResponse::json(200, &response)
+}
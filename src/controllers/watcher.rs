@@ -0,0 +1,144 @@
+use std::{
+    collections::HashSet,
+    fs, io,
+    path::{Path, PathBuf},
+    sync::{mpsc::Sender, Arc, Mutex},
+    thread,
+    time::Duration,
+};
+
+use notify::{Config, Event, EventKind, PollWatcher, RecommendedWatcher, RecursiveMode, Watcher};
+
+use crate::models::{message::Message, settings::WatchedFolder};
+
+const POLL_INTERVAL: Duration = Duration::from_secs(2);
+const PROCESSED_DIR: &str = "processed";
+const FAILED_DIR: &str = "failed";
+
+pub fn is_torrent_file(path: &Path) -> bool {
+    path.is_file() && path.extension().and_then(|e| e.to_str()) == Some("torrent")
+}
+
+/// Waits for the size of the file to settle, as the file may still be in the
+/// middle of being copied into the folder.
+fn wait_until_written(path: &Path) {
+    let mut last_len = None;
+    for _ in 0..20 {
+        let len = fs::metadata(path).map(|m| m.len()).ok();
+        if len.is_some() && len == last_len {
+            return;
+        }
+        last_len = len;
+        thread::sleep(Duration::from_millis(250));
+    }
+}
+
+/// Paths of the files waited on before being sent.
+type Pending = Arc<Mutex<HashSet<PathBuf>>>;
+
+/// Sends the torrent file once written, waiting in a thread of its own so that
+/// the other files aren't held up. Files already waited on are skipped.
+fn send_file(path: &Path, folder: &WatchedFolder, tx: &Sender<Message>, pending: &Pending) {
+    if !is_torrent_file(path) || !pending.lock().unwrap().insert(path.to_owned()) {
+        return;
+    }
+    let path = path.to_owned();
+    let folder = folder.clone();
+    let tx = tx.clone();
+    let pending = pending.clone();
+    thread::spawn(move || {
+        wait_until_written(&path);
+        pending.lock().unwrap().remove(&path);
+        let path = path
+            .to_str()
+            .expect("Failed to convert path to str")
+            .to_string();
+        let _ = tx.send(Message::AddWatchedTorrent(path, folder));
+    });
+}
+
+fn watch_folder(
+    folder: &WatchedFolder,
+    tx: &Sender<Message>,
+    pending: &Pending,
+) -> notify::Result<Box<dyn Watcher + Send>> {
+    let handler = {
+        let folder = folder.clone();
+        let tx = tx.clone();
+        let pending = pending.clone();
+        move |res: notify::Result<Event>| match res {
+            Ok(event) => {
+                if matches!(event.kind, EventKind::Create(_) | EventKind::Modify(_)) {
+                    for path in event.paths {
+                        send_file(&path, &folder, &tx, &pending);
+                    }
+                }
+            }
+            Err(e) => log::error!("Failed to watch folder: {}", e),
+        }
+    };
+    let path = Path::new(&folder.path);
+
+    if !folder.use_polling {
+        let watcher =
+            RecommendedWatcher::new(handler.clone(), Config::default()).and_then(|mut watcher| {
+                watcher.watch(path, RecursiveMode::NonRecursive)?;
+                Ok(watcher)
+            });
+        match watcher {
+            Ok(watcher) => return Ok(Box::new(watcher)),
+            Err(e) => log::warn!("Falling back to polling {}: {}", folder.path, e),
+        }
+    }
+
+    let mut watcher =
+        PollWatcher::new(handler, Config::default().with_poll_interval(POLL_INTERVAL))?;
+    watcher.watch(path, RecursiveMode::NonRecursive)?;
+    Ok(Box::new(watcher))
+}
+
+/// Starts watching the folders, sending the torrent files already in them and
+/// those which appear later as `Message::AddWatchedTorrent`. The folders are
+/// watched for as long as the returned watchers are kept alive.
+pub fn watch(folders: &[WatchedFolder], tx: &Sender<Message>) -> Vec<Box<dyn Watcher + Send>> {
+    let mut watchers = vec![];
+    let pending = Pending::default();
+    for folder in folders {
+        if let Err(e) = fs::create_dir_all(&folder.path) {
+            log::error!("Failed to create watched folder {}: {}", folder.path, e);
+            continue;
+        }
+        match watch_folder(folder, tx, &pending) {
+            Ok(watcher) => watchers.push(watcher),
+            Err(e) => {
+                log::error!("Failed to watch folder {}: {}", folder.path, e);
+                continue;
+            }
+        }
+
+        // Pick up the files dropped while we weren't watching
+        if let Ok(entries) = fs::read_dir(&folder.path) {
+            for entry in entries.flatten() {
+                send_file(&entry.path(), folder, tx, &pending);
+            }
+        }
+    }
+    watchers
+}
+
+/// Gets a consumed torrent file out of the watched folder: moves it into
+/// `failed/` if it couldn't be added, otherwise deletes it or moves it into
+/// `processed/`.
+pub fn finish(path: &Path, folder: &WatchedFolder, is_added: bool) -> io::Result<()> {
+    if is_added && folder.delete_processed {
+        return fs::remove_file(path);
+    }
+
+    let dir = Path::new(&folder.path).join(if is_added { PROCESSED_DIR } else { FAILED_DIR });
+    fs::create_dir_all(&dir)?;
+    let file_name = path
+        .file_name()
+        .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "Not a file"))?;
+    let dest: PathBuf = dir.join(file_name);
+    fs::rename(path, dest)
+}
@@ -0,0 +1,594 @@
+use std::{
+    collections::HashMap,
+    fs,
+    io::Read,
+    net::SocketAddr,
+    path::PathBuf,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        mpsc::{self, Sender},
+        Arc, Mutex,
+    },
+    thread::{self, JoinHandle},
+};
+
+use base64::{engine::general_purpose::STANDARD, Engine};
+use serde_json::Value;
+use tiny_http::{Header, Server, SslConfig};
+
+use super::{
+    metrics,
+    qbittorrent::{self, QbtState},
+    transmission::{self, RpcState},
+};
+use crate::{
+    instance::percent_decode,
+    models::{
+        control::{ControlRequest, ControlResponse},
+        log::Log,
+        message::Message,
+        settings::WebSettings,
+        stats::SessionStats,
+        torrent::Torrent,
+    },
+};
+
+const MAX_BODY_SIZE: u64 = 10 * 1024 * 1024;
+const UPLOAD_DIR: &str = "web_uploads";
+
+static UPLOAD_COUNT: AtomicUsize = AtomicUsize::new(0);
+
+/// The bundled single-page UI, by path.
+const STATIC_FILES: [(&str, &str, &str); 3] = [
+    (
+        "/",
+        "text/html; charset=utf-8",
+        include_str!("../../web/index.html"),
+    ),
+    (
+        "/app.js",
+        "text/javascript; charset=utf-8",
+        include_str!("../../web/app.js"),
+    ),
+    (
+        "/style.css",
+        "text/css; charset=utf-8",
+        include_str!("../../web/style.css"),
+    ),
+];
+
+/// Commands accepted as `POST /api/torrents/<hash>/<command>`, taking the
+/// rest of their fields from the JSON body.
+const TORRENT_COMMANDS: [&str; 11] = [
+    "pause",
+    "resume",
+    "recheck",
+    "reannounce",
+    "move",
+    "set-category",
+    "add-tag",
+    "remove-tag",
+    "queue",
+    "set-limit",
+    "set-file-priority",
+];
+
+pub struct WebRequest {
+    pub method: String,
+    /// Without the query string.
+    pub path: String,
+    pub query: HashMap<String, String>,
+    /// Names in lowercase.
+    pub headers: HashMap<String, String>,
+    pub body: Vec<u8>,
+}
+
+impl WebRequest {
+    pub fn new(method: &str, url: &str, headers: HashMap<String, String>, body: Vec<u8>) -> Self {
+        let (path, query) = url.split_once('?').unwrap_or((url, ""));
+        Self {
+            method: method.to_uppercase(),
+            path: percent_decode(path),
+            query: parse_form(query),
+            headers,
+            body,
+        }
+    }
+
+    pub fn header(&self, name: &str) -> Option<&str> {
+        self.headers.get(name).map(|v| v.as_str())
+    }
+
+    /// The media type of the body, without its parameters.
+    pub fn content_type(&self) -> &str {
+        let content_type = self.header("content-type").unwrap_or("");
+        content_type.split(';').next().unwrap_or("").trim()
+    }
+
+    /// Whether the page which sent the request, if it says, was served by
+    /// this host. Browsers send the origin of cross-site requests, other
+    /// clients usually neither.
+    pub fn is_same_origin(&self) -> bool {
+        let Some(origin) = self.header("origin").or(self.header("referer")) else {
+            return true;
+        };
+        let host = origin
+            .split_once("://")
+            .and_then(|(_, rest)| rest.split('/').next());
+        host.is_some_and(|host| Some(host) == self.header("host"))
+    }
+
+    /// The fields of a form sent as `multipart/form-data` or URL-encoded.
+    pub fn form(&self) -> Vec<FormField> {
+        let content_type = self.header("content-type").unwrap_or("");
+        match content_type.split_once("boundary=") {
+            Some((_, boundary)) if content_type.starts_with("multipart/form-data") => {
+                parse_multipart(&self.body, boundary.trim_matches('"'))
+            }
+            _ => parse_form(&String::from_utf8_lossy(&self.body))
+                .into_iter()
+                .map(|(name, value)| FormField {
+                    name,
+                    filename: None,
+                    data: value.into_bytes(),
+                })
+                .collect(),
+        }
+    }
+
+    /// The body parsed as JSON, an empty object if there's none.
+    fn json(&self) -> Result<Value, String> {
+        if self.body.iter().all(|b| b.is_ascii_whitespace()) {
+            return Ok(serde_json::json!({}));
+        }
+        serde_json::from_slice(&self.body).map_err(|e| format!("Invalid JSON: {}", e))
+    }
+}
+
+pub struct WebResponse {
+    pub status: u16,
+    pub content_type: String,
+    pub headers: Vec<(String, String)>,
+    pub body: Vec<u8>,
+}
+
+impl WebResponse {
+    pub fn new(status: u16, content_type: &str, body: Vec<u8>) -> Self {
+        Self {
+            status,
+            content_type: content_type.to_owned(),
+            headers: vec![],
+            body,
+        }
+    }
+
+    pub fn json(status: u16, value: &Value) -> Self {
+        let body = serde_json::to_vec(value).expect("Failed to serialize response");
+        Self::new(status, "application/json", body)
+    }
+
+    pub fn text(status: u16, text: &str) -> Self {
+        Self::new(
+            status,
+            "text/plain; charset=utf-8",
+            text.as_bytes().to_vec(),
+        )
+    }
+
+    pub fn with_header(mut self, name: &str, value: &str) -> Self {
+        self.headers.push((name.to_owned(), value.to_owned()));
+        self
+    }
+}
+
+pub struct FormField {
+    pub name: String,
+    /// Set for uploaded files.
+    pub filename: Option<String>,
+    pub data: Vec<u8>,
+}
+
+impl FormField {
+    pub fn text(&self) -> String {
+        String::from_utf8_lossy(&self.data).to_string()
+    }
+}
+
+fn find(haystack: &[u8], needle: &[u8]) -> Option<usize> {
+    haystack.windows(needle.len()).position(|w| w == needle)
+}
+
+/// Splits a `multipart/form-data` body into its fields.
+fn parse_multipart(body: &[u8], boundary: &str) -> Vec<FormField> {
+    let delimiter = format!("--{}", boundary).into_bytes();
+    let separator = [b"\r\n".as_slice(), &delimiter].concat();
+    let mut fields = vec![];
+    let Some(start) = find(body, &delimiter) else {
+        return fields;
+    };
+    // Parts start on the line after a delimiter, the last one being followed
+    // by `--` instead
+    let mut rest = &body[start + delimiter.len()..];
+    while let Some(part) = rest.strip_prefix(b"\r\n") {
+        let Some(end) = find(part, &separator) else {
+            break;
+        };
+        rest = &part[end + separator.len()..];
+        let part = &part[..end];
+        let Some(headers_end) = find(part, b"\r\n\r\n") else {
+            continue;
+        };
+        let headers = String::from_utf8_lossy(&part[..headers_end]);
+        let disposition = headers
+            .lines()
+            .find(|l| l.to_lowercase().starts_with("content-disposition:"));
+        let param = |name: &str| {
+            disposition?
+                .split(';')
+                .map(str::trim)
+                .find_map(|p| p.strip_prefix(name)?.strip_prefix('='))
+                .map(|v| v.trim_matches('"').to_owned())
+        };
+        if let Some(name) = param("name") {
+            fields.push(FormField {
+                name,
+                filename: param("filename"),
+                data: part[headers_end + 4..].to_vec(),
+            });
+        }
+    }
+    fields
+}
+
+/// Decodes `application/x-www-form-urlencoded` pairs, as in query strings.
+pub fn parse_form(s: &str) -> HashMap<String, String> {
+    s.split('&')
+        .filter(|pair| !pair.is_empty())
+        .map(|pair| {
+            let (name, value) = pair.split_once('=').unwrap_or((pair, ""));
+            (
+                percent_decode(&name.replace('+', " ")),
+                percent_decode(&value.replace('+', " ")),
+            )
+        })
+        .collect()
+}
+
+/// What the handlers share.
+#[derive(Clone)]
+pub struct WebContext {
+    pub settings: WebSettings,
+    pub data_dir: PathBuf,
+    pub tx: Sender<Message>,
+    /// The torrents as of the last refresh of the message controller.
+    pub torrents: Arc<Mutex<Vec<Torrent>>>,
+    pub session_stats: Arc<Mutex<SessionStats>>,
+    pub log: Arc<Mutex<Log>>,
+    pub transmission: Arc<RpcState>,
+    pub qbittorrent: Arc<QbtState>,
+}
+
+impl WebContext {
+    pub fn new(
+        settings: WebSettings,
+        data_dir: PathBuf,
+        tx: Sender<Message>,
+        torrents: Arc<Mutex<Vec<Torrent>>>,
+        session_stats: Arc<Mutex<SessionStats>>,
+        log: Arc<Mutex<Log>>,
+    ) -> Self {
+        Self {
+            settings,
+            data_dir,
+            tx,
+            torrents,
+            session_stats,
+            log,
+            transmission: Arc::new(RpcState::default()),
+            qbittorrent: Arc::new(QbtState::default()),
+        }
+    }
+
+    /// Passes the request on to the message controller, the same way the
+    /// control socket does.
+    pub fn control(&self, request: ControlRequest) -> ControlResponse {
+        let (reply_tx, reply_rx) = mpsc::channel();
+        if self.tx.send(Message::Control(request, reply_tx)).is_err() {
+            return ControlResponse::failure("Shutting down");
+        }
+        reply_rx
+            .recv()
+            .unwrap_or_else(|_| ControlResponse::failure("Shutting down"))
+    }
+
+    /// Whether the request carries the configured credentials as HTTP basic
+    /// authentication, always true without a username.
+    pub fn is_authorized(&self, request: &WebRequest) -> bool {
+        if self.settings.username.is_empty() {
+            return true;
+        }
+        let credentials = request
+            .header("authorization")
+            .and_then(|v| v.strip_prefix("Basic "))
+            .and_then(|v| STANDARD.decode(v.trim()).ok())
+            .and_then(|v| String::from_utf8(v).ok());
+        credentials.is_some_and(|c| {
+            c.split_once(':').is_some_and(|(username, password)| {
+                username == self.settings.username && password == self.settings.password
+            })
+        })
+    }
+}
+
+/// A hexadecimal string of 128 random bits, for session ids.
+pub fn random_token() -> String {
+    let mut bytes = [0u8; 16];
+    getrandom::getrandom(&mut bytes).expect("Failed to get random bytes");
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+fn respond_control(response: ControlResponse) -> WebResponse {
+    let status = if response.ok { 200 } else { 400 };
+    WebResponse::json(status, &serde_json::json!(response))
+}
+
+/// Adds the contents of a .torrent file, saving it for the session to load.
+pub fn add_torrent_data(
+    ctx: &WebContext,
+    data: &[u8],
+    save_path: String,
+    category: String,
+    paused: bool,
+) -> ControlResponse {
+    let dir = ctx.data_dir.join(UPLOAD_DIR);
+    let id = UPLOAD_COUNT.fetch_add(1, Ordering::SeqCst);
+    let path = dir.join(format!("{}.torrent", id));
+    if let Err(e) = fs::create_dir_all(&dir).and_then(|_| fs::write(&path, data)) {
+        log::error!("Failed to save uploaded torrent: {}", e);
+        return ControlResponse::failure("Failed to save torrent");
+    }
+    let response = ctx.control(ControlRequest::Add {
+        source: path.to_string_lossy().to_string(),
+        save_path,
+        category,
+        paused,
+    });
+    let _ = fs::remove_file(path);
+    response
+}
+
+/// Answers a request of the REST API under `/api/`.
+fn handle_api(ctx: &WebContext, request: &WebRequest) -> WebResponse {
+    let segments: Vec<&str> = request.path.trim_matches('/').split('/').skip(1).collect();
+    let method = request.method.as_str();
+    // Forms of other sites may only send simple requests, which the content
+    // types of the API and the preflight of the other methods rule out
+    if method != "GET" && !request.is_same_origin() {
+        return WebResponse::json(
+            403,
+            &serde_json::json!(ControlResponse::failure("Cross-site request")),
+        );
+    }
+    let is_upload = request.content_type() == "application/x-bittorrent";
+    if method == "POST" && !is_upload && request.content_type() != "application/json" {
+        return WebResponse::json(
+            415,
+            &serde_json::json!(ControlResponse::failure("Expected a JSON body")),
+        );
+    }
+    let control_request = match (method, segments.as_slice()) {
+        ("GET", ["torrents"]) => Ok(ControlRequest::List),
+        ("POST", ["torrents"]) => {
+            if is_upload {
+                let query = |name: &str| request.query.get(name).cloned().unwrap_or_default();
+                return respond_control(add_torrent_data(
+                    ctx,
+                    &request.body,
+                    query("save_path"),
+                    query("category"),
+                    query("paused") == "true",
+                ));
+            }
+            // Paths would reach into the file system of the host
+            request
+                .json()
+                .and_then(|body| {
+                    serde_json::from_value::<WebAddRequest>(body)
+                        .map_err(|e| format!("Invalid request: {}", e))
+                })
+                .and_then(|body| {
+                    if body.source.starts_with("magnet:") {
+                        Ok(ControlRequest::Add {
+                            source: body.source,
+                            save_path: body.save_path,
+                            category: body.category,
+                            paused: body.paused,
+                        })
+                    } else {
+                        Err("Not a magnet link".to_owned())
+                    }
+                })
+        }
+        ("GET", ["categories"]) => Ok(ControlRequest::Categories),
+        ("POST", ["limits"]) => request.json().and_then(|mut body| {
+            body["command"] = "set-limit".into();
+            body["hash"] = Value::Null;
+            serde_json::from_value(body).map_err(|e| format!("Invalid request: {}", e))
+        }),
+        ("GET", ["torrents", hash]) => Ok(ControlRequest::Info {
+            hash: hash.to_string(),
+        }),
+        ("DELETE", ["torrents", hash]) => Ok(ControlRequest::Remove {
+            hash: hash.to_string(),
+            delete_files: request
+                .query
+                .get("delete_files")
+                .is_some_and(|d| d == "true"),
+        }),
+        ("GET", ["torrents", hash, "files"]) => Ok(ControlRequest::Files {
+            hash: hash.to_string(),
+        }),
+        ("GET", ["torrents", hash, "peers"]) => Ok(ControlRequest::Peers {
+            hash: hash.to_string(),
+        }),
+        ("GET", ["torrents", hash, "trackers"]) => Ok(ControlRequest::Trackers {
+            hash: hash.to_string(),
+        }),
+        ("POST", ["torrents", hash, command]) if TORRENT_COMMANDS.contains(command) => {
+            request.json().and_then(|mut body| {
+                body["command"] = command.to_string().into();
+                body["hash"] = hash.to_string().into();
+                serde_json::from_value(body).map_err(|e| format!("Invalid request: {}", e))
+            })
+        }
+        _ => {
+            return WebResponse::json(
+                404,
+                &serde_json::json!(ControlResponse::failure("Not found")),
+            )
+        }
+    };
+    match control_request {
+        Ok(control_request) => respond_control(ctx.control(control_request)),
+        Err(e) => respond_control(ControlResponse::failure(e)),
+    }
+}
+
+#[derive(serde::Deserialize)]
+struct WebAddRequest {
+    source: String,
+    #[serde(default)]
+    save_path: String,
+    #[serde(default)]
+    category: String,
+    #[serde(default)]
+    paused: bool,
+}
+
+/// Answers a request of the web UI or the REST API.
+pub fn handle(ctx: &WebContext, request: &WebRequest) -> WebResponse {
+    // Logging in gives a session cookie instead
+    if ctx.settings.qbittorrent_api && request.path.starts_with(qbittorrent::API_PATH) {
+        return qbittorrent::handle(ctx, request);
+    }
+    if !ctx.is_authorized(request) {
+        return WebResponse::text(401, "Unauthorized")
+            .with_header("WWW-Authenticate", "Basic realm=\"Torrenter\"");
+    }
+    if ctx.settings.transmission_rpc && request.path == transmission::RPC_PATH {
+        return transmission::handle(ctx, request);
+    }
+    if ctx.settings.metrics && request.path == metrics::METRICS_PATH {
+        return metrics::handle(ctx);
+    }
+    if request.path.starts_with("/api/") {
+        return handle_api(ctx, request);
+    }
+    match STATIC_FILES.iter().find(|(path, ..)| *path == request.path) {
+        Some((_, content_type, contents)) if request.method == "GET" => {
+            WebResponse::new(200, content_type, contents.as_bytes().to_vec())
+        }
+        _ => WebResponse::text(404, "Not found"),
+    }
+}
+
+fn read_request(request: &mut tiny_http::Request) -> WebRequest {
+    let headers = request
+        .headers()
+        .iter()
+        .map(|h| {
+            (
+                h.field.as_str().as_str().to_lowercase(),
+                h.value.as_str().to_owned(),
+            )
+        })
+        .collect();
+    let mut body = vec![];
+    if let Err(e) = request
+        .as_reader()
+        .take(MAX_BODY_SIZE)
+        .read_to_end(&mut body)
+    {
+        log::error!("Failed to read web request: {}", e);
+    }
+    WebRequest::new(request.method().as_str(), request.url(), headers, body)
+}
+
+fn send_response(request: tiny_http::Request, response: WebResponse) {
+    let mut http_response = tiny_http::Response::from_data(response.body)
+        .with_status_code(response.status)
+        .with_header(
+            Header::from_bytes("Content-Type", response.content_type).expect("Valid header"),
+        );
+    for (name, value) in response.headers {
+        if let Ok(header) = Header::from_bytes(name, value) {
+            http_response = http_response.with_header(header);
+        }
+    }
+    if let Err(e) = request.respond(http_response) {
+        log::error!("Failed to send web response: {}", e);
+    }
+}
+
+/// The running server, stopped when dropped.
+pub struct WebServer {
+    pub settings: WebSettings,
+    server: Arc<Server>,
+    handle: Option<JoinHandle<()>>,
+}
+
+impl WebServer {
+    pub fn addr(&self) -> Option<SocketAddr> {
+        self.server.server_addr().to_ip()
+    }
+}
+
+impl Drop for WebServer {
+    fn drop(&mut self) {
+        self.server.unblock();
+        if let Some(handle) = self.handle.take() {
+            let _ = handle.join();
+        }
+    }
+}
+
+/// Starts serving the web UI and the APIs, passing the requests on to the
+/// message controller.
+pub fn serve(ctx: WebContext) -> Result<WebServer, String> {
+    let settings = ctx.settings.clone();
+    let addr = (settings.bind_address.trim(), settings.port);
+    let server = if settings.use_tls {
+        let certificate = fs::read(&settings.cert_path)
+            .map_err(|e| format!("Failed to read {}: {}", settings.cert_path, e))?;
+        let private_key = fs::read(&settings.key_path)
+            .map_err(|e| format!("Failed to read {}: {}", settings.key_path, e))?;
+        Server::https(
+            addr,
+            SslConfig {
+                certificate,
+                private_key,
+            },
+        )
+    } else {
+        Server::http(addr)
+    }
+    .map_err(|e| e.to_string())?;
+    let server = Arc::new(server);
+
+    let server_clone = server.clone();
+    let handle = thread::spawn(move || {
+        for mut request in server_clone.incoming_requests() {
+            let ctx = ctx.clone();
+            thread::spawn(move || {
+                let web_request = read_request(&mut request);
+                let response = handle(&ctx, &web_request);
+                send_response(request, response);
+            });
+        }
+    });
+    Ok(WebServer {
+        settings,
+        server,
+        handle: Some(handle),
+    })
+}
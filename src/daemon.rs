@@ -0,0 +1,58 @@
+use std::{
+    path::PathBuf,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        mpsc, Arc, Mutex,
+    },
+    thread,
+    time::Duration,
+};
+
+use egui_toast::Toasts;
+
+#[cfg(unix)]
+use crate::controllers::control;
+use crate::{
+    controllers::message::MessageController,
+    models::{
+        log::Log,
+        message::{AddTorrentKind, Message},
+    },
+};
+
+/// Runs the session without a window until SIGINT or SIGTERM, controlled
+/// through the control socket.
+pub fn run(data_dir: PathBuf, log: Arc<Mutex<Log>>, sources: Vec<(String, AddTorrentKind)>) {
+    let stop = Arc::new(AtomicBool::new(false));
+    let stop_clone = stop.clone();
+    ctrlc::set_handler(move || stop_clone.store(true, Ordering::SeqCst))
+        .expect("Failed to set the signal handler");
+
+    let (tx, rx) = mpsc::channel::<Message>();
+    let msg_controller = MessageController::new(tx.clone(), data_dir.clone(), Toasts::new(), log);
+    let toasts = msg_controller.toasts.clone();
+    let handle = msg_controller.spawn(rx);
+    for (source, kind) in sources {
+        tx.send(Message::AddTorrent(source, kind, "".to_owned()))
+            .unwrap();
+    }
+
+    #[cfg(unix)]
+    if let Err(e) = control::serve(&data_dir, tx.clone()) {
+        log::error!("Failed to listen on the control socket: {}", e);
+    }
+    log::info!("Running headless.");
+
+    while !stop.load(Ordering::SeqCst) {
+        tx.send(Message::Refresh).unwrap();
+        // Nothing shows the toasts, they are logged instead
+        *toasts.lock().unwrap() = Toasts::new();
+        thread::sleep(Duration::from_millis(500));
+    }
+
+    log::info!("Received a termination signal.");
+    tx.send(Message::Stop).unwrap();
+    handle.join().expect("Message controller panicked");
+    #[cfg(unix)]
+    let _ = std::fs::remove_file(control::socket_path(&data_dir));
+}
@@ -0,0 +1,124 @@
+use std::{
+    fs::{self, File, OpenOptions, TryLockError},
+    io,
+    path::{Path, PathBuf},
+};
+
+use crate::models::message::AddTorrentKind;
+#[cfg(unix)]
+use crate::{controllers::control, models::control::ControlRequest};
+
+pub const LOCK_FILE: &str = "instance.lock";
+const DESKTOP_FILE: &str = "torrenter.desktop";
+
+/// Locks the data dir for this process, `None` if another instance holds the
+/// lock. It's released once the file is dropped.
+pub fn lock(data_dir: &Path) -> Option<File> {
+    let file = OpenOptions::new()
+        .create(true)
+        .truncate(false)
+        .write(true)
+        .open(data_dir.join(LOCK_FILE))
+        .expect("Failed to open the lock file.");
+    match file.try_lock() {
+        Ok(()) => Some(file),
+        Err(TryLockError::WouldBlock) => None,
+        Err(TryLockError::Error(e)) => {
+            // Some file systems don't support locks, run anyway
+            eprintln!("Failed to lock the data dir: {}", e);
+            Some(file)
+        }
+    }
+}
+
+pub fn percent_decode(s: &str) -> String {
+    let bytes = s.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        let hex = bytes
+            .get(i + 1..i + 3)
+            .and_then(|h| std::str::from_utf8(h).ok());
+        match (bytes[i], hex.and_then(|h| u8::from_str_radix(h, 16).ok())) {
+            (b'%', Some(byte)) => {
+                decoded.push(byte);
+                i += 3;
+            }
+            (byte, _) => {
+                decoded.push(byte);
+                i += 1;
+            }
+        }
+    }
+    String::from_utf8_lossy(&decoded).to_string()
+}
+
+/// Recognizes a magnet link, a .torrent file path or a `file://` URL of one
+/// among the command-line arguments. Paths are made absolute.
+pub fn parse_source(arg: &str) -> Option<(String, AddTorrentKind)> {
+    if arg.starts_with("magnet:") {
+        return Some((arg.to_owned(), AddTorrentKind::MagnetUrl));
+    }
+    let path = match arg.strip_prefix("file://") {
+        Some(path) => PathBuf::from(percent_decode(path)),
+        None => PathBuf::from(arg),
+    };
+    if !path.is_file() {
+        return None;
+    }
+    let path = fs::canonicalize(path).ok()?;
+    Some((path.to_str()?.to_owned(), AddTorrentKind::File))
+}
+
+/// Hands the torrents over to the instance holding the lock.
+#[cfg(unix)]
+pub fn forward(data_dir: &Path, sources: Vec<String>) -> io::Result<()> {
+    let response = control::request(data_dir, &ControlRequest::Open { sources })?;
+    match response.error {
+        Some(e) => Err(io::Error::other(e)),
+        None => Ok(()),
+    }
+}
+
+#[cfg(not(unix))]
+pub fn forward(_data_dir: &Path, _sources: Vec<String>) -> io::Result<()> {
+    Err(io::Error::new(
+        io::ErrorKind::Unsupported,
+        "Forwarding needs Unix sockets",
+    ))
+}
+
+/// A freedesktop entry opening magnet links and .torrent files with `exe`.
+pub fn desktop_entry(exe: &Path) -> String {
+    let mut quoted = String::new();
+    for c in exe.to_string_lossy().chars() {
+        if matches!(c, '"' | '`' | '$' | '\\') {
+            quoted.push('\\');
+        }
+        quoted.push(c);
+    }
+    format!(
+        "[Desktop Entry]
+Type=Application
+Name=Torrenter
+Comment=BitTorrent client
+Exec=\"{}\" %U
+Terminal=false
+Categories=Network;FileTransfer;P2P;
+MimeType=x-scheme-handler/magnet;application/x-bittorrent;
+",
+        quoted
+    )
+}
+
+/// Writes the desktop entry into the user's applications, returning its path.
+pub fn install_desktop_entry() -> io::Result<PathBuf> {
+    let exe = std::env::current_exe()?;
+    let dir = dirs::data_dir()
+        .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "No data dir"))?
+        .join("applications");
+    fs::create_dir_all(&dir)?;
+    let path = dir.join(DESKTOP_FILE);
+    fs::write(&path, desktop_entry(&exe))?;
+    Ok(path)
+}
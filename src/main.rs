@@ -2,29 +2,57 @@
 #![allow(non_upper_case_globals)]
 
 use controllers::add_torrent;
+#[cfg(unix)]
+use controllers::control;
+use controllers::log as logger;
 use controllers::message::MessageController;
+use controllers::torrent;
 use eframe::egui;
 use egui::Align2;
 use egui_toast::Toasts;
-use models::message::Message;
+use models::category::Category;
+use models::column::{ListMode, PeerTableLayout, TableLayout};
+use models::feed::Feed;
+use models::filter::{Search, SearchCache, TorrentFilter};
+use models::history::{GraphRange, SpeedHistories};
+use models::ip_filter::IpFilterStatus;
+use models::log::{Log, LogFilter};
+use models::message::{AddTorrentKind, Message};
+use models::network::NetworkStatus;
+use models::selection::Selection;
+use models::settings::{ProxySettings, Settings};
+use models::sort::SortOrder;
+use models::stats::SessionStats;
 use models::tab::{Tab, TabView};
 use models::torrent::Torrent;
 use std::sync::mpsc::Sender;
-use std::time::Instant;
 use std::{
-    ffi::CString,
-    fs,
+    env, fs,
     path::PathBuf,
+    process,
     sync::{Arc, Mutex},
-    thread,
-    time::Duration,
 };
 use views::add_torrent::AddTorrentWidget;
+use views::batch::{BatchDraft, BatchWidget};
+use views::categories::{CategoryDraft, CategoryEditorWidget};
+use views::filter::FilterWidget;
+use views::log::LogWidget;
+use views::peers::{AddPeersWidget, PeersDraft};
+use views::rss::RssWidget;
+use views::search::SearchWidget;
+use views::settings::SettingsWidget;
+use views::speed_graph::SpeedGraphWidget;
+use views::stats::StatsWidget;
 use views::tab::TabWidget;
 use views::torrent::TorrentWidget;
+use views::torrent_table::TorrentTableWidget;
 mod bytes;
+#[cfg(unix)]
+mod cli;
 mod controllers;
+mod daemon;
 mod duration;
+mod instance;
 mod models;
 mod tests;
 mod toasts;
@@ -41,19 +69,71 @@ fn prepare_data_dir() -> PathBuf {
 }
 
 fn main() -> eframe::Result {
-    env_logger::init(); // Log to stderr (if you run with `RUST_LOG=debug`).
+    let data_dir = prepare_data_dir();
+    let args: Vec<String> = env::args().skip(1).collect();
+    #[cfg(unix)]
+    if cli::is_command(&args) {
+        process::exit(cli::run(&args, &data_dir));
+    }
+    if args.iter().any(|arg| arg == "--install-desktop-entry") {
+        match instance::install_desktop_entry() {
+            Ok(path) => {
+                println!("Installed {}", path.display());
+                process::exit(0);
+            }
+            Err(e) => {
+                eprintln!("Failed to install the desktop entry: {}", e);
+                process::exit(1);
+            }
+        }
+    }
+
+    let mut sources = vec![];
+    for arg in args.iter().filter(|arg| !arg.starts_with("--")) {
+        match instance::parse_source(arg) {
+            Some(source) => sources.push(source),
+            None => eprintln!("Ignoring {}, not a magnet link or a .torrent file.", arg),
+        }
+    }
+    // Only one instance runs the session, the others hand their torrents over
+    let Some(_lock) = instance::lock(&data_dir) else {
+        if sources.is_empty() {
+            eprintln!("Torrenter is already running.");
+            process::exit(1);
+        }
+        let sources = sources.into_iter().map(|(source, _)| source).collect();
+        if let Err(e) = instance::forward(&data_dir, sources) {
+            eprintln!(
+                "Failed to hand the torrents over to the running instance: {}",
+                e
+            );
+            process::exit(1);
+        }
+        process::exit(0);
+    };
+
+    // Log to the log panel, the log files and stderr (if you run with `RUST_LOG=debug`).
+    let settings = Settings::load(&data_dir);
+    let log = Arc::new(Mutex::new(Log::new(settings.log.max_entries)));
+    logger::init(&data_dir, log.clone());
     log::info!("Application started");
 
-    // Load torrents from resume files
-    let data_dir = prepare_data_dir();
+    // Load torrents from resume files, restoring the session state
     let resume_dir = data_dir
         .join("resume_files")
         .to_str()
         .expect("Failed to str of path")
         .to_string();
-    unsafe {
-        let resume_dir_cstr = CString::new(resume_dir).expect("Failed to convert to CString");
-        initiate(resume_dir_cstr.as_ptr());
+    let session_file = data_dir
+        .join("session_state")
+        .to_str()
+        .expect("Failed to str of path")
+        .to_string();
+    torrent::start_session(&resume_dir, &session_file, &settings);
+
+    if args.iter().any(|arg| arg == "--headless") {
+        daemon::run(data_dir, log, sources);
+        return Ok(());
     }
 
     // Spawn the frame
@@ -64,13 +144,29 @@ fn main() -> eframe::Result {
     eframe::run_native(
         "Torrenter",
         options,
-        Box::new(|cc| {
+        Box::new(move |cc| {
             egui_extras::install_image_loaders(&cc.egui_ctx);
-            Ok(Box::<AppState>::default())
+            Ok(Box::new(AppState::new(cc, data_dir, log, sources)))
         }),
     )
 }
 
+const FILTER_KEY: &str = "filter";
+const SEARCH_KEY: &str = "search";
+const SORT_KEY: &str = "sort";
+const LIST_MODE_KEY: &str = "list_mode";
+const TABLE_LAYOUT_KEY: &str = "table_layout";
+const PEER_TABLE_LAYOUT_KEY: &str = "peer_table_layout";
+
+fn load_value<T>(cc: &eframe::CreationContext, key: &str) -> T
+where
+    T: serde::de::DeserializeOwned + Default,
+{
+    cc.storage
+        .and_then(|storage| eframe::get_value(storage, key))
+        .unwrap_or_default()
+}
+
 struct AppState {
     torrents: Arc<Mutex<Vec<Torrent>>>,
     sel_torrent: Arc<Mutex<Option<usize>>>,
@@ -78,54 +174,70 @@ struct AppState {
     can_exit: Arc<Mutex<bool>>,
     tab_view: TabView,
     toasts: Arc<Mutex<Toasts>>,
+    settings: Arc<Mutex<Settings>>,
+    settings_draft: Option<Settings>,
+    feeds: Arc<Mutex<Vec<Feed>>>,
+    show_rss: bool,
+    session_stats: Arc<Mutex<SessionStats>>,
+    show_stats: bool,
+    ip_filter: Arc<Mutex<IpFilterStatus>>,
+    network: Arc<Mutex<NetworkStatus>>,
+    log: Arc<Mutex<Log>>,
+    log_filter: LogFilter,
+    show_log: bool,
+    categories: Arc<Mutex<Vec<Category>>>,
+    filter: TorrentFilter,
+    search: Search,
+    search_cache: SearchCache,
+    sort: SortOrder,
+    list_mode: ListMode,
+    table_layout: TableLayout,
+    peer_table_layout: PeerTableLayout,
+    selection: Selection,
+    history: Arc<Mutex<SpeedHistories>>,
+    show_graph: bool,
+    session_graph_range: GraphRange,
+    torrent_graph_range: GraphRange,
+    batch_draft: BatchDraft,
+    category_draft: Option<CategoryDraft>,
+    peers_draft: Option<PeersDraft>,
+    new_torrent_category: String,
 }
 
-impl Default for AppState {
-    fn default() -> Self {
-        let torrents = Arc::new(Mutex::new({
-            let torrents_count = unsafe { get_count() };
-            let mut torrents = Vec::new();
-            for _ in 0..torrents_count {
-                torrents.push(Torrent::new(
-                    "".to_owned(),
-                    dirs::download_dir()
-                        .expect("Failed to get downloads dir.")
-                        .to_str()
-                        .to_owned()
-                        .expect("Failed to get downloads dir.")
-                        .to_owned(),
-                ));
-            }
-            torrents
-        }));
-
-        let can_exit = Arc::new(Mutex::new(false));
+impl AppState {
+    fn new(
+        cc: &eframe::CreationContext,
+        data_dir: PathBuf,
+        log: Arc<Mutex<Log>>,
+        sources: Vec<(String, AddTorrentKind)>,
+    ) -> Self {
         let (tx, rx) = std::sync::mpsc::channel::<Message>();
-        let last_refresh = Box::new(Instant::now().checked_sub(Duration::from_secs(1)).unwrap());
-        let sel_torrent = Arc::new(Mutex::new(None));
-        let toasts = Arc::new(Mutex::new(
-            Toasts::new()
-                .anchor(Align2::CENTER_TOP, (10.0, 10.0))
-                .direction(egui::Direction::TopDown),
-        ));
+        let toasts = Toasts::new()
+            .anchor(Align2::CENTER_TOP, (10.0, 10.0))
+            .direction(egui::Direction::TopDown);
+        #[cfg(unix)]
+        if let Err(e) = control::serve(&data_dir, tx.clone()) {
+            log::error!("Failed to listen on the control socket: {}", e);
+        }
+        let msg_controller = MessageController::new(tx.clone(), data_dir, toasts, log.clone());
+        let torrents = msg_controller.torrents.clone();
+        let sel_torrent = msg_controller.sel_torrent.clone();
+        let can_exit = msg_controller.can_exit.clone();
+        let toasts = msg_controller.toasts.clone();
+        let settings = msg_controller.settings.clone();
+        let feeds = msg_controller.feeds.clone();
+        let categories = msg_controller.categories.clone();
+        let history = msg_controller.history.clone();
+        let session_stats = msg_controller.session_stats.clone();
+        let ip_filter = msg_controller.ip_filter.clone();
+        let network = msg_controller.network.clone();
 
         // Perform torrent-related tasks in the background
-        let mut msg_controller = MessageController {
-            tx: tx.clone(),
-            torrents: torrents.clone(),
-            last_refresh,
-            can_exit: can_exit.clone(),
-            sel_torrent: sel_torrent.clone(),
-            toasts: toasts.clone(),
-        };
-        let can_exit_clone = can_exit.clone();
-        thread::spawn(move || loop {
-            let message = rx.recv().unwrap();
-            msg_controller.process(message);
-            if *can_exit_clone.lock().unwrap() {
-                break;
-            }
-        });
+        msg_controller.spawn(rx);
+        for (source, kind) in sources {
+            tx.send(Message::AddTorrent(source, kind, "".to_owned()))
+                .unwrap();
+        }
 
         Self {
             torrents,
@@ -137,10 +249,40 @@ impl Default for AppState {
                     (Tab::General, "General".to_owned(), false),
                     (Tab::Files, "Files".to_owned(), false),
                     (Tab::Peers, "Peers".to_owned(), false),
+                    (Tab::Trackers, "Trackers".to_owned(), false),
+                    (Tab::Speed, "Speed".to_owned(), false),
                 ],
                 selected: Tab::General,
             },
             toasts,
+            settings,
+            settings_draft: None,
+            feeds,
+            show_rss: false,
+            session_stats,
+            show_stats: false,
+            ip_filter,
+            network,
+            log,
+            log_filter: LogFilter::default(),
+            show_log: false,
+            categories,
+            filter: load_value(cc, FILTER_KEY),
+            search: load_value(cc, SEARCH_KEY),
+            search_cache: SearchCache::default(),
+            sort: load_value(cc, SORT_KEY),
+            list_mode: load_value(cc, LIST_MODE_KEY),
+            table_layout: load_value(cc, TABLE_LAYOUT_KEY),
+            peer_table_layout: load_value(cc, PEER_TABLE_LAYOUT_KEY),
+            selection: Selection::default(),
+            history,
+            show_graph: false,
+            session_graph_range: GraphRange::default(),
+            torrent_graph_range: GraphRange::default(),
+            batch_draft: BatchDraft::default(),
+            category_draft: None,
+            peers_draft: None,
+            new_torrent_category: "".to_owned(),
         }
     }
 }
@@ -151,6 +293,233 @@ impl eframe::App for AppState {
         let torrents = self.torrents.lock().unwrap();
         let mut toasts = self.toasts.lock().unwrap();
 
+        // Top panel
+        egui::TopBottomPanel::top("toolbar").show(ctx, |ui| {
+            ui.horizontal(|ui| {
+                if ui.button("⚙").on_hover_text("Settings").clicked() {
+                    self.settings_draft = Some(self.settings.lock().unwrap().clone());
+                }
+                if ui.button("📰").on_hover_text("RSS").clicked() {
+                    self.show_rss = !self.show_rss;
+                }
+                if ui.button("📊").on_hover_text("Statistics").clicked() {
+                    self.show_stats = !self.show_stats;
+                }
+                if ui.button("📜").on_hover_text("Log").clicked() {
+                    self.show_log = !self.show_log;
+                }
+            });
+        });
+
+        // RSS window
+        egui::Window::new("RSS")
+            .open(&mut self.show_rss)
+            .default_width(500.0)
+            .show(ctx, |ui| {
+                let feeds = self.feeds.lock().unwrap();
+                ui.add(RssWidget {
+                    feeds: &feeds,
+                    channel_tx: &self.channel_tx,
+                });
+            });
+
+        // Statistics window
+        if self.show_stats {
+            self.channel_tx.send(Message::FetchSessionStats).unwrap();
+        }
+        egui::Window::new("Statistics")
+            .open(&mut self.show_stats)
+            .show(ctx, |ui| {
+                let stats = self.session_stats.lock().unwrap();
+                ui.add(StatsWidget { stats: &stats });
+            });
+
+        // Log window
+        egui::Window::new("Log")
+            .open(&mut self.show_log)
+            .default_size([700.0, 400.0])
+            .show(ctx, |ui| {
+                let mut log = self.log.lock().unwrap();
+                ui.add(LogWidget {
+                    log: &mut log,
+                    filter: &mut self.log_filter,
+                });
+            });
+
+        // Settings window
+        if let Some(settings) = &mut self.settings_draft {
+            let mut is_open = true;
+            let mut is_done = false;
+            egui::Window::new("Settings")
+                .open(&mut is_open)
+                .collapsible(false)
+                .show(ctx, |ui| {
+                    let banned_ips = self.settings.lock().unwrap().ip_filter.banned_ips.clone();
+                    let ip_filter = self.ip_filter.lock().unwrap();
+                    let network = self.network.lock().unwrap();
+                    ui.add(SettingsWidget {
+                        settings,
+                        ip_filter: &ip_filter,
+                        network: &network,
+                        banned_ips: &banned_ips,
+                        channel_tx: &self.channel_tx,
+                    });
+                    ui.add_space(5.0);
+                    ui.horizontal(|ui| {
+                        if ui.button("Save").clicked() {
+                            self.channel_tx
+                                .send(Message::UpdateSettings(Box::new(settings.clone())))
+                                .unwrap();
+                            is_done = true;
+                        }
+                        if ui.button("Cancel").clicked() {
+                            is_done = true;
+                        }
+                    });
+                });
+            if !is_open || is_done {
+                self.settings_draft = None;
+            }
+        }
+
+        // Category editor window
+        if let Some((old_name, category)) = &mut self.category_draft {
+            let mut is_open = true;
+            let mut is_done = false;
+            let title = if old_name.is_some() {
+                "Edit Category"
+            } else {
+                "New Category"
+            };
+            egui::Window::new(title)
+                .open(&mut is_open)
+                .collapsible(false)
+                .show(ctx, |ui| {
+                    ui.add(CategoryEditorWidget { category });
+                    ui.add_space(5.0);
+                    ui.horizontal(|ui| {
+                        let is_valid = !category.name.trim().is_empty();
+                        if ui
+                            .add_enabled(is_valid, egui::Button::new("Save"))
+                            .clicked()
+                        {
+                            category.name = category.name.trim().to_owned();
+                            self.channel_tx
+                                .send(Message::SaveCategory(old_name.clone(), category.clone()))
+                                .unwrap();
+                            is_done = true;
+                        }
+                        if ui.button("Cancel").clicked() {
+                            is_done = true;
+                        }
+                    });
+                });
+            if !is_open || is_done {
+                self.category_draft = None;
+            }
+        }
+
+        // Add peers window
+        if let Some((index, text)) = &mut self.peers_draft {
+            let mut is_open = true;
+            let mut is_done = false;
+            egui::Window::new("Add Peers")
+                .open(&mut is_open)
+                .collapsible(false)
+                .show(ctx, |ui| {
+                    ui.add(AddPeersWidget { text });
+                    ui.add_space(5.0);
+                    ui.horizontal(|ui| {
+                        let (endpoints, _) = models::peer::parse_endpoints(text);
+                        if ui
+                            .add_enabled(!endpoints.is_empty(), egui::Button::new("Add"))
+                            .clicked()
+                        {
+                            self.channel_tx
+                                .send(Message::AddPeers(*index, endpoints))
+                                .unwrap();
+                            is_done = true;
+                        }
+                        if ui.button("Cancel").clicked() {
+                            is_done = true;
+                        }
+                    });
+                });
+            if !is_open || is_done {
+                self.peers_draft = None;
+            }
+        }
+
+        let categories = self.categories.lock().unwrap().clone();
+        let tags: Vec<String> = torrents
+            .iter()
+            .flat_map(|t| t.tags.iter().cloned())
+            .collect::<std::collections::BTreeSet<_>>()
+            .into_iter()
+            .collect();
+
+        // Status bar
+        let history = self.history.lock().unwrap();
+        egui::TopBottomPanel::bottom("status").show(ctx, |ui| {
+            ui.horizontal(|ui| {
+                let download_rate: i64 = torrents.iter().map(|t| t.download_rate).sum();
+                let upload_rate: i64 = torrents.iter().map(|t| t.upload_rate).sum();
+                ui.label(format!(
+                    "{} torrents • ⬇ {} • ⬆ {}",
+                    torrents.len(),
+                    format_bytes!(download_rate, "/s"),
+                    format_bytes!(upload_rate, "/s")
+                ));
+                let proxy = self.settings.lock().unwrap().proxy.clone();
+                let proxied = proxy.proxied();
+                if !proxied.is_empty() {
+                    ui.label(
+                        egui::RichText::new(format!("• 🛡 {} proxy", proxy.kind.label()))
+                            .color(egui::Color32::LIGHT_GREEN),
+                    )
+                    .on_hover_text(format!(
+                        "Through {}:{}: {}\nDirect: {}",
+                        proxy.host.trim(),
+                        proxy.port,
+                        proxied.join(", "),
+                        ProxySettings::DIRECT
+                    ));
+                }
+                let network = self.network.lock().unwrap();
+                if network.is_paused {
+                    ui.label(
+                        egui::RichText::new(format!(
+                            "• ⚠ {} is down, session paused",
+                            network.interface
+                        ))
+                        .color(egui::Color32::RED),
+                    );
+                } else if !network.interface.is_empty() {
+                    let addresses: Vec<String> =
+                        network.addresses.iter().map(|a| a.to_string()).collect();
+                    ui.label(format!("• 🔗 {}", network.interface))
+                        .on_hover_text(format!("Bound to {}", addresses.join(", ")));
+                }
+                drop(network);
+                ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
+                    ui.toggle_value(&mut self.show_graph, "📈")
+                        .on_hover_text("Speed graph");
+                });
+            });
+            if self.show_graph {
+                let limits = self.settings.lock().unwrap().speed_limits.clone();
+                ui.add(SpeedGraphWidget {
+                    history: &history.session,
+                    range: &mut self.session_graph_range,
+                    max_range: GraphRange::SixHours,
+                    download_limit: (limits.download > 0).then_some(limits.download as i64),
+                    upload_limit: (limits.upload > 0).then_some(limits.upload as i64),
+                    height: 100.0,
+                });
+                ui.add_space(5.0);
+            }
+        });
+
         // Bottom panel
         let sel_torrent = *self.sel_torrent.lock().unwrap();
         if let Some(index) = sel_torrent {
@@ -166,14 +535,44 @@ impl eframe::App for AppState {
                         channel_tx: &self.channel_tx,
                         torrent,
                         index,
+                        history: history.torrents.get(&torrent.hash),
+                        graph_range: &mut self.torrent_graph_range,
+                        peer_layout: &mut self.peer_table_layout,
+                        peers_draft: &mut self.peers_draft,
                     });
                 });
         }
+        drop(history);
 
         // Central panel
         egui::CentralPanel::default().show(ctx, |ui| {
             ctx.set_pixels_per_point(1.3);
 
+            // Filter sidebar
+            egui::SidePanel::left("filters")
+                .resizable(true)
+                .default_width(160.0)
+                .show_inside(ui, |ui| {
+                    ui.add(FilterWidget {
+                        filter: &mut self.filter,
+                        torrents: &torrents,
+                        categories: &categories,
+                        category_draft: &mut self.category_draft,
+                        channel_tx: &self.channel_tx,
+                    });
+                });
+
+            // Above the list, to stay reachable whatever it shows
+            let matcher = self.search_cache.matcher(&self.search);
+            ui.add(SearchWidget {
+                search: &mut self.search,
+                sort: &mut self.sort,
+                error: matcher.as_ref().err().map(String::as_str),
+                mode: &mut self.list_mode,
+                layout: &mut self.table_layout,
+            });
+            ui.add_space(5.0);
+
             egui::ScrollArea::vertical().show(ui, |ui| {
                 // Handle drag and drop
                 let has_hovering_files = ctx.input(|i| !i.raw.hovered_files.is_empty());
@@ -183,30 +582,131 @@ impl eframe::App for AppState {
                     &mut add_btn_clicked,
                     ctx,
                 ));
+                ui.add_space(5.0);
+                ui.horizontal(|ui| {
+                    ui.label("Add to category: ");
+                    let selected = if self.new_torrent_category.is_empty() {
+                        "None"
+                    } else {
+                        &self.new_torrent_category
+                    };
+                    egui::ComboBox::from_id_salt("new_torrent_category")
+                        .selected_text(selected)
+                        .show_ui(ui, |ui| {
+                            ui.selectable_value(
+                                &mut self.new_torrent_category,
+                                "".to_owned(),
+                                "None",
+                            );
+                            for category in &categories {
+                                ui.selectable_value(
+                                    &mut self.new_torrent_category,
+                                    category.name.clone(),
+                                    &category.name,
+                                );
+                            }
+                        });
+                });
                 ui.add_space(10.0);
+                let category = &self.new_torrent_category;
                 let dropped_files = ctx.input(|r| r.raw.dropped_files.clone());
-                add_torrent::handle_file_drop(&dropped_files, &self.channel_tx);
+                add_torrent::handle_file_drop(&dropped_files, category, &self.channel_tx);
 
                 // Handle "torrent add" from a file
                 if add_btn_clicked {
-                    add_torrent::handle_file_add(&mut toasts, &self.channel_tx);
+                    add_torrent::handle_file_add(&mut toasts, category, &self.channel_tx);
                 }
 
                 // Listen for pasted magnet URLs
-                add_torrent::handle_magnet_pastes(ctx, &self.channel_tx);
+                add_torrent::handle_magnet_pastes(ctx, category, &self.channel_tx);
 
                 // Show the torrents
                 if !torrents.is_empty() {
                     ui.heading("Torrents");
                     ui.add_space(5.0);
-                    for (index, torrent) in torrents.iter().enumerate() {
-                        ui.add(TorrentWidget {
-                            torrent,
-                            sel_torrent: *self.sel_torrent.lock().unwrap(),
-                            index,
+
+                    let mut indices: Vec<usize> = match matcher {
+                        Ok(matcher) => (0..torrents.len())
+                            .filter(|i| {
+                                self.filter.matches(&torrents[*i]) && matcher.matches(&torrents[*i])
+                            })
+                            .collect(),
+                        Err(_) => vec![],
+                    };
+                    self.sort.sort(&mut indices, &torrents);
+                    if indices.len() < torrents.len() {
+                        ui.label(format!("Showing {} of {}", indices.len(), torrents.len()));
+                        ui.add_space(5.0);
+                    }
+
+                    // Selection
+                    self.selection.truncate(torrents.len());
+                    if ctx.memory(|m| m.focused().is_none()) {
+                        if ctx.input(|i| i.modifiers.command && i.key_pressed(egui::Key::A)) {
+                            self.selection.select_all(&indices);
+                        }
+                        if ctx.input(|i| i.key_pressed(egui::Key::Escape)) {
+                            self.selection.clear();
+                        }
+                    }
+                    if !self.selection.indices.is_empty() {
+                        let mut select_all = false;
+                        let mut clear_selection = false;
+                        ui.add(BatchWidget {
+                            selection: self.selection.to_vec(),
+                            num_visible: indices.len(),
+                            categories: &categories,
+                            tags: &tags,
+                            draft: &mut self.batch_draft,
                             channel_tx: &self.channel_tx,
+                            select_all: &mut select_all,
+                            clear_selection: &mut clear_selection,
                         });
-                        ui.add_space(10.0);
+                        ui.add_space(5.0);
+                        if select_all {
+                            self.selection.select_all(&indices);
+                        }
+                        if clear_selection {
+                            self.selection.clear();
+                        }
+                    }
+
+                    let mut clicked = None;
+                    match self.list_mode {
+                        ListMode::Cards => {
+                            for index in indices.iter().copied() {
+                                let torrent = &torrents[index];
+                                let title = ui.add(TorrentWidget {
+                                    torrent,
+                                    is_selected: self.selection.indices.contains(&index),
+                                    sel_torrent: *self.sel_torrent.lock().unwrap(),
+                                    index,
+                                    channel_tx: &self.channel_tx,
+                                    categories: &categories,
+                                    tags: &tags,
+                                });
+                                if title.clicked() {
+                                    clicked = Some(index);
+                                }
+                                ui.add_space(10.0);
+                            }
+                        }
+                        ListMode::Table => {
+                            ui.add(TorrentTableWidget {
+                                torrents: &torrents,
+                                indices: &indices,
+                                selection: &self.selection,
+                                layout: &self.table_layout,
+                                sort: &mut self.sort,
+                                clicked: &mut clicked,
+                                channel_tx: &self.channel_tx,
+                            });
+                        }
+                    }
+                    if let Some(index) = clicked {
+                        let modifiers = ui.input(|i| i.modifiers);
+                        self.selection
+                            .click(index, &indices, modifiers.command, modifiers.shift);
                     }
                 }
             });
@@ -216,6 +716,15 @@ impl eframe::App for AppState {
         });
     }
 
+    fn save(&mut self, storage: &mut dyn eframe::Storage) {
+        eframe::set_value(storage, FILTER_KEY, &self.filter);
+        eframe::set_value(storage, SEARCH_KEY, &self.search);
+        eframe::set_value(storage, SORT_KEY, &self.sort);
+        eframe::set_value(storage, LIST_MODE_KEY, &self.list_mode);
+        eframe::set_value(storage, TABLE_LAYOUT_KEY, &self.table_layout);
+        eframe::set_value(storage, PEER_TABLE_LAYOUT_KEY, &self.peer_table_layout);
+    }
+
     fn on_exit(&mut self, _gl: Option<&eframe::glow::Context>) {
         self.channel_tx.send(Message::Stop).unwrap();
         loop {
@@ -0,0 +1,58 @@
+use std::{fs, path::Path};
+
+use serde::{Deserialize, Serialize};
+
+use super::torrent::Torrent;
+
+const CATEGORIES_FILE: &str = "categories.json";
+
+#[derive(PartialEq, Clone, Debug, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Category {
+    pub name: String,
+    /// Empty for the downloads directory.
+    pub save_path: String,
+    pub ratio_limit: Option<f32>,
+    pub seeding_time_limit_mins: Option<u64>,
+}
+
+impl Category {
+    pub fn is_share_limit_reached(&self, torrent: &Torrent) -> bool {
+        self.ratio_limit
+            .is_some_and(|limit| torrent.ratio() >= limit)
+            || self
+                .seeding_time_limit_mins
+                .is_some_and(|limit| torrent.seeding_duration as u64 >= limit * 60)
+    }
+
+    pub fn load_all(data_dir: &Path) -> Vec<Category> {
+        fs::read_to_string(data_dir.join(CATEGORIES_FILE))
+            .ok()
+            .and_then(|contents| serde_json::from_str(&contents).ok())
+            .unwrap_or_default()
+    }
+
+    pub fn save_all(data_dir: &Path, categories: &[Category]) -> bool {
+        let contents =
+            serde_json::to_string_pretty(categories).expect("Failed to serialize categories");
+        fs::write(data_dir.join(CATEGORIES_FILE), contents).is_ok()
+    }
+}
+
+#[derive(PartialEq, Clone, Debug, Default, Serialize, Deserialize)]
+pub enum CategoryFilter {
+    #[default]
+    All,
+    Uncategorized,
+    Category(String),
+}
+
+impl CategoryFilter {
+    pub fn matches(&self, torrent: &Torrent) -> bool {
+        match self {
+            CategoryFilter::All => true,
+            CategoryFilter::Uncategorized => torrent.category.is_empty(),
+            CategoryFilter::Category(name) => &torrent.category == name,
+        }
+    }
+}
@@ -0,0 +1,283 @@
+use std::{cmp::Ordering, net::IpAddr};
+
+use serde::{Deserialize, Serialize};
+
+use super::{peer::Peer, sort::SortKey};
+
+#[derive(PartialEq, Clone, Copy, Debug, Default, Serialize, Deserialize)]
+pub enum ListMode {
+    #[default]
+    Cards,
+    Table,
+}
+
+#[derive(PartialEq, Eq, Hash, Clone, Copy, Debug, Serialize, Deserialize)]
+pub enum TorrentColumn {
+    Name,
+    Size,
+    Progress,
+    State,
+    Seeds,
+    Peers,
+    DownloadRate,
+    UploadRate,
+    Eta,
+    Ratio,
+    Added,
+    Category,
+    Tracker,
+    SavePath,
+}
+
+impl TorrentColumn {
+    pub const ALL: [TorrentColumn; 14] = [
+        TorrentColumn::Name,
+        TorrentColumn::Size,
+        TorrentColumn::Progress,
+        TorrentColumn::State,
+        TorrentColumn::Seeds,
+        TorrentColumn::Peers,
+        TorrentColumn::DownloadRate,
+        TorrentColumn::UploadRate,
+        TorrentColumn::Eta,
+        TorrentColumn::Ratio,
+        TorrentColumn::Added,
+        TorrentColumn::Category,
+        TorrentColumn::Tracker,
+        TorrentColumn::SavePath,
+    ];
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            TorrentColumn::Name => "Name",
+            TorrentColumn::Size => "Size",
+            TorrentColumn::Progress => "Progress",
+            TorrentColumn::State => "State",
+            TorrentColumn::Seeds => "Seeds",
+            TorrentColumn::Peers => "Peers",
+            TorrentColumn::DownloadRate => "Down Speed",
+            TorrentColumn::UploadRate => "Up Speed",
+            TorrentColumn::Eta => "ETA",
+            TorrentColumn::Ratio => "Ratio",
+            TorrentColumn::Added => "Added",
+            TorrentColumn::Category => "Category",
+            TorrentColumn::Tracker => "Tracker",
+            TorrentColumn::SavePath => "Save Path",
+        }
+    }
+
+    pub fn initial_width(&self) -> f32 {
+        match self {
+            TorrentColumn::Name => 250.0,
+            TorrentColumn::Progress | TorrentColumn::Tracker | TorrentColumn::SavePath => 120.0,
+            TorrentColumn::Seeds | TorrentColumn::Peers | TorrentColumn::Ratio => 50.0,
+            _ => 80.0,
+        }
+    }
+
+    /// The key sorting the list when the header of the column is clicked.
+    pub fn sort_key(&self) -> Option<SortKey> {
+        match self {
+            TorrentColumn::Name => Some(SortKey::Name),
+            TorrentColumn::Size => Some(SortKey::Size),
+            TorrentColumn::Progress => Some(SortKey::Progress),
+            TorrentColumn::DownloadRate => Some(SortKey::DownloadSpeed),
+            TorrentColumn::UploadRate => Some(SortKey::UploadSpeed),
+            TorrentColumn::Eta => Some(SortKey::Eta),
+            TorrentColumn::Ratio => Some(SortKey::Ratio),
+            TorrentColumn::Added => Some(SortKey::Added),
+            _ => None,
+        }
+    }
+}
+
+/// The order of the columns of the table and whether each is shown.
+#[derive(PartialEq, Clone, Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub struct TableLayout {
+    pub columns: Vec<(TorrentColumn, bool)>,
+}
+
+impl Default for TableLayout {
+    fn default() -> Self {
+        let hidden = [
+            TorrentColumn::Added,
+            TorrentColumn::Category,
+            TorrentColumn::Tracker,
+            TorrentColumn::SavePath,
+        ];
+        Self {
+            columns: TorrentColumn::ALL
+                .iter()
+                .map(|c| (*c, !hidden.contains(c)))
+                .collect(),
+        }
+    }
+}
+
+impl TableLayout {
+    pub fn visible(&self) -> Vec<TorrentColumn> {
+        self.columns
+            .iter()
+            .filter(|(_, is_visible)| *is_visible)
+            .map(|(column, _)| *column)
+            .collect()
+    }
+
+    /// Moves the column at `index` by `offset` places.
+    pub fn move_column(&mut self, index: usize, offset: isize) {
+        let target = index as isize + offset;
+        if target >= 0 && (target as usize) < self.columns.len() {
+            self.columns.swap(index, target as usize);
+        }
+    }
+}
+
+#[derive(PartialEq, Eq, Hash, Clone, Copy, Debug, Serialize, Deserialize)]
+pub enum PeerColumn {
+    Address,
+    Client,
+    Connection,
+    Encrypted,
+    Flags,
+    Source,
+    Progress,
+    DownloadRate,
+    UploadRate,
+    Downloaded,
+    Uploaded,
+    Relevance,
+    Rtt,
+}
+
+impl PeerColumn {
+    pub const ALL: [PeerColumn; 13] = [
+        PeerColumn::Address,
+        PeerColumn::Client,
+        PeerColumn::Connection,
+        PeerColumn::Encrypted,
+        PeerColumn::Flags,
+        PeerColumn::Source,
+        PeerColumn::Progress,
+        PeerColumn::DownloadRate,
+        PeerColumn::UploadRate,
+        PeerColumn::Downloaded,
+        PeerColumn::Uploaded,
+        PeerColumn::Relevance,
+        PeerColumn::Rtt,
+    ];
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            PeerColumn::Address => "Address",
+            PeerColumn::Client => "Client",
+            PeerColumn::Connection => "Connection",
+            PeerColumn::Encrypted => "Encrypted",
+            PeerColumn::Flags => "Flags",
+            PeerColumn::Source => "Source",
+            PeerColumn::Progress => "Progress",
+            PeerColumn::DownloadRate => "Down Speed",
+            PeerColumn::UploadRate => "Up Speed",
+            PeerColumn::Downloaded => "Downloaded",
+            PeerColumn::Uploaded => "Uploaded",
+            PeerColumn::Relevance => "Relevance",
+            PeerColumn::Rtt => "RTT",
+        }
+    }
+
+    pub fn initial_width(&self) -> f32 {
+        match self {
+            PeerColumn::Address => 200.0,
+            PeerColumn::Client | PeerColumn::Progress => 120.0,
+            PeerColumn::Flags | PeerColumn::Rtt => 50.0,
+            _ => 80.0,
+        }
+    }
+
+    pub fn compare(&self, a: &Peer, b: &Peer) -> Ordering {
+        match self {
+            // Numerically, IPv4 first
+            PeerColumn::Address => {
+                let key = |p: &Peer| (p.ip_address.parse::<IpAddr>().ok(), p.port);
+                key(a).cmp(&key(b))
+            }
+            PeerColumn::Client => a.client.to_lowercase().cmp(&b.client.to_lowercase()),
+            PeerColumn::Connection => a.connection.label().cmp(b.connection.label()),
+            PeerColumn::Encrypted => a.is_encrypted.cmp(&b.is_encrypted),
+            PeerColumn::Flags => {
+                let key = |p: &Peer| -> String {
+                    p.flags.abbreviations().iter().map(|(c, _)| c).collect()
+                };
+                key(a).cmp(&key(b))
+            }
+            PeerColumn::Source => {
+                let key = |p: &Peer| p.sources.iter().map(|s| s.label()).collect::<Vec<_>>();
+                key(a).cmp(&key(b))
+            }
+            PeerColumn::Progress => a.progress.total_cmp(&b.progress),
+            PeerColumn::DownloadRate => a.download_rate.cmp(&b.download_rate),
+            PeerColumn::UploadRate => a.upload_rate.cmp(&b.upload_rate),
+            PeerColumn::Downloaded => a.total_download.cmp(&b.total_download),
+            PeerColumn::Uploaded => a.total_upload.cmp(&b.total_upload),
+            PeerColumn::Relevance => a.relevance.total_cmp(&b.relevance),
+            PeerColumn::Rtt => a.rtt.cmp(&b.rtt),
+        }
+    }
+}
+
+/// The columns of the peers table, whether each is shown, and its order.
+#[derive(PartialEq, Clone, Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub struct PeerTableLayout {
+    pub columns: Vec<(PeerColumn, bool)>,
+    pub sort_column: PeerColumn,
+    pub sort_descending: bool,
+}
+
+impl Default for PeerTableLayout {
+    fn default() -> Self {
+        let hidden = [PeerColumn::Encrypted, PeerColumn::Source, PeerColumn::Rtt];
+        Self {
+            columns: PeerColumn::ALL
+                .iter()
+                .map(|c| (*c, !hidden.contains(c)))
+                .collect(),
+            sort_column: PeerColumn::DownloadRate,
+            sort_descending: true,
+        }
+    }
+}
+
+impl PeerTableLayout {
+    pub fn visible(&self) -> Vec<PeerColumn> {
+        self.columns
+            .iter()
+            .filter(|(_, is_visible)| *is_visible)
+            .map(|(column, _)| *column)
+            .collect()
+    }
+
+    /// Sorts by the column, reversing the order if it already sorts.
+    pub fn sort_by(&mut self, column: PeerColumn) {
+        if self.sort_column == column {
+            self.sort_descending = !self.sort_descending;
+        } else {
+            self.sort_column = column;
+            self.sort_descending = false;
+        }
+    }
+
+    /// The indices of the peers in order, keeping the original order for ties.
+    pub fn sorted(&self, peers: &[Peer]) -> Vec<usize> {
+        let mut indices: Vec<usize> = (0..peers.len()).collect();
+        indices.sort_by(|a, b| {
+            let ordering = self.sort_column.compare(&peers[*a], &peers[*b]);
+            if self.sort_descending {
+                ordering.reverse()
+            } else {
+                ordering
+            }
+        });
+        indices
+    }
+}
@@ -0,0 +1,167 @@
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use super::{
+    message::QueueMove,
+    torrent::{Torrent, TorrentFilePriority},
+};
+
+/// A request over the control socket, one JSON object per line such as
+/// `{"command": "pause", "hash": "…"}`.
+#[derive(PartialEq, Clone, Debug, Serialize, Deserialize)]
+#[serde(tag = "command", rename_all = "kebab-case")]
+pub enum ControlRequest {
+    List,
+    Info {
+        hash: String,
+    },
+    Files {
+        hash: String,
+    },
+    Peers {
+        hash: String,
+    },
+    Trackers {
+        hash: String,
+    },
+    Categories,
+    /// The default save path and the global limits.
+    Session,
+    /// Adds a .torrent file by path or a magnet link.
+    Add {
+        source: String,
+        /// The category's save path or the downloads directory if empty.
+        #[serde(default)]
+        save_path: String,
+        #[serde(default)]
+        category: String,
+        #[serde(default)]
+        paused: bool,
+    },
+    Pause {
+        hash: String,
+    },
+    Resume {
+        hash: String,
+    },
+    Remove {
+        hash: String,
+        #[serde(default)]
+        delete_files: bool,
+    },
+    Recheck {
+        hash: String,
+    },
+    Reannounce {
+        hash: String,
+    },
+    Move {
+        hash: String,
+        path: String,
+    },
+    SetCategory {
+        hash: String,
+        category: String,
+    },
+    AddTag {
+        hash: String,
+        tag: String,
+    },
+    RemoveTag {
+        hash: String,
+        tag: String,
+    },
+    Queue {
+        hash: String,
+        to: QueueMove,
+    },
+    SetFilePriority {
+        hash: String,
+        index: usize,
+        priority: TorrentFilePriority,
+    },
+    /// Hands over the magnet links and .torrent files a second instance was
+    /// launched with.
+    Open {
+        sources: Vec<String>,
+    },
+    /// Sets the limits of the torrent, or the global ones without a hash, in
+    /// bytes per second. 0 is unlimited and a missing limit is left as is.
+    SetLimit {
+        #[serde(default)]
+        hash: Option<String>,
+        #[serde(default)]
+        download: Option<i32>,
+        #[serde(default)]
+        upload: Option<i32>,
+    },
+}
+
+#[derive(PartialEq, Clone, Debug, Serialize, Deserialize)]
+pub struct ControlResponse {
+    pub ok: bool,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+    #[serde(default, skip_serializing_if = "Value::is_null")]
+    pub data: Value,
+}
+
+impl ControlResponse {
+    pub fn success(data: Value) -> Self {
+        Self {
+            ok: true,
+            error: None,
+            data,
+        }
+    }
+
+    pub fn failure<S: Into<String>>(error: S) -> Self {
+        Self {
+            ok: false,
+            error: Some(error.into()),
+            data: Value::Null,
+        }
+    }
+}
+
+/// A torrent as listed by the control socket.
+#[derive(PartialEq, Clone, Debug, Serialize, Deserialize)]
+pub struct TorrentSummary {
+    pub hash: String,
+    pub name: String,
+    pub state: String,
+    pub progress: f32,
+    pub total_size: i64,
+    pub download_rate: i64,
+    pub upload_rate: i64,
+    pub num_peers: i32,
+    pub num_seeds: i32,
+    pub ratio: f32,
+    pub eta: i64,
+    pub save_path: String,
+    pub category: String,
+    pub tags: Vec<String>,
+    pub error: String,
+}
+
+impl From<&Torrent> for TorrentSummary {
+    fn from(torrent: &Torrent) -> Self {
+        Self {
+            hash: torrent.hash.clone(),
+            name: torrent.name.clone(),
+            state: torrent.state.to_string(),
+            progress: torrent.progress,
+            total_size: torrent.total_size,
+            download_rate: torrent.download_rate,
+            upload_rate: torrent.upload_rate,
+            num_peers: torrent.num_peers,
+            num_seeds: torrent.num_seeds,
+            ratio: torrent.ratio(),
+            eta: torrent.eta,
+            save_path: torrent.save_path.clone(),
+            category: torrent.category.clone(),
+            tags: torrent.tags.clone(),
+            error: torrent.error.clone(),
+        }
+    }
+}
@@ -0,0 +1,5 @@
+#[derive(PartialEq, Clone, Debug)]
+pub enum TorrentEvent {
+    Added(String),
+    Finished(String),
+}
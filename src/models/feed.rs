@@ -0,0 +1,16 @@
+#[derive(PartialEq, Clone, Debug, Default)]
+pub struct FeedItem {
+    pub title: String,
+    /// URL of the .torrent file or a magnet URL.
+    pub url: String,
+    pub guid: String,
+    pub size: Option<i64>,
+}
+
+#[derive(PartialEq, Clone, Debug, Default)]
+pub struct Feed {
+    pub url: String,
+    pub title: String,
+    pub items: Vec<FeedItem>,
+    pub error: Option<String>,
+}
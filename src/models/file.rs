@@ -1,6 +1,8 @@
+use serde::Serialize;
+
 use super::torrent::TorrentFilePriority;
 
-#[derive(Clone)]
+#[derive(Clone, Serialize)]
 pub struct File {
     pub path: String,
     pub priority: TorrentFilePriority,
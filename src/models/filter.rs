@@ -0,0 +1,179 @@
+use regex::{Regex, RegexBuilder};
+use serde::{Deserialize, Serialize};
+
+use super::{
+    category::CategoryFilter,
+    torrent::{Torrent, TorrentState},
+};
+
+#[derive(PartialEq, Clone, Copy, Debug, Default, Serialize, Deserialize)]
+pub enum StateFilter {
+    #[default]
+    All,
+    Downloading,
+    Seeding,
+    Paused,
+    Errored,
+    Stalled,
+}
+
+impl StateFilter {
+    pub const ALL: [StateFilter; 6] = [
+        StateFilter::All,
+        StateFilter::Downloading,
+        StateFilter::Seeding,
+        StateFilter::Paused,
+        StateFilter::Errored,
+        StateFilter::Stalled,
+    ];
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            StateFilter::All => "All",
+            StateFilter::Downloading => "Downloading",
+            StateFilter::Seeding => "Seeding",
+            StateFilter::Paused => "Paused",
+            StateFilter::Errored => "Errored",
+            StateFilter::Stalled => "Stalled",
+        }
+    }
+
+    pub fn matches(&self, torrent: &Torrent) -> bool {
+        match self {
+            StateFilter::All => true,
+            StateFilter::Downloading => matches!(
+                torrent.state,
+                TorrentState::Downloading | TorrentState::DownloadingMetaData
+            ),
+            StateFilter::Seeding => {
+                matches!(
+                    torrent.state,
+                    TorrentState::Seeding | TorrentState::Finished
+                )
+            }
+            StateFilter::Paused => torrent.state == TorrentState::Paused,
+            StateFilter::Errored => !torrent.error.is_empty(),
+            StateFilter::Stalled => torrent.is_stalled(),
+        }
+    }
+}
+
+#[derive(PartialEq, Clone, Debug, Default, Serialize, Deserialize)]
+pub enum TagFilter {
+    #[default]
+    All,
+    Untagged,
+    Tag(String),
+}
+
+impl TagFilter {
+    pub fn matches(&self, torrent: &Torrent) -> bool {
+        match self {
+            TagFilter::All => true,
+            TagFilter::Untagged => torrent.tags.is_empty(),
+            TagFilter::Tag(tag) => torrent.tags.contains(tag),
+        }
+    }
+}
+
+#[derive(PartialEq, Clone, Debug, Default, Serialize, Deserialize)]
+pub enum TrackerFilter {
+    #[default]
+    All,
+    Trackerless,
+    Host(String),
+}
+
+impl TrackerFilter {
+    pub fn matches(&self, torrent: &Torrent) -> bool {
+        match self {
+            TrackerFilter::All => true,
+            TrackerFilter::Trackerless => torrent.trackers.is_empty(),
+            TrackerFilter::Host(host) => torrent
+                .trackers
+                .iter()
+                .any(|t| t.host().as_ref() == Some(host)),
+        }
+    }
+}
+
+/// The filters of the sidebar; a torrent is listed if it matches all of them.
+#[derive(PartialEq, Clone, Debug, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct TorrentFilter {
+    pub state: StateFilter,
+    pub category: CategoryFilter,
+    pub tag: TagFilter,
+    pub tracker: TrackerFilter,
+}
+
+impl TorrentFilter {
+    pub fn matches(&self, torrent: &Torrent) -> bool {
+        self.state.matches(torrent)
+            && self.category.matches(torrent)
+            && self.tag.matches(torrent)
+            && self.tracker.matches(torrent)
+    }
+}
+
+/// The search box above the torrent list.
+#[derive(PartialEq, Clone, Debug, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Search {
+    #[serde(skip)]
+    pub text: String,
+    pub is_regex: bool,
+}
+
+pub enum SearchMatcher {
+    All,
+    Substring(String),
+    Regex(Regex),
+}
+
+impl Search {
+    pub fn matcher(&self) -> Result<SearchMatcher, String> {
+        if self.text.trim().is_empty() {
+            Ok(SearchMatcher::All)
+        } else if self.is_regex {
+            RegexBuilder::new(&self.text)
+                .case_insensitive(true)
+                .build()
+                .map(SearchMatcher::Regex)
+                .map_err(|e| e.to_string())
+        } else {
+            Ok(SearchMatcher::Substring(self.text.trim().to_lowercase()))
+        }
+    }
+}
+
+/// The matcher of the last search, only rebuilt when the search changes.
+#[derive(Default)]
+pub struct SearchCache(Option<(Search, Result<SearchMatcher, String>)>);
+
+impl SearchCache {
+    pub fn matcher(&mut self, search: &Search) -> &Result<SearchMatcher, String> {
+        if !matches!(&self.0, Some((cached, _)) if cached == search) {
+            self.0 = Some((search.clone(), search.matcher()));
+        }
+        &self.0.as_ref().unwrap().1
+    }
+}
+
+impl SearchMatcher {
+    /// Searches the name, hash, comment and tracker URLs of the torrent.
+    pub fn matches(&self, torrent: &Torrent) -> bool {
+        [&torrent.name, &torrent.hash, &torrent.comment]
+            .into_iter()
+            .chain(torrent.trackers.iter().map(|t| &t.url))
+            .any(|field| self.matches_text(field))
+    }
+
+    pub fn matches_text(&self, field: &str) -> bool {
+        match self {
+            SearchMatcher::All => true,
+            SearchMatcher::Substring(text) => field.to_lowercase().contains(text),
+            SearchMatcher::Regex(re) => re.is_match(field),
+        }
+    }
+}
@@ -0,0 +1,132 @@
+use std::{
+    collections::{HashMap, HashSet, VecDeque},
+    time::{Duration, Instant},
+};
+
+use super::torrent::Torrent;
+
+/// Samples kept of the session's rates, one per second.
+pub const SESSION_HISTORY_LEN: usize = 6 * 60 * 60;
+/// Samples kept of each torrent's rates.
+pub const TORRENT_HISTORY_LEN: usize = 60 * 60;
+
+#[derive(Clone, Copy, Debug)]
+pub struct RateSample {
+    pub time: Instant,
+    pub download: i64,
+    pub upload: i64,
+}
+
+/// A ring buffer of download and upload rates, grown as samples come in.
+pub struct SpeedHistory {
+    samples: VecDeque<RateSample>,
+    capacity: usize,
+}
+
+impl SpeedHistory {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            samples: VecDeque::new(),
+            capacity,
+        }
+    }
+
+    pub fn push(&mut self, sample: RateSample) {
+        if self.samples.len() == self.capacity {
+            self.samples.pop_front();
+        }
+        self.samples.push_back(sample);
+    }
+
+    /// The samples taken in the `range` before `now`, oldest first.
+    pub fn since(&self, now: Instant, range: Duration) -> impl Iterator<Item = &RateSample> {
+        let start = now.checked_sub(range);
+        let skip = self
+            .samples
+            .partition_point(|s| start.is_some_and(|start| s.time < start));
+        self.samples.range(skip..)
+    }
+}
+
+/// The rates of the session and of each torrent, by hash.
+pub struct SpeedHistories {
+    pub session: SpeedHistory,
+    pub torrents: HashMap<String, SpeedHistory>,
+}
+
+impl Default for SpeedHistories {
+    fn default() -> Self {
+        Self {
+            session: SpeedHistory::new(SESSION_HISTORY_LEN),
+            torrents: HashMap::new(),
+        }
+    }
+}
+
+impl SpeedHistories {
+    pub fn record(&mut self, time: Instant, torrents: &[Torrent]) {
+        let mut session = RateSample {
+            time,
+            download: 0,
+            upload: 0,
+        };
+        for torrent in torrents {
+            session.download += torrent.download_rate;
+            session.upload += torrent.upload_rate;
+            self.torrents
+                .entry(torrent.hash.clone())
+                .or_insert_with(|| SpeedHistory::new(TORRENT_HISTORY_LEN))
+                .push(RateSample {
+                    time,
+                    download: torrent.download_rate,
+                    upload: torrent.upload_rate,
+                });
+        }
+        self.session.push(session);
+
+        // Forget the removed torrents
+        let hashes: HashSet<&String> = torrents.iter().map(|t| &t.hash).collect();
+        self.torrents.retain(|hash, _| hashes.contains(hash));
+    }
+}
+
+#[derive(PartialEq, Clone, Copy, Debug, Default)]
+pub enum GraphRange {
+    OneMinute,
+    #[default]
+    FiveMinutes,
+    ThirtyMinutes,
+    OneHour,
+    SixHours,
+}
+
+impl GraphRange {
+    pub const ALL: [GraphRange; 5] = [
+        GraphRange::OneMinute,
+        GraphRange::FiveMinutes,
+        GraphRange::ThirtyMinutes,
+        GraphRange::OneHour,
+        GraphRange::SixHours,
+    ];
+
+    pub fn duration(&self) -> Duration {
+        let mins = match self {
+            GraphRange::OneMinute => 1,
+            GraphRange::FiveMinutes => 5,
+            GraphRange::ThirtyMinutes => 30,
+            GraphRange::OneHour => 60,
+            GraphRange::SixHours => 360,
+        };
+        Duration::from_secs(mins * 60)
+    }
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            GraphRange::OneMinute => "1 min",
+            GraphRange::FiveMinutes => "5 min",
+            GraphRange::ThirtyMinutes => "30 min",
+            GraphRange::OneHour => "1 hour",
+            GraphRange::SixHours => "6 hours",
+        }
+    }
+}
@@ -0,0 +1,126 @@
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
+
+/// Addresses from `first` to `last`, both included, of the same family.
+#[derive(PartialEq, Eq, Clone, Copy, Debug)]
+pub struct IpRange {
+    pub first: IpAddr,
+    pub last: IpAddr,
+}
+
+/// What the filter applied to the session blocks.
+#[derive(Default)]
+pub struct IpFilterStatus {
+    pub num_ranges: usize,
+    /// Connections refused since the session started.
+    pub blocked_peers: i64,
+    /// Sources which failed to load at the last refresh, with the reasons.
+    pub errors: Vec<String>,
+}
+
+fn to_bits(addr: IpAddr) -> (bool, u128) {
+    match addr {
+        IpAddr::V4(addr) => (false, u32::from(addr) as u128),
+        IpAddr::V6(addr) => (true, u128::from(addr)),
+    }
+}
+
+fn from_bits(is_v6: bool, bits: u128) -> IpAddr {
+    if is_v6 {
+        IpAddr::V6(Ipv6Addr::from(bits))
+    } else {
+        IpAddr::V4(Ipv4Addr::from(bits as u32))
+    }
+}
+
+/// An address, allowing the zero padding of eMule lists, e.g. `001.002.003.004`.
+fn parse_addr(s: &str) -> Option<IpAddr> {
+    let s = s.trim();
+    if s.contains(':') {
+        return s.parse::<Ipv6Addr>().ok().map(IpAddr::V6);
+    }
+    let octets: Vec<u8> = s
+        .split('.')
+        .map(|octet| octet.parse().ok())
+        .collect::<Option<_>>()?;
+    let octets: [u8; 4] = octets.try_into().ok()?;
+    Some(IpAddr::V4(Ipv4Addr::from(octets)))
+}
+
+/// A range `first-last`, a CIDR block `addr/prefix` or a single address.
+fn parse_range(s: &str) -> Option<IpRange> {
+    if let Some((addr, prefix)) = s.split_once('/') {
+        let (is_v6, bits) = to_bits(parse_addr(addr)?);
+        let width = if is_v6 { 128 } else { 32 };
+        let prefix: u32 = prefix.trim().parse().ok().filter(|p| *p <= width)?;
+        let host_mask = u128::MAX.checked_shr(128 - width + prefix).unwrap_or(0);
+        return Some(IpRange {
+            first: from_bits(is_v6, bits & !host_mask),
+            last: from_bits(is_v6, bits | host_mask),
+        });
+    }
+    let (first, last) = match s.split_once('-') {
+        Some((first, last)) => (parse_addr(first)?, parse_addr(last)?),
+        None => {
+            let addr = parse_addr(s)?;
+            (addr, addr)
+        }
+    };
+    let is_valid = first.is_ipv4() == last.is_ipv4() && to_bits(first) <= to_bits(last);
+    is_valid.then_some(IpRange { first, last })
+}
+
+/// A line of an eMule `ipfilter.dat`, `first - last , level , description`,
+/// of a PeerGuardian P2P list, `description:first-last`, or of a CIDR list.
+/// `None` for comments, invalid lines and eMule ranges above level 127,
+/// which eMule allows.
+pub fn parse_line(line: &str) -> Option<IpRange> {
+    let line = line.trim();
+    if line.is_empty() || line.starts_with('#') || line.starts_with("//") {
+        return None;
+    }
+    // P2P descriptions may contain commas too
+    if let Some(range) = line
+        .split_once(',')
+        .and_then(|(range, _)| parse_range(range))
+    {
+        let level = line.split(',').nth(1).unwrap_or("").trim();
+        let is_allowed = level.parse::<u32>().is_ok_and(|level| level > 127);
+        return (!is_allowed).then_some(range);
+    }
+    // Descriptions may contain colons, IPv4 ranges don't
+    parse_range(line).or_else(|| parse_range(line.rsplit_once(':')?.1))
+}
+
+pub fn parse_list(text: &str) -> Vec<IpRange> {
+    text.lines().filter_map(parse_line).collect()
+}
+
+/// Sorts the ranges, joining those which overlap or touch.
+pub fn merge(ranges: Vec<IpRange>) -> Vec<IpRange> {
+    let mut bounds: Vec<(bool, u128, u128)> = ranges
+        .iter()
+        .map(|r| {
+            let (is_v6, first) = to_bits(r.first);
+            (is_v6, first, to_bits(r.last).1)
+        })
+        .collect();
+    bounds.sort();
+    let mut merged: Vec<(bool, u128, u128)> = vec![];
+    for (is_v6, first, last) in bounds {
+        match merged.last_mut() {
+            Some(prev)
+                if prev.0 == is_v6 && prev.2.checked_add(1).is_none_or(|next| first <= next) =>
+            {
+                prev.2 = prev.2.max(last)
+            }
+            _ => merged.push((is_v6, first, last)),
+        }
+    }
+    merged
+        .into_iter()
+        .map(|(is_v6, first, last)| IpRange {
+            first: from_bits(is_v6, first),
+            last: from_bits(is_v6, last),
+        })
+        .collect()
+}
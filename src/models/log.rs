@@ -0,0 +1,263 @@
+use std::{
+    collections::{HashMap, VecDeque},
+    fmt,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use serde::{Deserialize, Serialize};
+
+use super::filter::{Search, SearchMatcher};
+
+#[derive(
+    PartialEq, Eq, PartialOrd, Ord, Hash, Clone, Copy, Debug, Default, Serialize, Deserialize,
+)]
+pub enum LogLevel {
+    Debug,
+    #[default]
+    Info,
+    Warning,
+    Error,
+}
+
+impl LogLevel {
+    pub const ALL: [LogLevel; 4] = [
+        LogLevel::Debug,
+        LogLevel::Info,
+        LogLevel::Warning,
+        LogLevel::Error,
+    ];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            LogLevel::Debug => "Debug",
+            LogLevel::Info => "Info",
+            LogLevel::Warning => "Warning",
+            LogLevel::Error => "Error",
+        }
+    }
+}
+
+impl From<log::Level> for LogLevel {
+    fn from(level: log::Level) -> Self {
+        match level {
+            log::Level::Error => LogLevel::Error,
+            log::Level::Warn => LogLevel::Warning,
+            log::Level::Info => LogLevel::Info,
+            log::Level::Debug | log::Level::Trace => LogLevel::Debug,
+        }
+    }
+}
+
+impl From<LogLevel> for log::Level {
+    fn from(level: LogLevel) -> Self {
+        match level {
+            LogLevel::Error => log::Level::Error,
+            LogLevel::Warning => log::Level::Warn,
+            LogLevel::Info => log::Level::Info,
+            LogLevel::Debug => log::Level::Debug,
+        }
+    }
+}
+
+#[derive(PartialEq, Eq, Hash, Clone, Copy, Debug)]
+pub enum LogSource {
+    App,
+    /// Messages of the C++ bridge.
+    Session,
+    /// libtorrent alerts.
+    Alert,
+}
+
+impl LogSource {
+    pub const ALL: [LogSource; 3] = [LogSource::App, LogSource::Session, LogSource::Alert];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            LogSource::App => "App",
+            LogSource::Session => "Session",
+            LogSource::Alert => "Alert",
+        }
+    }
+
+    /// Target of the source's records in the `log` crate.
+    pub fn target(self) -> &'static str {
+        match self {
+            LogSource::App => "torrenter",
+            LogSource::Session => "libtorrent",
+            LogSource::Alert => "libtorrent::alert",
+        }
+    }
+
+    /// The source of a record, `None` for records of other crates.
+    pub fn from_target(target: &str) -> Option<Self> {
+        if target.starts_with(LogSource::Alert.target()) {
+            Some(LogSource::Alert)
+        } else if target.starts_with(LogSource::Session.target()) {
+            Some(LogSource::Session)
+        } else if target.starts_with(LogSource::App.target()) {
+            Some(LogSource::App)
+        } else {
+            None
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct LogEntry {
+    /// Seconds since the Unix epoch.
+    pub time: u64,
+    pub level: LogLevel,
+    pub source: LogSource,
+    pub message: String,
+}
+
+impl LogEntry {
+    pub fn new(level: LogLevel, source: LogSource, message: String) -> Self {
+        let time = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_secs())
+            .unwrap_or(0);
+        Self {
+            time,
+            level,
+            source,
+            message,
+        }
+    }
+
+    /// Time of day in UTC, `HH:MM:SS`.
+    pub fn time_of_day(&self) -> String {
+        let secs = self.time % 86400;
+        format!("{:02}:{:02}:{:02}", secs / 3600, secs / 60 % 60, secs % 60)
+    }
+}
+
+impl fmt::Display for LogEntry {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "[{}] {} {}: {}",
+            self.time,
+            self.level.label().to_uppercase(),
+            self.source.label(),
+            self.message
+        )
+    }
+}
+
+/// The latest log entries, dropping the oldest ones past the capacity.
+pub struct Log {
+    entries: VecDeque<LogEntry>,
+    capacity: usize,
+    /// Entries ever pushed by source and level, kept when clearing.
+    counts: HashMap<(LogSource, LogLevel), u64>,
+}
+
+impl Log {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            entries: VecDeque::new(),
+            capacity,
+            counts: HashMap::new(),
+        }
+    }
+
+    pub fn push(&mut self, entry: LogEntry) {
+        if self.entries.len() >= self.capacity {
+            self.entries.pop_front();
+        }
+        *self.counts.entry((entry.source, entry.level)).or_default() += 1;
+        self.entries.push_back(entry);
+    }
+
+    pub fn count(&self, source: LogSource, level: LogLevel) -> u64 {
+        self.counts.get(&(source, level)).copied().unwrap_or(0)
+    }
+
+    pub fn set_capacity(&mut self, capacity: usize) {
+        self.capacity = capacity.max(1);
+        let excess = self.entries.len().saturating_sub(self.capacity);
+        self.entries.drain(..excess);
+    }
+
+    pub fn entries(&self) -> impl Iterator<Item = &LogEntry> {
+        self.entries.iter()
+    }
+
+    pub fn clear(&mut self) {
+        self.entries.clear();
+    }
+}
+
+#[derive(Default)]
+pub struct LogFilter {
+    pub min_level: LogLevel,
+    /// All sources if `None`.
+    pub source: Option<LogSource>,
+    pub search: Search,
+}
+
+impl LogFilter {
+    pub fn matches(&self, entry: &LogEntry, matcher: &SearchMatcher) -> bool {
+        entry.level >= self.min_level
+            && self.source.is_none_or(|source| entry.source == source)
+            && matcher.matches_text(&entry.message)
+    }
+}
+
+/// Categories of the libtorrent alerts logged.
+#[derive(PartialEq, Eq, Clone, Copy, Debug, Serialize, Deserialize)]
+pub enum AlertCategory {
+    Error,
+    Peer,
+    PortMapping,
+    Storage,
+    Tracker,
+    Connect,
+    Status,
+    IpBlock,
+    Performance,
+    Dht,
+}
+
+impl AlertCategory {
+    pub const ALL: [AlertCategory; 10] = [
+        AlertCategory::Error,
+        AlertCategory::Peer,
+        AlertCategory::PortMapping,
+        AlertCategory::Storage,
+        AlertCategory::Tracker,
+        AlertCategory::Connect,
+        AlertCategory::Status,
+        AlertCategory::IpBlock,
+        AlertCategory::Performance,
+        AlertCategory::Dht,
+    ];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            AlertCategory::Error => "Errors",
+            AlertCategory::Peer => "Peers",
+            AlertCategory::PortMapping => "Port mapping",
+            AlertCategory::Storage => "Storage",
+            AlertCategory::Tracker => "Trackers",
+            AlertCategory::Connect => "Connections",
+            AlertCategory::Status => "Status",
+            AlertCategory::IpBlock => "IP blocking",
+            AlertCategory::Performance => "Performance warnings",
+            AlertCategory::Dht => "DHT",
+        }
+    }
+
+    /// The category's bit in the mask given to the bridge.
+    pub fn bit(self) -> i32 {
+        1 << AlertCategory::ALL
+            .iter()
+            .position(|c| *c == self)
+            .expect("Category is listed")
+    }
+
+    pub fn mask(categories: &[AlertCategory]) -> i32 {
+        categories.iter().fold(0, |mask, c| mask | c.bit())
+    }
+}
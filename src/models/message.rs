@@ -1,4 +1,15 @@
-use super::torrent::{TorrentFilePriority, TorrentState};
+use std::{net::SocketAddr, sync::mpsc::Sender};
+
+use serde::{Deserialize, Serialize};
+
+use super::{
+    category::Category,
+    control::{ControlRequest, ControlResponse},
+    feed::{Feed, FeedItem},
+    ip_filter::IpRange,
+    settings::{ProxySettings, RssRule, Settings, WatchedFolder},
+    torrent::{TorrentFilePriority, TorrentState},
+};
 
 #[derive(PartialEq)]
 pub enum AddTorrentKind {
@@ -6,18 +17,75 @@ pub enum AddTorrentKind {
     MagnetUrl,
 }
 
-#[derive(PartialEq)]
+#[derive(PartialEq, Clone, Copy, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum QueueMove {
+    Top,
+    Up,
+    Down,
+    Bottom,
+}
+
+/// An action applied to several selected torrents at once.
+#[derive(PartialEq, Clone, Debug)]
+pub enum BatchAction {
+    Pause,
+    Resume,
+    Remove {
+        delete_files: bool,
+    },
+    Recheck,
+    Reannounce,
+    MoveStorage(String),
+    SetCategory(String),
+    AddTag(String),
+    RemoveTag(String),
+    /// Download and upload limits in bytes per second, 0 for unlimited.
+    SetLimits(i32, i32),
+    Queue(QueueMove),
+}
+
 pub enum Message {
     Stop,
     Refresh,
     ForcedRefresh,
-    AddTorrent(String, AddTorrentKind),
+    AddTorrent(String, AddTorrentKind, String),
+    AddWatchedTorrent(String, WatchedFolder),
     RemoveTorrent(usize),
     UpdateState(TorrentState, usize),
     UpdateSelTorrent(Option<usize>),
     ToggleStreamMode(usize),
     UpdateFilePriority(usize, usize, TorrentFilePriority),
     FetchPeers(usize),
+    /// Torrent index, IP address and port of the peer.
+    DisconnectPeer(usize, String, u16),
+    /// Adds the IP address to the ban list.
+    BanPeer(String),
+    /// Removes the IP address from the ban list.
+    UnbanPeer(String),
+    AddPeers(usize, Vec<SocketAddr>),
     FetchFiles(usize),
+    FetchSessionStats,
     OpenDir(String),
+    UpdateSettings(Box<Settings>),
+    RefreshFeeds,
+    FeedsFetched(Vec<Feed>),
+    DownloadFeedItem(FeedItem),
+    /// The .torrent file of the item, downloaded in the background, added
+    /// as the rule says.
+    FeedItemDownloaded(FeedItem, Box<RssRule>, Result<Vec<u8>, String>),
+    RefreshIpFilter,
+    /// The merged ranges of the IP filter lists and the errors of those
+    /// failing to load.
+    IpFilterLoaded(Vec<IpRange>, Vec<String>),
+    /// Connects through the proxy, which may not be saved yet.
+    CheckProxy(ProxySettings),
+    ProxyChecked(Result<(), String>),
+    SaveCategory(Option<String>, Category),
+    RemoveCategory(String),
+    SetCategory(usize, String),
+    SetTags(usize, Vec<String>),
+    Batch(Vec<usize>, BatchAction),
+    /// A request of the control socket and where to send the response.
+    Control(ControlRequest, Sender<ControlResponse>),
 }
@@ -0,0 +1,48 @@
+use std::{collections::HashMap, fs, path::Path};
+
+use serde::{Deserialize, Serialize};
+
+/// Torrenter's own data about a torrent, kept next to its `.resume` file.
+#[derive(PartialEq, Clone, Debug, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct TorrentMetadata {
+    pub category: String,
+    pub tags: Vec<String>,
+}
+
+impl TorrentMetadata {
+    pub fn load_all(resume_dir: &Path) -> HashMap<String, TorrentMetadata> {
+        let mut metadata = HashMap::new();
+        let entries = match fs::read_dir(resume_dir) {
+            Ok(entries) => entries,
+            Err(_) => return metadata,
+        };
+        for entry in entries.flatten() {
+            let path = entry.path();
+            if path.extension().and_then(|e| e.to_str()) != Some("json") {
+                continue;
+            }
+            let hash = match path.file_stem().and_then(|s| s.to_str()) {
+                Some(hash) => hash.to_string(),
+                None => continue,
+            };
+            let contents = fs::read_to_string(&path).unwrap_or_default();
+            match serde_json::from_str(&contents) {
+                Ok(m) => {
+                    metadata.insert(hash, m);
+                }
+                Err(e) => log::error!("Failed to parse {}: {}", path.display(), e),
+            }
+        }
+        metadata
+    }
+
+    pub fn save(&self, resume_dir: &Path, hash: &str) -> bool {
+        let contents = serde_json::to_string_pretty(self).expect("Failed to serialize metadata");
+        fs::write(resume_dir.join(format!("{}.json", hash)), contents).is_ok()
+    }
+
+    pub fn remove(resume_dir: &Path, hash: &str) {
+        let _ = fs::remove_file(resume_dir.join(format!("{}.json", hash)));
+    }
+}
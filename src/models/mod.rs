@@ -1,6 +1,22 @@
+pub mod category;
+pub mod column;
+pub mod control;
+pub mod event;
+pub mod feed;
 pub mod file;
+pub mod filter;
 pub mod fs_tree;
+pub mod history;
+pub mod ip_filter;
+pub mod log;
 pub mod message;
+pub mod metadata;
+pub mod network;
 pub mod peer;
+pub mod selection;
+pub mod settings;
+pub mod sort;
+pub mod stats;
 pub mod tab;
 pub mod torrent;
+pub mod tracker;
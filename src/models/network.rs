@@ -0,0 +1,66 @@
+use std::net::IpAddr;
+
+/// libtorrent's default, used whichever interface the session binds to.
+pub const LISTEN_PORT: u16 = 6881;
+
+/// An address of a local network interface.
+#[derive(PartialEq, Clone, Debug)]
+pub struct NetInterface {
+    pub name: String,
+    pub address: IpAddr,
+    pub is_up: bool,
+}
+
+/// The interface the session is bound to, as of the watchdog's last check.
+#[derive(Default)]
+pub struct NetworkStatus {
+    /// Addresses of all local interfaces.
+    pub interfaces: Vec<NetInterface>,
+    /// Name or address, empty if the session uses any interface.
+    pub interface: String,
+    /// Usable addresses of the interface, none while it's down or gone.
+    pub addresses: Vec<IpAddr>,
+    /// Whether the watchdog paused the session.
+    pub is_paused: bool,
+}
+
+impl NetworkStatus {
+    /// Whether the session is bound to an interface which went down.
+    pub fn is_down(&self) -> bool {
+        !self.interface.is_empty() && self.addresses.is_empty()
+    }
+}
+
+fn is_link_local(address: &IpAddr) -> bool {
+    match address {
+        IpAddr::V4(ip) => ip.is_link_local(),
+        IpAddr::V6(ip) => ip.segments()[0] & 0xffc0 == 0xfe80,
+    }
+}
+
+/// The addresses of the interface, named so or having that address, which
+/// can reach beyond the link. None if it's down or gone.
+pub fn addresses(interfaces: &[NetInterface], interface: &str) -> Vec<IpAddr> {
+    let interface = interface.trim();
+    let address = interface.parse::<IpAddr>().ok();
+    interfaces
+        .iter()
+        .filter(|i| i.is_up && !is_link_local(&i.address))
+        .filter(|i| match address {
+            Some(address) => i.address == address,
+            None => i.name == interface,
+        })
+        .map(|i| i.address)
+        .collect()
+}
+
+/// libtorrent's `listen_interfaces` for the interface, all if empty.
+pub fn listen_interfaces(interface: &str) -> String {
+    match interface.trim() {
+        "" => format!("0.0.0.0:{0},[::]:{0}", LISTEN_PORT),
+        interface => match interface.parse::<IpAddr>() {
+            Ok(IpAddr::V6(ip)) => format!("[{}]:{}", ip, LISTEN_PORT),
+            _ => format!("{}:{}", interface, LISTEN_PORT),
+        },
+    }
+}
@@ -1,10 +1,164 @@
-#[derive(Clone)]
+use std::net::SocketAddr;
+
+use serde::Serialize;
+
+#[derive(PartialEq, Clone, Copy, Debug, Default, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum ConnectionType {
+    #[default]
+    Tcp,
+    Utp,
+    WebSeed,
+}
+
+impl ConnectionType {
+    pub fn label(&self) -> &'static str {
+        match self {
+            ConnectionType::Tcp => "TCP",
+            ConnectionType::Utp => "uTP",
+            ConnectionType::WebSeed => "Web seed",
+        }
+    }
+}
+
+#[derive(PartialEq, Clone, Copy, Debug, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum PeerSource {
+    Tracker,
+    Dht,
+    Pex,
+    Lsd,
+    ResumeData,
+    Incoming,
+}
+
+impl PeerSource {
+    /// By bit of the sources reported by the bridge.
+    pub const ALL: [PeerSource; 6] = [
+        PeerSource::Tracker,
+        PeerSource::Dht,
+        PeerSource::Pex,
+        PeerSource::Lsd,
+        PeerSource::ResumeData,
+        PeerSource::Incoming,
+    ];
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            PeerSource::Tracker => "Tracker",
+            PeerSource::Dht => "DHT",
+            PeerSource::Pex => "PeX",
+            PeerSource::Lsd => "LSD",
+            PeerSource::ResumeData => "Resume data",
+            PeerSource::Incoming => "Incoming",
+        }
+    }
+
+    pub fn from_bits(bits: i32) -> Vec<PeerSource> {
+        PeerSource::ALL
+            .iter()
+            .enumerate()
+            .filter(|(bit, _)| bits & (1 << bit) != 0)
+            .map(|(_, source)| *source)
+            .collect()
+    }
+}
+
+#[derive(PartialEq, Clone, Copy, Debug, Default, Serialize)]
+pub struct PeerFlags {
+    /// We want pieces of the peer.
+    pub is_interested: bool,
+    /// We don't upload to the peer.
+    pub is_choked: bool,
+    pub is_remote_interested: bool,
+    pub is_remote_choked: bool,
+    /// The peer stopped sending what we requested.
+    pub is_snubbed: bool,
+    pub is_optimistic_unchoke: bool,
+}
+
+impl PeerFlags {
+    pub fn from_bits(bits: i32) -> Self {
+        Self {
+            is_interested: bits & 1 != 0,
+            is_choked: bits & 2 != 0,
+            is_remote_interested: bits & 4 != 0,
+            is_remote_choked: bits & 8 != 0,
+            is_snubbed: bits & 16 != 0,
+            is_optimistic_unchoke: bits & 32 != 0,
+        }
+    }
+
+    /// The flags as qBittorrent abbreviates them, with their meanings.
+    pub fn abbreviations(&self) -> Vec<(char, &'static str)> {
+        let mut flags = vec![];
+        if self.is_interested {
+            flags.push(match self.is_remote_choked {
+                false => ('D', "Downloading"),
+                true => ('d', "Interested, choked by the peer"),
+            });
+        }
+        if self.is_remote_interested {
+            flags.push(match self.is_choked {
+                false => ('U', "Uploading"),
+                true => ('u', "Peer interested, choked"),
+            });
+        }
+        if self.is_optimistic_unchoke {
+            flags.push(('O', "Optimistic unchoke"));
+        }
+        if self.is_snubbed {
+            flags.push(('S', "Snubbed"));
+        }
+        flags
+    }
+}
+
+#[derive(Clone, Default, Serialize)]
 pub struct Peer {
     // TODO: Implement region
     // pub region: String,
     pub client: String,
     pub ip_address: String,
+    pub port: u16,
     pub progress: f32,
     pub download_rate: i64,
     pub upload_rate: i64,
+    pub connection: ConnectionType,
+    pub is_encrypted: bool,
+    pub sources: Vec<PeerSource>,
+    pub flags: PeerFlags,
+    /// Payload downloaded from the peer in bytes.
+    pub total_download: i64,
+    /// Payload uploaded to the peer in bytes.
+    pub total_upload: i64,
+    /// Share of the pieces missing here which the peer has.
+    pub relevance: f32,
+    /// Round trip time in milliseconds.
+    pub rtt: i32,
+}
+
+impl Peer {
+    /// `ip:port`, with IPv6 addresses in brackets.
+    pub fn address(&self) -> String {
+        if self.ip_address.contains(':') {
+            format!("[{}]:{}", self.ip_address, self.port)
+        } else {
+            format!("{}:{}", self.ip_address, self.port)
+        }
+    }
+}
+
+/// The `ip:port` lines of the text, IPv6 addresses in brackets, and the
+/// invalid lines. Blank lines are skipped.
+pub fn parse_endpoints(text: &str) -> (Vec<SocketAddr>, Vec<String>) {
+    let mut endpoints = vec![];
+    let mut invalid = vec![];
+    for line in text.lines().map(str::trim).filter(|l| !l.is_empty()) {
+        match line.parse::<SocketAddr>() {
+            Ok(endpoint) if endpoint.port() != 0 => endpoints.push(endpoint),
+            _ => invalid.push(line.to_owned()),
+        }
+    }
+    (endpoints, invalid)
 }
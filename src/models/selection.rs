@@ -0,0 +1,60 @@
+use std::collections::BTreeSet;
+
+/// Torrents selected in the list for batch actions.
+#[derive(Default)]
+pub struct Selection {
+    pub indices: BTreeSet<usize>,
+    anchor: Option<usize>,
+}
+
+impl Selection {
+    /// Handles a click on the torrent at `index`. `visible` holds the listed
+    /// torrents in their displayed order. `toggle` (ctrl) adds or removes the
+    /// torrent and `extend` (shift) selects the range from the last click.
+    pub fn click(&mut self, index: usize, visible: &[usize], toggle: bool, extend: bool) {
+        let position = |index: usize| visible.iter().position(|i| *i == index);
+        if extend {
+            if let (Some(from), Some(to)) = (self.anchor.and_then(position), position(index)) {
+                let (from, to) = if from <= to { (from, to) } else { (to, from) };
+                if !toggle {
+                    self.indices.clear();
+                }
+                self.indices.extend(&visible[from..=to]);
+                return;
+            }
+        }
+
+        if toggle {
+            if !self.indices.remove(&index) {
+                self.indices.insert(index);
+            }
+        } else if self.indices.len() == 1 && self.indices.contains(&index) {
+            self.indices.clear();
+        } else {
+            self.indices.clear();
+            self.indices.insert(index);
+        }
+        self.anchor = Some(index);
+    }
+
+    pub fn select_all(&mut self, visible: &[usize]) {
+        self.indices = visible.iter().copied().collect();
+    }
+
+    pub fn clear(&mut self) {
+        self.indices.clear();
+        self.anchor = None;
+    }
+
+    /// Drops the torrents which no longer exist.
+    pub fn truncate(&mut self, num_torrents: usize) {
+        self.indices.retain(|i| *i < num_torrents);
+        if self.anchor.is_some_and(|i| i >= num_torrents) {
+            self.anchor = None;
+        }
+    }
+
+    pub fn to_vec(&self) -> Vec<usize> {
+        self.indices.iter().copied().collect()
+    }
+}
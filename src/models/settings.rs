@@ -0,0 +1,313 @@
+use std::{fs, path::Path};
+
+use serde::{Deserialize, Serialize};
+
+use super::log::AlertCategory;
+
+const SETTINGS_FILE: &str = "settings.json";
+
+#[derive(PartialEq, Clone, Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub struct HookSettings {
+    pub on_added: String,
+    pub on_finished: String,
+    pub use_shell: bool,
+    pub timeout_secs: u64,
+}
+
+impl Default for HookSettings {
+    fn default() -> Self {
+        Self {
+            on_added: "".to_owned(),
+            on_finished: "".to_owned(),
+            use_shell: false,
+            timeout_secs: 60,
+        }
+    }
+}
+
+#[derive(PartialEq, Clone, Debug, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct WatchedFolder {
+    pub path: String,
+    /// Empty for the downloads directory.
+    pub save_path: String,
+    pub category: String,
+    pub start_paused: bool,
+    /// Delete consumed files instead of moving them into `processed/`.
+    pub delete_processed: bool,
+    /// Poll the folder instead of relying on file system notifications, which
+    /// network shares don't deliver.
+    pub use_polling: bool,
+}
+
+#[derive(PartialEq, Clone, Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub struct RssRule {
+    pub name: String,
+    pub enabled: bool,
+    /// Regular expressions matched against item titles, ignoring case.
+    pub include: String,
+    pub exclude: String,
+    /// Episodes or ranges separated by `;`, e.g. `S01E01-S01E10;S02E05-`.
+    pub episode_filter: String,
+    /// Size bounds in MB, 0 for no bound.
+    pub min_size_mb: u64,
+    pub max_size_mb: u64,
+    /// Feed URLs the rule applies to, all feeds if empty.
+    pub feeds: Vec<String>,
+    pub category: String,
+    pub save_path: String,
+    pub start_paused: bool,
+}
+
+impl Default for RssRule {
+    fn default() -> Self {
+        Self {
+            name: "".to_owned(),
+            enabled: true,
+            include: "".to_owned(),
+            exclude: "".to_owned(),
+            episode_filter: "".to_owned(),
+            min_size_mb: 0,
+            max_size_mb: 0,
+            feeds: vec![],
+            category: "".to_owned(),
+            save_path: "".to_owned(),
+            start_paused: false,
+        }
+    }
+}
+
+#[derive(PartialEq, Clone, Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub struct RssSettings {
+    pub feeds: Vec<String>,
+    pub refresh_interval_mins: u64,
+    pub rules: Vec<RssRule>,
+}
+
+impl Default for RssSettings {
+    fn default() -> Self {
+        Self {
+            feeds: vec![],
+            refresh_interval_mins: 30,
+            rules: vec![],
+        }
+    }
+}
+
+#[derive(PartialEq, Clone, Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub struct IpFilterSettings {
+    pub enabled: bool,
+    /// URLs or paths of eMule `ipfilter.dat`, PeerGuardian P2P or CIDR lists,
+    /// gzipped or not.
+    pub sources: Vec<String>,
+    pub refresh_interval_hours: u64,
+    /// Addresses banned from the Peers tab, blocked whether the lists are
+    /// enabled or not.
+    pub banned_ips: Vec<String>,
+}
+
+impl Default for IpFilterSettings {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            sources: vec![],
+            refresh_interval_hours: 24,
+            banned_ips: vec![],
+        }
+    }
+}
+
+#[derive(PartialEq, Clone, Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub struct LogSettings {
+    /// Categories of the libtorrent alerts written to the log.
+    pub alert_categories: Vec<AlertCategory>,
+    /// Entries kept in memory for the log panel.
+    pub max_entries: usize,
+}
+
+impl Default for LogSettings {
+    fn default() -> Self {
+        Self {
+            alert_categories: vec![
+                AlertCategory::Error,
+                AlertCategory::PortMapping,
+                AlertCategory::Performance,
+            ],
+            max_entries: 5000,
+        }
+    }
+}
+
+/// Global speed limits in bytes per second, 0 for unlimited.
+#[derive(PartialEq, Clone, Debug, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SpeedLimits {
+    pub download: i32,
+    pub upload: i32,
+}
+
+#[derive(PartialEq, Clone, Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub struct WebSettings {
+    pub enabled: bool,
+    pub bind_address: String,
+    pub port: u16,
+    /// No authentication if empty.
+    pub username: String,
+    pub password: String,
+    pub use_tls: bool,
+    /// PEM files of the certificate chain and its private key.
+    pub cert_path: String,
+    pub key_path: String,
+    /// Serve the Transmission RPC protocol at `/transmission/rpc`.
+    pub transmission_rpc: bool,
+    /// Serve the qBittorrent Web API at `/api/v2/`.
+    pub qbittorrent_api: bool,
+    /// Serve Prometheus metrics at `/metrics`.
+    pub metrics: bool,
+}
+
+impl Default for WebSettings {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            bind_address: "127.0.0.1".to_owned(),
+            port: 8080,
+            username: "admin".to_owned(),
+            password: "".to_owned(),
+            use_tls: false,
+            cert_path: "".to_owned(),
+            key_path: "".to_owned(),
+            transmission_rpc: false,
+            qbittorrent_api: false,
+            metrics: false,
+        }
+    }
+}
+
+#[derive(PartialEq, Clone, Debug, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct NetworkSettings {
+    /// Name or address of the only interface used for peers and trackers,
+    /// any if empty. The session is paused while it's down.
+    pub interface: String,
+}
+
+#[derive(PartialEq, Clone, Copy, Debug, Default, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum ProxyKind {
+    #[default]
+    None,
+    Socks4,
+    Socks5,
+    Http,
+}
+
+impl ProxyKind {
+    pub const ALL: [ProxyKind; 4] = [
+        ProxyKind::None,
+        ProxyKind::Socks4,
+        ProxyKind::Socks5,
+        ProxyKind::Http,
+    ];
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            ProxyKind::None => "None",
+            ProxyKind::Socks4 => "SOCKS4",
+            ProxyKind::Socks5 => "SOCKS5",
+            ProxyKind::Http => "HTTP",
+        }
+    }
+}
+
+#[derive(PartialEq, Clone, Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ProxySettings {
+    pub kind: ProxyKind,
+    pub host: String,
+    pub port: u16,
+    /// No authentication if empty. SOCKS4 sends it as the user ID.
+    pub username: String,
+    pub password: String,
+    /// Let the proxy resolve the host names instead of resolving them here.
+    pub proxy_hostnames: bool,
+    pub proxy_peer_connections: bool,
+    pub proxy_tracker_connections: bool,
+}
+
+impl Default for ProxySettings {
+    fn default() -> Self {
+        Self {
+            kind: ProxyKind::None,
+            host: "".to_owned(),
+            port: 1080,
+            username: "".to_owned(),
+            password: "".to_owned(),
+            proxy_hostnames: true,
+            proxy_peer_connections: true,
+            proxy_tracker_connections: true,
+        }
+    }
+}
+
+impl ProxySettings {
+    /// Downloaded by Torrenter itself rather than libtorrent, so never
+    /// proxied.
+    pub const DIRECT: &'static str = "RSS feeds, .torrent file URLs and IP filter lists";
+
+    pub fn is_enabled(&self) -> bool {
+        self.kind != ProxyKind::None && !self.host.trim().is_empty()
+    }
+
+    /// What goes through the proxy, nothing if it's disabled.
+    pub fn proxied(&self) -> Vec<&'static str> {
+        if !self.is_enabled() {
+            return vec![];
+        }
+        [
+            (self.proxy_peer_connections, "peer connections"),
+            (self.proxy_tracker_connections, "tracker connections"),
+            (self.proxy_hostnames, "host name lookups"),
+        ]
+        .into_iter()
+        .filter_map(|(is_proxied, label)| is_proxied.then_some(label))
+        .collect()
+    }
+}
+
+#[derive(PartialEq, Clone, Debug, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Settings {
+    pub speed_limits: SpeedLimits,
+    pub hooks: HookSettings,
+    pub watched_folders: Vec<WatchedFolder>,
+    pub rss: RssSettings,
+    pub log: LogSettings,
+    pub web: WebSettings,
+    pub ip_filter: IpFilterSettings,
+    pub proxy: ProxySettings,
+    pub network: NetworkSettings,
+}
+
+impl Settings {
+    pub fn load(data_dir: &Path) -> Self {
+        match fs::read_to_string(data_dir.join(SETTINGS_FILE)) {
+            Ok(contents) => serde_json::from_str(&contents).unwrap_or_else(|e| {
+                log::error!("Failed to parse settings, using defaults: {}", e);
+                Settings::default()
+            }),
+            Err(_) => Settings::default(),
+        }
+    }
+
+    pub fn save(&self, data_dir: &Path) -> bool {
+        let contents = serde_json::to_string_pretty(self).expect("Failed to serialize settings");
+        fs::write(data_dir.join(SETTINGS_FILE), contents).is_ok()
+    }
+}
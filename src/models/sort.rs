@@ -0,0 +1,82 @@
+use std::cmp::Ordering;
+
+use serde::{Deserialize, Serialize};
+
+use super::torrent::Torrent;
+
+#[derive(PartialEq, Clone, Copy, Debug, Default, Serialize, Deserialize)]
+pub enum SortKey {
+    #[default]
+    Added,
+    Name,
+    Size,
+    Progress,
+    DownloadSpeed,
+    UploadSpeed,
+    Eta,
+    Ratio,
+}
+
+impl SortKey {
+    pub const ALL: [SortKey; 8] = [
+        SortKey::Added,
+        SortKey::Name,
+        SortKey::Size,
+        SortKey::Progress,
+        SortKey::DownloadSpeed,
+        SortKey::UploadSpeed,
+        SortKey::Eta,
+        SortKey::Ratio,
+    ];
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            SortKey::Added => "Added",
+            SortKey::Name => "Name",
+            SortKey::Size => "Size",
+            SortKey::Progress => "Progress",
+            SortKey::DownloadSpeed => "Download speed",
+            SortKey::UploadSpeed => "Upload speed",
+            SortKey::Eta => "ETA",
+            SortKey::Ratio => "Ratio",
+        }
+    }
+
+    pub fn compare(&self, a: &Torrent, b: &Torrent) -> Ordering {
+        match self {
+            SortKey::Added => a.added_time.cmp(&b.added_time),
+            SortKey::Name => a.name.to_lowercase().cmp(&b.name.to_lowercase()),
+            SortKey::Size => a.total_size.cmp(&b.total_size),
+            SortKey::Progress => a.progress.total_cmp(&b.progress),
+            SortKey::DownloadSpeed => a.download_rate.cmp(&b.download_rate),
+            SortKey::UploadSpeed => a.upload_rate.cmp(&b.upload_rate),
+            // An unknown ETA is negative, yet the furthest away
+            SortKey::Eta => {
+                let eta = |t: &Torrent| if t.eta < 0 { i64::MAX } else { t.eta };
+                eta(a).cmp(&eta(b))
+            }
+            SortKey::Ratio => a.ratio().total_cmp(&b.ratio()),
+        }
+    }
+}
+
+#[derive(PartialEq, Clone, Copy, Debug, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SortOrder {
+    pub key: SortKey,
+    pub descending: bool,
+}
+
+impl SortOrder {
+    /// Sorts the indices of `torrents`, keeping the insertion order for ties.
+    pub fn sort(&self, indices: &mut [usize], torrents: &[Torrent]) {
+        indices.sort_by(|a, b| {
+            let ordering = self.key.compare(&torrents[*a], &torrents[*b]);
+            if self.descending {
+                ordering.reverse()
+            } else {
+                ordering
+            }
+        });
+    }
+}
@@ -0,0 +1,48 @@
+use std::{collections::HashMap, time::Instant};
+
+/// Snapshots of libtorrent's named session counters, the last two of which
+/// give the rates.
+#[derive(Default)]
+pub struct SessionStats {
+    counters: HashMap<String, i64>,
+    time: Option<Instant>,
+    previous: HashMap<String, i64>,
+    previous_time: Option<Instant>,
+}
+
+impl SessionStats {
+    pub fn update(&mut self, time: Instant, counters: HashMap<String, i64>) {
+        if counters.is_empty() {
+            return;
+        }
+        self.previous = std::mem::replace(&mut self.counters, counters);
+        self.previous_time = self.time.replace(time);
+    }
+
+    pub fn get(&self, name: &str) -> Option<i64> {
+        self.counters.get(name).copied()
+    }
+
+    /// Change of the counter per second between the last two snapshots.
+    pub fn rate(&self, name: &str) -> Option<f64> {
+        let secs = self.time?.duration_since(self.previous_time?).as_secs_f64();
+        if secs <= 0.0 {
+            return None;
+        }
+        let delta = self.get(name)? - self.previous.get(name)?;
+        Some(delta as f64 / secs)
+    }
+
+    /// Sum of the counters, `None` if any of them is missing.
+    pub fn sum(&self, names: &[&str]) -> Option<i64> {
+        names.iter().map(|name| self.get(name)).sum()
+    }
+
+    pub fn sum_rate(&self, names: &[&str]) -> Option<f64> {
+        names.iter().map(|name| self.rate(name)).sum()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.counters.is_empty()
+    }
+}
@@ -3,9 +3,11 @@ pub enum Tab {
     General,
     Files,
     Peers,
+    Trackers,
+    Speed,
 }
 
 pub struct TabView {
-    pub tabs: [(Tab, String, bool); 3],
+    pub tabs: [(Tab, String, bool); 5],
     pub selected: Tab,
 }
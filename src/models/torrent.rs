@@ -1,8 +1,11 @@
 use std::fmt;
 
-use super::{file::File, peer::Peer};
+use serde::{Deserialize, Serialize};
 
-#[derive(PartialEq, Clone, Debug)]
+use super::{file::File, peer::Peer, tracker::Tracker};
+
+#[derive(PartialEq, Clone, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
 pub enum TorrentFilePriority {
     Skip,
     Default,
@@ -74,6 +77,17 @@ pub struct Torrent {
     pub total_ses_download: i64,
     pub total_ses_upload: i64,
     pub eta: i64,
+    pub num_files: i32,
+    /// Unix time of when the torrent was added.
+    pub added_time: i64,
+    /// Bytes per second, negative when unlimited.
+    pub download_limit: i32,
+    pub upload_limit: i32,
+    pub category: String,
+    pub tags: Vec<String>,
+    /// Empty unless the torrent stopped on an error.
+    pub error: String,
+    pub trackers: Vec<Tracker>,
 }
 
 impl Torrent {
@@ -104,6 +118,36 @@ impl Torrent {
             total_ses_download: 0,
             total_ses_upload: 0,
             eta: 0,
+            num_files: 0,
+            added_time: 0,
+            download_limit: -1,
+            upload_limit: -1,
+            category: "".to_string(),
+            tags: vec![],
+            error: "".to_string(),
+            trackers: vec![],
+        }
+    }
+
+    /// Over the size at least, as qBittorrent does, so that torrents added
+    /// complete get a ratio too.
+    pub fn ratio(&self) -> f32 {
+        let downloaded = self.total_download.max(self.total_size);
+        if downloaded > 0 {
+            self.total_upload as f32 / downloaded as f32
+        } else {
+            0.0
+        }
+    }
+
+    /// Whether the torrent is active but not transferring any data.
+    pub fn is_stalled(&self) -> bool {
+        match self.state {
+            TorrentState::Downloading | TorrentState::DownloadingMetaData => {
+                self.download_rate == 0
+            }
+            TorrentState::Seeding | TorrentState::Finished => self.upload_rate == 0,
+            _ => false,
         }
     }
 }
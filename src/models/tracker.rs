@@ -0,0 +1,46 @@
+use serde::Serialize;
+
+#[derive(PartialEq, Clone, Debug, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum TrackerStatus {
+    NotContacted,
+    Working,
+    Updating,
+    Error,
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct Tracker {
+    pub tier: i32,
+    pub url: String,
+    pub status: TrackerStatus,
+    pub num_peers: i32,
+    pub num_seeds: i32,
+    pub message: String,
+}
+
+impl Tracker {
+    /// The host name of the tracker, e.g. `tracker.example.com` for
+    /// `udp://tracker.example.com:1337/announce`.
+    pub fn host(&self) -> Option<String> {
+        let rest = match self.url.split_once("://") {
+            Some((_, rest)) => rest,
+            None => &self.url,
+        };
+        let authority = rest.split(['/', '?', '#']).next().unwrap_or("");
+        let authority = match authority.rsplit_once('@') {
+            Some((_, host)) => host,
+            None => authority,
+        };
+        let host = if let Some(ipv6) = authority.strip_prefix('[') {
+            ipv6.split(']').next().unwrap_or("")
+        } else {
+            authority.split(':').next().unwrap_or("")
+        };
+        if host.is_empty() {
+            None
+        } else {
+            Some(host.to_lowercase())
+        }
+    }
+}
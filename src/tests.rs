@@ -1,8 +1,143 @@
 #[cfg(test)]
 mod tests {
-    use crate::models::fs_tree::FSTree;
+    #[cfg(unix)]
+    use crate::{cli, controllers::control};
+    use crate::{
+        controllers::{
+            hook::{self, HookContext},
+            ip_filter,
+            log::RotatingFile,
+            metrics, proxy, qbittorrent, rss, watcher,
+            web::{self, WebContext, WebServer},
+        },
+        instance,
+        models::{
+            category::{Category, CategoryFilter},
+            column::{PeerColumn, PeerTableLayout, TableLayout, TorrentColumn},
+            control::{ControlRequest, ControlResponse},
+            filter::{Search, SearchCache, StateFilter, TagFilter, TorrentFilter, TrackerFilter},
+            fs_tree::FSTree,
+            history::{RateSample, SpeedHistories, SpeedHistory},
+            ip_filter::{merge, parse_line, IpRange},
+            log::{AlertCategory, Log, LogEntry, LogFilter, LogLevel, LogSource},
+            message::{AddTorrentKind, Message},
+            metadata::TorrentMetadata,
+            network::{self, NetInterface, NetworkStatus},
+            peer::{self, ConnectionType, Peer, PeerFlags, PeerSource},
+            selection::Selection,
+            settings::{
+                HookSettings, ProxyKind, ProxySettings, RssRule, WatchedFolder, WebSettings,
+            },
+            sort::{SortKey, SortOrder},
+            stats::SessionStats,
+            torrent::{Torrent, TorrentState},
+            tracker::{Tracker, TrackerStatus},
+        },
+    };
 
-    use std::{collections::HashSet, path::Path};
+    use std::{
+        collections::{HashMap, HashSet},
+        env, fs,
+        io::{BufRead, BufReader, Read, Write},
+        net::TcpListener,
+        path::{Path, PathBuf},
+        sync::{mpsc, Arc, Mutex},
+        thread,
+        time::{Duration, Instant},
+    };
+
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir = env::temp_dir().join(format!("torrenter-{}-{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    /// Serves the `(path, body)` pairs over HTTP on a local port, returning the
+    /// base URL.
+    fn serve(files: Vec<(&'static str, &'static str)>) -> String {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        thread::spawn(move || {
+            for mut stream in listener.incoming().flatten() {
+                let mut reader = BufReader::new(stream.try_clone().unwrap());
+                let mut request_line = String::new();
+                reader.read_line(&mut request_line).unwrap();
+                loop {
+                    let mut header = String::new();
+                    reader.read_line(&mut header).unwrap();
+                    if header.trim().is_empty() {
+                        break;
+                    }
+                }
+
+                let path = request_line.split_whitespace().nth(1).unwrap_or("");
+                let response = match files.iter().find(|(p, _)| *p == path) {
+                    Some((_, body)) => format!(
+                        "HTTP/1.1 200 OK\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+                        body.len(),
+                        body
+                    ),
+                    None => "HTTP/1.1 404 Not Found\r\nContent-Length: 0\r\n\
+                        Connection: close\r\n\r\n"
+                        .to_string(),
+                };
+                stream.write_all(response.as_bytes()).unwrap();
+            }
+        });
+        format!("http://{}", addr)
+    }
+
+    /// Serves the web UI on a local port for the torrents, returning the base
+    /// URL and the requests reaching the message controller, whose response
+    /// data `respond` gives.
+    fn web_server<F>(
+        settings: WebSettings,
+        dir: &Path,
+        torrents: Vec<Torrent>,
+        respond: F,
+    ) -> (WebServer, String, mpsc::Receiver<ControlRequest>)
+    where
+        F: Fn(&ControlRequest) -> serde_json::Value + Send + 'static,
+    {
+        let settings = WebSettings {
+            enabled: true,
+            port: 0,
+            ..settings
+        };
+        let (tx, rx) = mpsc::channel();
+        let torrents = Arc::new(Mutex::new(torrents));
+        let ctx = WebContext::new(
+            settings,
+            dir.to_path_buf(),
+            tx,
+            torrents,
+            Arc::default(),
+            Arc::new(Mutex::new(Log::new(10))),
+        );
+        let server = web::serve(ctx).unwrap();
+        let base = format!("http://{}", server.addr().unwrap());
+        let (requests_tx, requests_rx) = mpsc::channel();
+        thread::spawn(move || {
+            while let Ok(Message::Control(request, reply_tx)) = rx.recv() {
+                let _ = reply_tx.send(ControlResponse::success(respond(&request)));
+                let _ = requests_tx.send(request);
+            }
+        });
+        (server, base, requests_rx)
+    }
+
+    fn hook_context() -> HookContext {
+        HookContext {
+            name: "Big Buck Bunny".to_string(),
+            hash: "dd8255ecdc7ca55fb0bbf81323d87062db1f6d1c".to_string(),
+            save_path: "/downloads".to_string(),
+            content_path: "/downloads/Big Buck Bunny".to_string(),
+            num_files: 3,
+            total_size: 276445467,
+            category: "movies".to_string(),
+        }
+    }
 
     #[test]
     fn test_fs_tree() {
@@ -105,4 +240,1471 @@ mod tests {
             vec![8, 9, 10, 11].into_iter().collect::<HashSet<usize>>()
         );
     }
+
+    #[test]
+    fn test_hook_args() {
+        assert_eq!(
+            hook::split_args(r#"/usr/bin/notify  "%N done" '%F' %Z"#),
+            vec!["/usr/bin/notify", "%N done", "%F", "%Z"]
+        );
+        assert_eq!(hook::split_args("  "), Vec::<String>::new());
+        assert_eq!(hook::split_args(r#"a "" b"#), vec!["a", "", "b"]);
+
+        let ctx = hook_context();
+        assert_eq!(hook::expand("%N done", &ctx), "Big Buck Bunny done");
+        assert_eq!(
+            hook::expand("%I|%D|%F|%C|%Z|%L", &ctx),
+            "dd8255ecdc7ca55fb0bbf81323d87062db1f6d1c|/downloads|\
+            /downloads/Big Buck Bunny|3|276445467|movies"
+        );
+        assert_eq!(hook::expand("100%% %X %", &ctx), "100% %X %");
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_hook_run() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let dir = temp_dir("hook-run");
+        let script = dir.join("hook.sh");
+        fs::write(
+            &script,
+            "#!/bin/sh\necho \"$#|$1|$2\"\necho oops >&2\nexit 3\n",
+        )
+        .unwrap();
+        fs::set_permissions(&script, fs::Permissions::from_mode(0o755)).unwrap();
+        let log_path = dir.join("hooks.log");
+        let command = format!("\"{}\" %N %C", script.to_str().unwrap());
+
+        // Arguments are passed without a shell, so the name stays one argument.
+        let output = hook::execute(
+            &command,
+            "finished",
+            &hook_context(),
+            &HookSettings::default(),
+            &log_path,
+        )
+        .unwrap();
+        assert_eq!(output.status.unwrap().code(), Some(3));
+        assert_eq!(output.stdout, "2|Big Buck Bunny|3\n");
+        assert_eq!(output.stderr, "oops\n");
+
+        let log = fs::read_to_string(&log_path).unwrap();
+        assert!(log.contains(&format!("finished: {}", command)));
+        assert!(log.contains("2|Big Buck Bunny|3"));
+        assert!(log.contains("oops"));
+
+        // Through the shell, the values are quoted parameters which can't run
+        // commands
+        let settings = HookSettings {
+            use_shell: true,
+            ..Default::default()
+        };
+        let output =
+            hook::execute(&command, "added", &hook_context(), &settings, &log_path).unwrap();
+        assert_eq!(output.stdout, "2|Big Buck Bunny|3\n");
+        let ctx = HookContext {
+            name: "x\"; echo injected; \"$(echo injected)`echo injected`".to_string(),
+            ..hook_context()
+        };
+        let output = hook::execute(&command, "added", &ctx, &settings, &log_path).unwrap();
+        assert_eq!(output.stdout, format!("2|{}|3\n", ctx.name));
+        assert_eq!(
+            hook::expand_for_shell("echo %N '%%' %X"),
+            "echo \"$1\" '%' %X"
+        );
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_hook_timeout() {
+        let output = hook::run(
+            "sleep 10",
+            &hook_context(),
+            false,
+            Duration::from_millis(200),
+        );
+        assert!(output.unwrap().status.is_none());
+
+        let output = hook::run(
+            "/nonexistent/program",
+            &hook_context(),
+            false,
+            Duration::from_secs(1),
+        );
+        assert!(output.is_err());
+    }
+
+    fn watched_folder_test(name: &str, use_polling: bool) {
+        let dir = temp_dir(name);
+        let folder = WatchedFolder {
+            path: dir.to_str().unwrap().to_string(),
+            use_polling,
+            ..Default::default()
+        };
+        fs::write(dir.join("existing.torrent"), "d4:infoe").unwrap();
+
+        let (tx, rx) = mpsc::channel();
+        let _watchers = watcher::watch(std::slice::from_ref(&folder), &tx);
+        fs::write(dir.join("notes.txt"), "not a torrent").unwrap();
+        fs::write(dir.join("dropped.torrent"), "d4:infoe").unwrap();
+
+        let mut received = HashSet::new();
+        while received.len() < 2 {
+            match rx.recv_timeout(Duration::from_secs(10)).unwrap() {
+                Message::AddWatchedTorrent(path, f) => {
+                    assert_eq!(f, folder);
+                    received.insert(Path::new(&path).file_name().unwrap().to_owned());
+                }
+                _ => panic!("Unexpected message"),
+            }
+        }
+        assert!(received.contains(Path::new("existing.torrent").as_os_str()));
+        assert!(received.contains(Path::new("dropped.torrent").as_os_str()));
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn test_watched_folder() {
+        watched_folder_test("watch-notify", false);
+        watched_folder_test("watch-poll", true);
+    }
+
+    #[test]
+    fn test_watched_folder_finish() {
+        let dir = temp_dir("watch-finish");
+        let mut folder = WatchedFolder {
+            path: dir.to_str().unwrap().to_string(),
+            ..Default::default()
+        };
+        for name in ["a.torrent", "b.torrent", "c.torrent"] {
+            fs::write(dir.join(name), "").unwrap();
+        }
+
+        watcher::finish(&dir.join("a.torrent"), &folder, true).unwrap();
+        watcher::finish(&dir.join("b.torrent"), &folder, false).unwrap();
+        folder.delete_processed = true;
+        watcher::finish(&dir.join("c.torrent"), &folder, true).unwrap();
+
+        assert!(dir.join("processed").join("a.torrent").exists());
+        assert!(dir.join("failed").join("b.torrent").exists());
+        assert!(!dir.join("c.torrent").exists());
+        assert!(!dir.join("processed").join("c.torrent").exists());
+        assert!(!dir.join("a.torrent").exists() && !dir.join("b.torrent").exists());
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn test_rss_fetch() {
+        let base_url = serve(vec![
+            ("/feed.xml", include_str!("../fixtures/rss/feed.xml")),
+            ("/atom.xml", include_str!("../fixtures/rss/atom.xml")),
+            ("/page.html", "<html><body>Not a feed</body></html>"),
+        ]);
+
+        let feed = rss::fetch_feed(&format!("{}/feed.xml", base_url));
+        assert_eq!(feed.error, None);
+        assert_eq!(feed.title, "Example Tracker");
+        assert_eq!(feed.items.len(), 5);
+        assert_eq!(feed.items[0].title, "Show Name S01E01 1080p WEB");
+        assert_eq!(feed.items[0].guid, "show-s01e01-1080p");
+        assert_eq!(
+            feed.items[0].url,
+            "https://tracker.example.com/download/1.torrent"
+        );
+        assert_eq!(feed.items[0].size, Some(1500000000));
+        assert_eq!(feed.items[3].title, "Show Name 2x03 1080p & Extras");
+        assert_eq!(
+            feed.items[3].url,
+            "magnet:?xt=urn:btih:dd8255ecdc7ca55fb0bbf81323d87062db1f6d1c&dn=show"
+        );
+        assert_eq!(feed.items[3].guid, feed.items[3].url);
+        assert_eq!(feed.items[3].size, Some(1600000000));
+        assert_eq!(
+            feed.items[4].url,
+            "https://tracker.example.com/download/5.torrent"
+        );
+        assert_eq!(feed.items[4].size, None);
+
+        let feed = rss::fetch_feed(&format!("{}/atom.xml", base_url));
+        assert_eq!(feed.error, None);
+        assert_eq!(feed.title, "Example Atom Feed");
+        assert_eq!(feed.items.len(), 2);
+        assert_eq!(feed.items[0].url, "https://example.org/docs/1.torrent");
+        assert_eq!(feed.items[0].size, Some(2500000000));
+        assert_eq!(
+            feed.items[0].guid,
+            "urn:uuid:1225c695-cfb8-4ebb-aaaa-80da344efa6a"
+        );
+        assert_eq!(feed.items[1].url, "https://example.org/docs/2.torrent");
+
+        let feed = rss::fetch_feed(&format!("{}/page.html", base_url));
+        assert!(feed.error.is_some());
+        let feed = rss::fetch_feed(&format!("{}/missing.xml", base_url));
+        assert!(feed.error.is_some());
+    }
+
+    #[test]
+    fn test_rss_episode_filter() {
+        assert_eq!(rss::parse_episode("Show S01E05 1080p"), Some((1, 5)));
+        assert_eq!(rss::parse_episode("Show.s02e103.720p"), Some((2, 103)));
+        assert_eq!(rss::parse_episode("Show 3x07"), Some((3, 7)));
+        assert_eq!(rss::parse_episode("Show 1080p x264"), None);
+
+        assert!(rss::episode_matches("", "Anything"));
+        assert!(rss::episode_matches("S01E05", "Show S01E05"));
+        assert!(!rss::episode_matches("S01E05", "Show S01E06"));
+        assert!(rss::episode_matches("S01", "Show 1x07"));
+        assert!(rss::episode_matches("S01E03-S01E05", "Show S01E04"));
+        assert!(!rss::episode_matches("S01E03-S01E05", "Show S01E06"));
+        assert!(rss::episode_matches("S02E01-", "Show S03E01"));
+        assert!(!rss::episode_matches("S02E01-", "Show S01E20"));
+        assert!(rss::episode_matches("S01E02; S01E04", "Show S01E04"));
+        assert!(!rss::episode_matches("S01E02; S01E04", "Show S01E03"));
+        assert!(!rss::episode_matches("garbage", "Show S01E03"));
+        assert!(!rss::episode_matches("S01E01-", "Show without episode"));
+    }
+
+    #[test]
+    fn test_rss_rules() {
+        let (title, items) = rss::parse_feed(include_str!("../fixtures/rss/feed.xml")).unwrap();
+        let feed = crate::models::feed::Feed {
+            url: "https://tracker.example.com/rss".to_string(),
+            title,
+            items,
+            error: None,
+        };
+        let feeds = vec![feed];
+        let rule = RssRule {
+            name: "Show".to_string(),
+            include: "^show name.*1080p".to_string(),
+            exclude: "720p".to_string(),
+            episode_filter: "S01E02-".to_string(),
+            max_size_mb: 2000,
+            ..Default::default()
+        };
+
+        let titles = |selected: Vec<(crate::models::feed::FeedItem, RssRule)>| {
+            selected
+                .into_iter()
+                .map(|(item, _)| item.title)
+                .collect::<Vec<String>>()
+        };
+        let mut history = HashSet::new();
+        assert_eq!(
+            titles(rss::select_items(
+                &feeds,
+                std::slice::from_ref(&rule),
+                &history
+            )),
+            vec![
+                "Show Name S01E02 1080p WEB",
+                "Show Name 2x03 1080p & Extras"
+            ]
+        );
+
+        // Downloaded items are skipped
+        history.insert("show-s01e02-1080p".to_string());
+        assert_eq!(
+            titles(rss::select_items(
+                &feeds,
+                std::slice::from_ref(&rule),
+                &history
+            )),
+            vec!["Show Name 2x03 1080p & Extras"]
+        );
+
+        // Size bounds
+        let bounded = RssRule {
+            max_size_mb: 1550,
+            ..rule.clone()
+        };
+        assert!(rss::select_items(&feeds, &[bounded], &history).is_empty());
+        let unknown_size = RssRule {
+            name: "Other".to_string(),
+            include: "Other Show".to_string(),
+            min_size_mb: 1,
+            ..Default::default()
+        };
+        assert!(rss::select_items(&feeds, &[unknown_size], &history).is_empty());
+
+        // Rules limited to other feeds or disabled don't apply
+        let other_feed = RssRule {
+            feeds: vec!["https://elsewhere.example.com/rss".to_string()],
+            ..rule.clone()
+        };
+        let disabled = RssRule {
+            enabled: false,
+            ..rule.clone()
+        };
+        assert!(rss::select_items(&feeds, &[other_feed, disabled], &history).is_empty());
+
+        // History persists
+        let dir = temp_dir("rss-history");
+        assert!(rss::save_history(&dir, &history));
+        assert_eq!(rss::load_history(&dir), history);
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn test_categories() {
+        let mut torrent = Torrent::new("Movie".to_string(), "/downloads".to_string());
+        torrent.category = "Movies".to_string();
+        torrent.total_download = 1000;
+        torrent.total_upload = 1500;
+        torrent.seeding_duration = 30 * 60;
+
+        assert!(CategoryFilter::All.matches(&torrent));
+        assert!(!CategoryFilter::Uncategorized.matches(&torrent));
+        assert!(CategoryFilter::Category("Movies".to_string()).matches(&torrent));
+        assert!(!CategoryFilter::Category("TV".to_string()).matches(&torrent));
+
+        let mut category = Category {
+            name: "Movies".to_string(),
+            ..Default::default()
+        };
+        assert!(!category.is_share_limit_reached(&torrent));
+        category.ratio_limit = Some(2.0);
+        assert!(!category.is_share_limit_reached(&torrent));
+        category.ratio_limit = Some(1.5);
+        assert!(category.is_share_limit_reached(&torrent));
+        // Added complete, nothing downloaded
+        let seed = Torrent {
+            total_download: 0,
+            total_size: 1000,
+            ..torrent.clone()
+        };
+        assert!(category.is_share_limit_reached(&seed));
+        category.ratio_limit = None;
+        category.seeding_time_limit_mins = Some(30);
+        assert!(category.is_share_limit_reached(&torrent));
+
+        let dir = temp_dir("categories");
+        Category::save_all(&dir, std::slice::from_ref(&category));
+        assert_eq!(Category::load_all(&dir), vec![category]);
+
+        let hash = "dd8255ecdc7ca55fb0bbf81323d87062db1f6d1c";
+        let metadata = TorrentMetadata {
+            category: "Movies".to_string(),
+            tags: vec!["4K".to_string()],
+        };
+        assert!(metadata.save(&dir, hash));
+        assert_eq!(TorrentMetadata::load_all(&dir).get(hash), Some(&metadata));
+        TorrentMetadata::remove(&dir, hash);
+        assert!(TorrentMetadata::load_all(&dir).is_empty());
+    }
+
+    #[test]
+    fn test_filters() {
+        let tracker = |url: &str| Tracker {
+            tier: 0,
+            url: url.to_string(),
+            status: TrackerStatus::Working,
+            num_peers: 0,
+            num_seeds: 0,
+            message: "".to_string(),
+        };
+        assert_eq!(
+            tracker("udp://Tracker.Example.com:1337/announce").host(),
+            Some("tracker.example.com".to_string())
+        );
+        assert_eq!(
+            tracker("http://user:pass@[2001:db8::1]:80/announce?k=v").host(),
+            Some("2001:db8::1".to_string())
+        );
+        assert_eq!(tracker("").host(), None);
+
+        let mut torrent = Torrent::new("Movie".to_string(), "/downloads".to_string());
+        torrent.state = TorrentState::Downloading;
+        torrent.tags = vec!["4K".to_string()];
+        torrent.trackers = vec![tracker("https://tracker.example.com/announce")];
+
+        assert!(StateFilter::Downloading.matches(&torrent));
+        assert!(StateFilter::Stalled.matches(&torrent));
+        assert!(!StateFilter::Errored.matches(&torrent));
+        torrent.download_rate = 1000;
+        assert!(!StateFilter::Stalled.matches(&torrent));
+        torrent.error = "No space left on device".to_string();
+        assert!(StateFilter::Errored.matches(&torrent));
+
+        assert!(TagFilter::Tag("4K".to_string()).matches(&torrent));
+        assert!(!TagFilter::Untagged.matches(&torrent));
+        assert!(TrackerFilter::Host("tracker.example.com".to_string()).matches(&torrent));
+        assert!(!TrackerFilter::Trackerless.matches(&torrent));
+
+        let mut filter = TorrentFilter {
+            state: StateFilter::Downloading,
+            tag: TagFilter::Tag("4K".to_string()),
+            ..Default::default()
+        };
+        assert!(filter.matches(&torrent));
+        filter.category = CategoryFilter::Category("TV".to_string());
+        assert!(!filter.matches(&torrent));
+    }
+
+    #[test]
+    fn test_search_and_sort() {
+        let torrent = |name: &str, size: i64, eta: i64| {
+            let mut torrent = Torrent::new(name.to_string(), "/downloads".to_string());
+            torrent.total_size = size;
+            torrent.eta = eta;
+            torrent
+        };
+        let mut torrents = vec![
+            torrent("ubuntu-24.04-desktop-amd64.iso", 6_000, -1),
+            torrent("Big Buck Bunny", 300, 60),
+            torrent("debian-12.5.0-amd64-netinst.iso", 600, 10),
+        ];
+        torrents[1].hash = "dd8255ecdc7ca55fb0bbf81323d87062db1f6d1c".to_string();
+
+        let search = |text: &str, is_regex: bool| {
+            let matcher = Search {
+                text: text.to_string(),
+                is_regex,
+            }
+            .matcher()
+            .unwrap();
+            (0..torrents.len())
+                .filter(|i| matcher.matches(&torrents[*i]))
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(search("", false), vec![0, 1, 2]);
+        assert_eq!(search("AMD64", false), vec![0, 2]);
+        assert_eq!(search("dd8255", false), vec![1]);
+        assert_eq!(search(r"^(ubuntu|debian)-\d+", true), vec![0, 2]);
+        assert!(Search {
+            text: "(".to_string(),
+            is_regex: true
+        }
+        .matcher()
+        .is_err());
+
+        // The matcher follows the search
+        let mut cache = SearchCache::default();
+        let mut search = Search {
+            text: "bunny".to_string(),
+            is_regex: false,
+        };
+        assert!(cache
+            .matcher(&search)
+            .as_ref()
+            .unwrap()
+            .matches(&torrents[1]));
+        search.text = "netinst".to_string();
+        assert!(!cache
+            .matcher(&search)
+            .as_ref()
+            .unwrap()
+            .matches(&torrents[1]));
+        search.is_regex = true;
+        search.text = "[".to_string();
+        assert!(cache.matcher(&search).is_err());
+
+        let sort = |key: SortKey, descending: bool| {
+            let mut indices = vec![0, 1, 2];
+            SortOrder { key, descending }.sort(&mut indices, &torrents);
+            indices
+        };
+        assert_eq!(sort(SortKey::Name, false), vec![1, 2, 0]);
+        assert_eq!(sort(SortKey::Size, true), vec![0, 2, 1]);
+        assert_eq!(sort(SortKey::Eta, false), vec![2, 1, 0]);
+        assert_eq!(sort(SortKey::Added, false), vec![0, 1, 2]);
+    }
+
+    #[test]
+    fn test_selection() {
+        let visible = vec![4, 0, 3, 1];
+        let selected = |selection: &Selection| selection.to_vec();
+        let mut selection = Selection::default();
+
+        selection.click(0, &visible, false, false);
+        assert_eq!(selected(&selection), vec![0]);
+        selection.click(1, &visible, false, true);
+        assert_eq!(selected(&selection), vec![0, 1, 3]);
+        selection.click(4, &visible, true, false);
+        assert_eq!(selected(&selection), vec![0, 1, 3, 4]);
+        selection.click(0, &visible, true, false);
+        assert_eq!(selected(&selection), vec![1, 3, 4]);
+        selection.click(3, &visible, false, false);
+        assert_eq!(selected(&selection), vec![3]);
+        selection.click(3, &visible, false, false);
+        assert!(selection.indices.is_empty());
+
+        selection.select_all(&visible);
+        assert_eq!(selected(&selection), vec![0, 1, 3, 4]);
+        selection.truncate(2);
+        assert_eq!(selected(&selection), vec![0, 1]);
+    }
+
+    #[test]
+    fn test_table_layout() {
+        let mut layout = TableLayout::default();
+        assert_eq!(layout.visible()[0], TorrentColumn::Name);
+        assert!(!layout.visible().contains(&TorrentColumn::SavePath));
+
+        layout.move_column(0, 1);
+        layout.move_column(0, -1);
+        assert_eq!(
+            &layout.visible()[..2],
+            &[TorrentColumn::Size, TorrentColumn::Name]
+        );
+        let last = layout.columns.len() - 1;
+        layout.columns[last].1 = true;
+        layout.move_column(last, 1);
+        assert_eq!(layout.visible().last(), Some(&TorrentColumn::SavePath));
+
+        let json = serde_json::to_string(&layout).unwrap();
+        assert_eq!(serde_json::from_str::<TableLayout>(&json).unwrap(), layout);
+    }
+
+    #[test]
+    fn test_speed_history() {
+        let start = Instant::now();
+        let sample = |secs: u64, rate: i64| RateSample {
+            time: start + Duration::from_secs(secs),
+            download: rate,
+            upload: rate / 2,
+        };
+        let mut history = SpeedHistory::new(3);
+        for secs in 0..5 {
+            history.push(sample(secs, secs as i64 * 100));
+        }
+        let now = start + Duration::from_secs(4);
+        let rates = |range: u64| {
+            history
+                .since(now, Duration::from_secs(range))
+                .map(|s| s.download)
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(rates(60), vec![200, 300, 400]);
+        assert_eq!(rates(1), vec![300, 400]);
+
+        let mut torrents = vec![
+            Torrent::new("a".to_string(), "/downloads".to_string()),
+            Torrent::new("b".to_string(), "/downloads".to_string()),
+        ];
+        torrents[0].hash = "a".to_string();
+        torrents[0].download_rate = 1000;
+        torrents[1].hash = "b".to_string();
+        torrents[1].download_rate = 500;
+        let mut histories = SpeedHistories::default();
+        histories.record(now, &torrents);
+        let last = histories.session.since(now, Duration::from_secs(1)).last();
+        assert_eq!(last.map(|s| s.download), Some(1500));
+        assert_eq!(histories.torrents.len(), 2);
+        torrents.remove(0);
+        histories.record(now + Duration::from_secs(1), &torrents);
+        assert!(!histories.torrents.contains_key("a"));
+    }
+
+    #[test]
+    fn test_session_stats() {
+        let start = Instant::now();
+        let counters = |recv: i64, sent: i64| {
+            HashMap::from([
+                ("net.recv_bytes".to_string(), recv),
+                ("net.sent_bytes".to_string(), sent),
+            ])
+        };
+        let mut stats = SessionStats::default();
+        assert!(stats.is_empty());
+        stats.update(start, counters(1000, 100));
+        assert_eq!(stats.get("net.recv_bytes"), Some(1000));
+        assert_eq!(stats.rate("net.recv_bytes"), None);
+
+        // Empty snapshots, posted before the first alert, are ignored
+        stats.update(start + Duration::from_secs(1), HashMap::new());
+        stats.update(start + Duration::from_secs(2), counters(5000, 300));
+        assert_eq!(stats.rate("net.recv_bytes"), Some(2000.0));
+        assert_eq!(stats.sum(&["net.recv_bytes", "net.sent_bytes"]), Some(5300));
+        assert_eq!(
+            stats.sum_rate(&["net.recv_bytes", "net.sent_bytes"]),
+            Some(2100.0)
+        );
+        assert_eq!(stats.sum(&["net.recv_bytes", "dht.dht_nodes"]), None);
+        assert_eq!(stats.rate("dht.dht_nodes"), None);
+    }
+
+    #[test]
+    fn test_log() {
+        let mut log = Log::new(3);
+        for i in 0..5 {
+            log.push(LogEntry::new(
+                LogLevel::Info,
+                LogSource::App,
+                format!("entry {}", i),
+            ));
+        }
+        log.push(LogEntry::new(
+            LogLevel::Error,
+            LogSource::Alert,
+            "tracker error".to_owned(),
+        ));
+        let messages = |log: &Log, filter: &LogFilter| {
+            let matcher = filter.search.matcher().unwrap();
+            log.entries()
+                .filter(|e| filter.matches(e, &matcher))
+                .map(|e| e.message.clone())
+                .collect::<Vec<_>>()
+        };
+        let mut filter = LogFilter::default();
+        assert_eq!(
+            messages(&log, &filter),
+            vec!["entry 3", "entry 4", "tracker error"]
+        );
+        filter.min_level = LogLevel::Warning;
+        assert_eq!(messages(&log, &filter), vec!["tracker error"]);
+        filter.min_level = LogLevel::Debug;
+        filter.source = Some(LogSource::App);
+        filter.search.text = "4".to_owned();
+        assert_eq!(messages(&log, &filter), vec!["entry 4"]);
+        log.set_capacity(1);
+        assert_eq!(log.entries().count(), 1);
+        log.clear();
+        assert_eq!(log.count(LogSource::App, LogLevel::Info), 5);
+        assert_eq!(log.count(LogSource::Alert, LogLevel::Error), 1);
+
+        assert_eq!(
+            LogSource::from_target("torrenter::controllers::message"),
+            Some(LogSource::App)
+        );
+        assert_eq!(
+            LogSource::from_target("libtorrent::alert"),
+            Some(LogSource::Alert)
+        );
+        assert_eq!(LogSource::from_target("eframe"), None);
+        assert_eq!(
+            AlertCategory::mask(&[AlertCategory::Error, AlertCategory::Status]),
+            1 | 64
+        );
+
+        // Files rotate past the size limit, keeping at most 3
+        let dir = temp_dir("log");
+        let mut file = RotatingFile::new(&dir, 20, 3);
+        for i in 0..5 {
+            file.write_line(&format!("line {} of the log", i)).unwrap();
+        }
+        assert_eq!(
+            fs::read_to_string(file.path(0)).unwrap(),
+            "line 4 of the log\n"
+        );
+        assert_eq!(
+            fs::read_to_string(file.path(2)).unwrap(),
+            "line 2 of the log\n"
+        );
+        assert!(!file.path(3).exists());
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_control_socket() {
+        use std::os::unix::net::UnixStream;
+
+        let dir = temp_dir("control");
+        let (tx, rx) = mpsc::channel();
+        control::serve(&dir, tx.clone()).unwrap();
+        assert!(control::serve(&dir, tx).is_err());
+
+        let stream = UnixStream::connect(control::socket_path(&dir)).unwrap();
+        let mut writer = stream.try_clone().unwrap();
+        let mut reader = BufReader::new(stream);
+        let mut response = || {
+            let mut line = String::new();
+            reader.read_line(&mut line).unwrap();
+            serde_json::from_str::<ControlResponse>(&line).unwrap()
+        };
+
+        writeln!(writer, r#"{{"command": "pause", "hash": "abc"}}"#).unwrap();
+        match rx.recv_timeout(Duration::from_secs(10)).unwrap() {
+            Message::Control(request, reply_tx) => {
+                assert_eq!(
+                    request,
+                    ControlRequest::Pause {
+                        hash: "abc".to_owned()
+                    }
+                );
+                reply_tx
+                    .send(ControlResponse::failure("No such torrent"))
+                    .unwrap();
+            }
+            _ => panic!("Unexpected message"),
+        }
+        let res = response();
+        assert!(!res.ok);
+        assert_eq!(res.error.as_deref(), Some("No such torrent"));
+
+        writeln!(writer, r#"{{"command": "explode"}}"#).unwrap();
+        assert!(!response().ok);
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_cli() {
+        let args = |line: &str| line.split(' ').map(str::to_owned).collect::<Vec<_>>();
+        assert!(!cli::is_command(&args("--headless")));
+        assert!(!cli::is_command(&[]));
+        assert!(cli::is_command(&args("list")));
+        assert_eq!(cli::parse(&args("list")), Ok(ControlRequest::List));
+        assert_eq!(
+            cli::parse(&args("add magnet:?xt=abc --category Linux --paused")),
+            Ok(ControlRequest::Add {
+                source: "magnet:?xt=abc".to_owned(),
+                save_path: "".to_owned(),
+                category: "Linux".to_owned(),
+                paused: true,
+            })
+        );
+        assert_eq!(
+            cli::parse(&args("remove abc --delete-files")),
+            Ok(ControlRequest::Remove {
+                hash: "abc".to_owned(),
+                delete_files: true,
+            })
+        );
+        assert_eq!(
+            cli::parse(&args("set-limit --download 500")),
+            Ok(ControlRequest::SetLimit {
+                hash: None,
+                download: Some(500_000),
+                upload: None,
+            })
+        );
+        assert_eq!(
+            cli::parse(&args("set-limit abc --upload 0")),
+            Ok(ControlRequest::SetLimit {
+                hash: Some("abc".to_owned()),
+                download: None,
+                upload: Some(0),
+            })
+        );
+        assert!(cli::parse(&args("set-limit abc")).is_err());
+        assert!(cli::parse(&args("set-limit --download -5")).is_err());
+        assert!(cli::parse(&args("pause")).is_err());
+        assert!(cli::parse(&args("list --force")).is_err());
+
+        // Requests reach the running instance as JSON
+        let json = serde_json::to_value(cli::parse(&args("pause abc")).unwrap()).unwrap();
+        assert_eq!(json, serde_json::json!({"command": "pause", "hash": "abc"}));
+        let dir = temp_dir("cli");
+        assert_eq!(cli::run(&args("list"), &dir), cli::EXIT_NOT_RUNNING);
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn test_single_instance() {
+        let dir = temp_dir("instance");
+        let lock = instance::lock(&dir);
+        assert!(lock.is_some());
+        assert!(instance::lock(&dir).is_none());
+        drop(lock);
+        assert!(instance::lock(&dir).is_some());
+
+        let magnet = "magnet:?xt=urn:btih:abc";
+        assert!(matches!(
+            instance::parse_source(magnet),
+            Some((source, AddTorrentKind::MagnetUrl)) if source == magnet
+        ));
+        let path = dir.join("a file.torrent");
+        fs::write(&path, "d4:infoe").unwrap();
+        let path = fs::canonicalize(path).unwrap().to_str().unwrap().to_owned();
+        let url = format!("file://{}", path.replace(' ', "%20"));
+        for arg in [&path, &url] {
+            assert!(matches!(
+                instance::parse_source(arg),
+                Some((source, AddTorrentKind::File)) if source == path
+            ));
+        }
+        assert!(instance::parse_source("--headless").is_none());
+        assert!(instance::parse_source(dir.join("missing.torrent").to_str().unwrap()).is_none());
+
+        let entry = instance::desktop_entry(Path::new("/opt/my apps/torrenter"));
+        assert!(entry.contains("Exec=\"/opt/my apps/torrenter\" %U\n"));
+        assert!(entry.contains("MimeType=x-scheme-handler/magnet;application/x-bittorrent;\n"));
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn test_web_api() {
+        use base64::{engine::general_purpose::STANDARD, Engine};
+
+        let dir = temp_dir("web");
+        let settings = WebSettings {
+            password: "secret".to_owned(),
+            ..Default::default()
+        };
+        let (server, base, _) = web_server(settings, &dir, vec![], |r| serde_json::json!(r));
+
+        let auth = format!("Basic {}", STANDARD.encode("admin:secret"));
+        let send = |method: &str, path: &str, headers: &[(&str, &str)], body: &str| {
+            let mut request =
+                ureq::request(method, &format!("{}{}", base, path)).set("Authorization", &auth);
+            for (name, value) in headers {
+                request = request.set(name, value);
+            }
+            match request.send_string(body) {
+                Ok(res) | Err(ureq::Error::Status(_, res)) => {
+                    (res.status(), res.into_string().unwrap())
+                }
+                Err(e) => panic!("{}", e),
+            }
+        };
+        let call = |method: &str, path: &str, body: &str| {
+            send(method, path, &[("Content-Type", "application/json")], body)
+        };
+
+        match ureq::get(&format!("{}/api/torrents", base)).call() {
+            Err(ureq::Error::Status(401, res)) => {
+                assert!(res.header("WWW-Authenticate").is_some())
+            }
+            _ => panic!("Expected 401"),
+        }
+        let (status, page) = call("GET", "/", "");
+        assert_eq!(status, 200);
+        assert!(page.contains("<title>Torrenter</title>"));
+
+        let data = |(status, body): (u16, String)| {
+            assert_eq!(status, 200, "{}", body);
+            serde_json::from_str::<ControlResponse>(&body).unwrap().data
+        };
+        assert_eq!(
+            data(call("GET", "/api/torrents", "")),
+            serde_json::json!({"command": "list"})
+        );
+        assert_eq!(
+            data(call("GET", "/api/torrents/abc/peers", "")),
+            serde_json::json!({"command": "peers", "hash": "abc"})
+        );
+        assert_eq!(
+            data(call("DELETE", "/api/torrents/abc?delete_files=true", "")),
+            serde_json::json!({"command": "remove", "hash": "abc", "delete_files": true})
+        );
+        assert_eq!(
+            data(call(
+                "POST",
+                "/api/torrents/abc/set-limit",
+                r#"{"download": 1000}"#
+            )),
+            serde_json::json!({"command": "set-limit", "hash": "abc", "download": 1000, "upload": null})
+        );
+        assert_eq!(
+            data(call(
+                "POST",
+                "/api/torrents",
+                r#"{"source": "magnet:?xt=abc"}"#
+            )),
+            serde_json::json!({"command": "add", "source": "magnet:?xt=abc", "save_path": "", "category": "", "paused": false})
+        );
+
+        // Paths on the host can't be added, nor arbitrary commands run
+        assert_eq!(
+            call("POST", "/api/torrents", r#"{"source": "/etc/passwd"}"#).0,
+            400
+        );
+        assert_eq!(call("POST", "/api/torrents/abc/open", "{}").0, 404);
+        assert_eq!(call("POST", "/api/torrents/abc/move", "{}").0, 400);
+
+        // Other sites can't make the browser send commands
+        let form = [("Content-Type", "text/plain")];
+        assert_eq!(send("POST", "/api/torrents/abc/pause", &form, "{}").0, 415);
+        let host = base.trim_start_matches("http://");
+        let foreign = [
+            ("Content-Type", "application/json"),
+            ("Origin", "http://evil.example"),
+        ];
+        assert_eq!(
+            send("POST", "/api/torrents/abc/pause", &foreign, "{}").0,
+            403
+        );
+        assert_eq!(
+            send("DELETE", "/api/torrents/abc", &foreign[1..], "").0,
+            403
+        );
+        let origin = format!("http://{}", host);
+        let own = [
+            ("Content-Type", "application/json"),
+            ("Origin", origin.as_str()),
+        ];
+        assert_eq!(send("POST", "/api/torrents/abc/pause", &own, "{}").0, 200);
+        drop(server);
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn test_transmission_rpc() {
+        let dir = temp_dir("transmission");
+        let mut torrent = Torrent::new("Big Buck Bunny".to_owned(), "/downloads".to_owned());
+        torrent.hash = "dd8255ecdc7ca55fb0bbf81323d87062db1f6d1c".to_owned();
+        torrent.state = TorrentState::Downloading;
+        torrent.progress = 0.25;
+        torrent.total_size = 1000;
+        let settings = WebSettings {
+            username: "".to_owned(),
+            transmission_rpc: true,
+            ..Default::default()
+        };
+        let (_server, base, requests) =
+            web_server(settings, &dir, vec![torrent], |r| serde_json::json!(r));
+        let url = format!("{}/transmission/rpc", base);
+
+        // The session id comes with a 409 response
+        let session_id = match ureq::post(&url).send_string("{}") {
+            Err(ureq::Error::Status(409, res)) => {
+                res.header("X-Transmission-Session-Id").unwrap().to_owned()
+            }
+            _ => panic!("Expected 409"),
+        };
+        let call = |body: serde_json::Value| -> serde_json::Value {
+            let res = ureq::post(&url)
+                .set("X-Transmission-Session-Id", &session_id)
+                .send_string(&body.to_string())
+                .unwrap();
+            serde_json::from_str(&res.into_string().unwrap()).unwrap()
+        };
+
+        let res = call(serde_json::json!({
+            "method": "torrent-get",
+            "arguments": {"fields": ["id", "name", "status", "leftUntilDone", "unknown"]},
+            "tag": 7,
+        }));
+        assert_eq!(res["result"], "success");
+        assert_eq!(res["tag"], 7);
+        assert_eq!(
+            res["arguments"]["torrents"],
+            serde_json::json!([{"id": 1, "name": "Big Buck Bunny", "status": 4, "leftUntilDone": 750}])
+        );
+
+        let res = call(serde_json::json!({"method": "torrent-stop", "arguments": {"ids": [1]}}));
+        assert_eq!(res["result"], "success");
+        assert_eq!(
+            requests.recv_timeout(Duration::from_secs(10)).unwrap(),
+            ControlRequest::Pause {
+                hash: "dd8255ecdc7ca55fb0bbf81323d87062db1f6d1c".to_owned()
+            }
+        );
+        // Unknown ids select nothing
+        call(serde_json::json!({"method": "torrent-start", "arguments": {"ids": [2]}}));
+        assert!(requests.try_recv().is_err());
+
+        let res = call(serde_json::json!({
+            "method": "torrent-add",
+            "arguments": {"filename": "magnet:?xt=abc", "download-dir": "/movies", "paused": true},
+        }));
+        assert_eq!(res["result"], "success");
+        assert_eq!(
+            requests.recv_timeout(Duration::from_secs(10)).unwrap(),
+            ControlRequest::Add {
+                source: "magnet:?xt=abc".to_owned(),
+                save_path: "/movies".to_owned(),
+                category: "".to_owned(),
+                paused: true,
+            }
+        );
+
+        let res = call(serde_json::json!({"method": "session-stats"}));
+        assert_eq!(res["arguments"]["torrentCount"], 1);
+        let res = call(serde_json::json!({"method": "torrent-explode"}));
+        assert_ne!(res["result"], "success");
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn test_qbittorrent_api() {
+        use std::{
+            io::Read,
+            net::{Shutdown, TcpStream},
+        };
+
+        let dir = temp_dir("qbittorrent");
+        let hash = "dd8255ecdc7ca55fb0bbf81323d87062db1f6d1c";
+        let mut torrent = Torrent::new("Series.S01E01".to_owned(), "/downloads/tv".to_owned());
+        torrent.hash = hash.to_owned();
+        torrent.state = TorrentState::Seeding;
+        torrent.progress = 1.0;
+        torrent.upload_rate = 1000;
+        torrent.category = "tv-sonarr".to_owned();
+        let settings = WebSettings {
+            password: "secret".to_owned(),
+            qbittorrent_api: true,
+            ..Default::default()
+        };
+        let (_server, base, requests) =
+            web_server(settings, &dir, vec![torrent], move |r| match r {
+                ControlRequest::Categories => {
+                    serde_json::json!([{"name": "tv-sonarr", "save_path": "/downloads/tv"}])
+                }
+                ControlRequest::Files { .. } => {
+                    serde_json::json!([{"path": "Series.S01E01.mkv", "priority": "default"}])
+                }
+                _ => serde_json::json!({"hash": hash}),
+            });
+        let addr = base.trim_start_matches("http://").to_owned();
+
+        // Replays a request recorded from Sonarr under the session, returning
+        // the status, the headers and the body of the response
+        let replay = |fixture: &str, sid: &str| -> (u16, String, String) {
+            let raw = fs::read(format!("fixtures/qbittorrent/{}.http", fixture)).unwrap();
+            let raw = String::from_utf8(raw).unwrap();
+            let re = regex::Regex::new("SID=[0-9A-Za-z]+").unwrap();
+            let raw = re.replace(&raw, format!("SID={}", sid));
+            let mut stream = TcpStream::connect(&addr).unwrap();
+            stream.write_all(raw.as_bytes()).unwrap();
+            stream.shutdown(Shutdown::Write).unwrap();
+            let mut response = String::new();
+            stream.read_to_string(&mut response).unwrap();
+            let (head, body) = response.split_once("\r\n\r\n").unwrap();
+            let status = head.split(' ').nth(1).unwrap().parse().unwrap();
+            (status, head.to_owned(), body.to_owned())
+        };
+
+        assert_eq!(replay("app_version", "expired").0, 403);
+        // The web UI's basic authentication doesn't log in
+        let basic = ureq::get(&format!("{}/api/v2/app/version", base))
+            .set("Authorization", "Basic YWRtaW46c2VjcmV0")
+            .call();
+        assert!(matches!(basic, Err(ureq::Error::Status(403, _))));
+        let (status, head, body) = replay("auth_login", "");
+        assert_eq!((status, body.as_str()), (200, "Ok."));
+        let sid = regex::Regex::new("Set-Cookie: SID=([0-9a-f]+)")
+            .unwrap()
+            .captures(&head)
+            .unwrap()[1]
+            .to_owned();
+        let json = |fixture: &str| {
+            let (status, _, body) = replay(fixture, &sid);
+            assert_eq!(status, 200, "{}: {}", fixture, body);
+            serde_json::from_str::<serde_json::Value>(&body).unwrap()
+        };
+
+        assert_eq!(replay("app_version", &sid).2, "v4.6.0");
+        let info = json("torrents_info");
+        assert_eq!(info[0]["hash"], hash);
+        assert_eq!(info[0]["state"], "uploading");
+        assert_eq!(info[0]["content_path"], "/downloads/tv/Series.S01E01");
+        assert_eq!(json("torrents_properties")["save_path"], "/downloads/tv");
+        assert_eq!(
+            json("torrents_files"),
+            serde_json::json!([{"index": 0, "name": "Series.S01E01.mkv", "priority": 1}])
+        );
+        assert_eq!(
+            json("torrents_categories"),
+            serde_json::json!({"tv-sonarr": {"name": "tv-sonarr", "savePath": "/downloads/tv"}})
+        );
+
+        // Requests reach the message controller
+        while requests.try_recv().is_ok() {}
+        assert_eq!(replay("torrents_add", &sid).2, "Ok.");
+        let add = requests.recv_timeout(Duration::from_secs(10)).unwrap();
+        assert!(matches!(
+            add,
+            ControlRequest::Add { save_path, category, paused: true, .. }
+                if save_path == "/downloads/tv" && category == "tv-sonarr"
+        ));
+        replay("torrents_set_category", &sid);
+        let mut seen = vec![];
+        while let Ok(request) = requests.recv_timeout(Duration::from_millis(500)) {
+            seen.push(request);
+        }
+        assert!(seen.contains(&ControlRequest::SetCategory {
+            hash: hash.to_owned(),
+            category: "tv-sonarr".to_owned(),
+        }));
+        // Nor can links or forms of other sites change anything
+        let url = format!("{}/api/v2/torrents/delete?hashes=all", base);
+        let cookie = format!("SID={}", sid);
+        let get = ureq::get(&url).set("Cookie", &cookie).call();
+        assert!(matches!(get, Err(ureq::Error::Status(405, _))));
+        let cross_site = ureq::post(&url)
+            .set("Cookie", &cookie)
+            .set("Origin", "http://evil.example")
+            .call();
+        assert!(matches!(cross_site, Err(ureq::Error::Status(401, _))));
+        assert!(requests.recv_timeout(Duration::from_millis(500)).is_err());
+
+        replay("torrents_delete", &sid);
+        assert_eq!(
+            requests.recv_timeout(Duration::from_secs(10)).unwrap(),
+            ControlRequest::Remove {
+                hash: hash.to_owned(),
+                delete_files: true,
+            }
+        );
+
+        // Logging in again and again ends the oldest sessions
+        for _ in 0..qbittorrent::MAX_SESSIONS {
+            replay("auth_login", "");
+        }
+        assert_eq!(replay("app_version", &sid).0, 403);
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn test_metrics() {
+        let mut torrent = Torrent::new("Big \"Buck\" Bunny".to_owned(), "/downloads".to_owned());
+        torrent.hash = "dd8255ecdc7ca55fb0bbf81323d87062db1f6d1c".to_owned();
+        torrent.state = TorrentState::Downloading;
+        torrent.progress = 0.5;
+        torrent.download_rate = 2000;
+        torrent.num_peers = 3;
+        torrent.num_seeds = 1;
+        let mut paused = torrent.clone();
+        paused.hash = "a".repeat(40);
+        paused.state = TorrentState::Paused;
+        paused.download_rate = 0;
+        paused.error = "file error".to_owned();
+        let mut stats = SessionStats::default();
+        stats.update(
+            Instant::now(),
+            HashMap::from([
+                ("net.recv_payload_bytes".to_owned(), 123456),
+                ("dht.dht_nodes".to_owned(), 42),
+            ]),
+        );
+        let mut log = Log::new(10);
+        log.push(LogEntry::new(
+            LogLevel::Error,
+            LogSource::Alert,
+            "tracker error".to_owned(),
+        ));
+
+        let text = metrics::render(&[torrent, paused], &stats, &log);
+        for line in [
+            "# TYPE torrenter_download_rate_bytes gauge",
+            "torrenter_download_rate_bytes 2000",
+            "# TYPE torrenter_downloaded_bytes_total counter",
+            "torrenter_downloaded_bytes_total 123456",
+            "torrenter_dht_nodes 42",
+            "torrenter_torrents{state=\"downloading\"} 1",
+            "torrenter_torrents{state=\"paused\"} 1",
+            "torrenter_torrents{state=\"seeding\"} 0",
+            "torrenter_torrents_errored 1",
+            "torrenter_torrent_progress{hash=\"dd8255ecdc7ca55fb0bbf81323d87062db1f6d1c\",name=\"Big \\\"Buck\\\" Bunny\"} 0.5",
+            "torrenter_torrent_peers{hash=\"dd8255ecdc7ca55fb0bbf81323d87062db1f6d1c\",name=\"Big \\\"Buck\\\" Bunny\"} 3",
+            "torrenter_log_messages_total{source=\"alert\",level=\"error\"} 1",
+            "torrenter_log_messages_total{source=\"app\",level=\"warning\"} 0",
+        ] {
+            assert!(text.lines().any(|l| l == line), "{} missing from\n{}", line, text);
+        }
+        // Counters the session didn't post are left out
+        assert!(!text.contains("torrenter_uploaded_bytes_total"));
+
+        // Served only when enabled
+        let dir = temp_dir("metrics");
+        let settings = WebSettings {
+            username: "".to_owned(),
+            metrics: true,
+            ..Default::default()
+        };
+        let (_server, base, _) =
+            web_server(settings.clone(), &dir, vec![], |r| serde_json::json!(r));
+        let response = ureq::get(&format!("{}/metrics", base)).call().unwrap();
+        assert!(response.content_type().starts_with("text/plain"));
+        assert!(response
+            .into_string()
+            .unwrap()
+            .contains("torrenter_download_rate_bytes 0"));
+        let settings = WebSettings {
+            metrics: false,
+            ..settings
+        };
+        let (_server, base, _) = web_server(settings, &dir, vec![], |r| serde_json::json!(r));
+        let status = match ureq::get(&format!("{}/metrics", base)).call() {
+            Err(ureq::Error::Status(status, _)) => status,
+            _ => 0,
+        };
+        assert_eq!(status, 404);
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn test_ip_filter() {
+        let range = |first: &str, last: &str| IpRange {
+            first: first.parse().unwrap(),
+            last: last.parse().unwrap(),
+        };
+        // eMule, zero padded, allowing levels above 127
+        assert_eq!(
+            parse_line("001.002.004.000 - 001.002.004.255 , 000 , China Internet Information"),
+            Some(range("1.2.4.0", "1.2.4.255"))
+        );
+        assert_eq!(parse_line("1.2.5.0 - 1.2.5.255 , 200 , Allowed"), None);
+        // PeerGuardian P2P, whose descriptions contain colons and dashes
+        assert_eq!(
+            parse_line("Bad-Org: Inc.:3.0.0.0-3.0.0.255"),
+            Some(range("3.0.0.0", "3.0.0.255"))
+        );
+        assert_eq!(
+            parse_line("Foo, Inc:1.2.3.4-1.2.3.5"),
+            Some(range("1.2.3.4", "1.2.3.5"))
+        );
+        // CIDR and single addresses
+        assert_eq!(
+            parse_line("10.0.0.7/8"),
+            Some(range("10.0.0.0", "10.255.255.255"))
+        );
+        assert_eq!(
+            parse_line("2001:db8::/32"),
+            Some(range(
+                "2001:db8::",
+                "2001:db8:ffff:ffff:ffff:ffff:ffff:ffff"
+            ))
+        );
+        assert_eq!(
+            parse_line("0.0.0.0/0"),
+            Some(range("0.0.0.0", "255.255.255.255"))
+        );
+        assert_eq!(parse_line("5.6.7.8"), Some(range("5.6.7.8", "5.6.7.8")));
+        for invalid in [
+            "# comment",
+            "",
+            "1.2.3.4/33",
+            "2.0.0.0-1.0.0.0",
+            "1.2.3-1.2.4",
+            "::1-1.2.3.4",
+        ] {
+            assert_eq!(parse_line(invalid), None, "{}", invalid);
+        }
+
+        assert_eq!(
+            merge(vec![
+                range("1.0.0.10", "1.0.0.20"),
+                range("::1", "::2"),
+                range("1.0.0.0", "1.0.0.9"),
+                range("1.0.0.15", "1.0.0.30"),
+                range("1.0.0.32", "1.0.0.40"),
+                range("::2", "::3"),
+            ]),
+            vec![
+                range("1.0.0.0", "1.0.0.30"),
+                range("1.0.0.32", "1.0.0.40"),
+                range("::1", "::3"),
+            ]
+        );
+
+        // Lists merge, gzipped or not, skipping the missing ones
+        let dir = temp_dir("ip_filter");
+        let plain = dir.join("level1.p2p");
+        fs::write(&plain, "Bad:1.0.0.0-1.0.0.255\n").unwrap();
+        let gzipped = dir.join("ipfilter.dat.gz");
+        let mut encoder =
+            flate2::write::GzEncoder::new(fs::File::create(&gzipped).unwrap(), Default::default());
+        encoder
+            .write_all(b"001.000.001.000 - 001.000.001.255 , 000 , Bad\n")
+            .unwrap();
+        encoder.finish().unwrap();
+        let missing = dir.join("missing.txt");
+        let sources: Vec<String> = [&plain, &gzipped, &missing]
+            .iter()
+            .map(|p| p.to_str().unwrap().to_owned())
+            .collect();
+        let (ranges, errors) = ip_filter::load_all(&sources);
+        assert_eq!(ranges, vec![range("1.0.0.0", "1.0.1.255")]);
+        assert_eq!(errors.len(), 1);
+        assert!(errors[0].starts_with(&sources[2]));
+        fs::remove_dir_all(dir).unwrap();
+
+        // Banned peers join the lists
+        let banned_ips = ["1.0.2.0", "2001:db8::1", "invalid"].map(String::from);
+        assert_eq!(
+            ip_filter::with_banned(&ranges, &banned_ips),
+            vec![
+                range("1.0.0.0", "1.0.2.0"),
+                range("2001:db8::1", "2001:db8::1")
+            ]
+        );
+        let mut peer = Peer {
+            client: "qBittorrent 4.6.0".to_owned(),
+            ip_address: "2001:db8::1".to_owned(),
+            port: 6881,
+            ..Default::default()
+        };
+        assert_eq!(peer.address(), "[2001:db8::1]:6881");
+        peer.ip_address = "1.0.2.0".to_owned();
+        assert_eq!(peer.address(), "1.0.2.0:6881");
+    }
+
+    #[test]
+    fn test_peer_table() {
+        let peer = |ip: &str, port: u16, download_rate: i64| Peer {
+            ip_address: ip.to_owned(),
+            port,
+            download_rate,
+            ..Default::default()
+        };
+        let peers = vec![
+            peer("10.0.0.2", 6881, 300),
+            peer("2001:db8::1", 51413, 100),
+            peer("9.0.0.1", 6881, 300),
+            Peer {
+                connection: ConnectionType::Utp,
+                ..peer("10.0.0.10", 80, 200)
+            },
+        ];
+        let mut layout = PeerTableLayout::default();
+        assert_eq!(layout.sorted(&peers), vec![0, 2, 3, 1]);
+        // Addresses sort numerically, IPv4 first
+        layout.sort_by(PeerColumn::Address);
+        assert_eq!(layout.sorted(&peers), vec![2, 0, 3, 1]);
+        layout.sort_by(PeerColumn::Address);
+        assert_eq!(layout.sorted(&peers), vec![1, 3, 0, 2]);
+        layout.sort_by(PeerColumn::Connection);
+        assert_eq!(layout.sorted(&peers), vec![0, 1, 2, 3]);
+        assert!(!layout.visible().contains(&PeerColumn::Rtt));
+
+        assert_eq!(
+            PeerSource::from_bits(2 | 32),
+            vec![PeerSource::Dht, PeerSource::Incoming]
+        );
+        // Interested and unchoked by the peer, which is interested but choked
+        let flags = PeerFlags::from_bits(1 | 2 | 4 | 16);
+        let abbreviations: String = flags.abbreviations().iter().map(|(c, _)| c).collect();
+        assert_eq!(abbreviations, "DuS");
+        assert_eq!(peers[1].address(), "[2001:db8::1]:51413");
+    }
+
+    #[test]
+    fn test_parse_endpoints() {
+        let text = "192.168.1.2:6881\n\n  [2001:db8::1]:51413 \n10.0.0.1\n10.0.0.1:0\n2001:db8::1:80\n[::1]:99999\nseedbox:6881\n";
+        let (endpoints, invalid) = peer::parse_endpoints(text);
+        assert_eq!(
+            endpoints,
+            vec![
+                "192.168.1.2:6881".parse().unwrap(),
+                "[2001:db8::1]:51413".parse().unwrap()
+            ]
+        );
+        assert_eq!(
+            invalid,
+            vec![
+                "10.0.0.1",
+                "10.0.0.1:0",
+                "2001:db8::1:80",
+                "[::1]:99999",
+                "seedbox:6881"
+            ]
+        );
+    }
+
+    #[test]
+    fn test_proxy() {
+        // A SOCKS5 proxy accepting user:pass, recording the destinations
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let server = thread::spawn(move || {
+            let mut destinations = vec![];
+            for _ in 0..2 {
+                let (mut stream, _) = listener.accept().unwrap();
+                let mut greeting = [0; 3];
+                stream.read_exact(&mut greeting).unwrap();
+                assert_eq!(greeting, [5, 1, 2]);
+                stream.write_all(&[5, 2]).unwrap();
+
+                let read_field = |stream: &mut std::net::TcpStream| {
+                    let mut len = [0; 1];
+                    stream.read_exact(&mut len).unwrap();
+                    let mut field = vec![0; len[0] as usize];
+                    stream.read_exact(&mut field).unwrap();
+                    String::from_utf8(field).unwrap()
+                };
+                let mut version = [0; 1];
+                stream.read_exact(&mut version).unwrap();
+                let credentials = (read_field(&mut stream), read_field(&mut stream));
+                if credentials != ("user".to_owned(), "pass".to_owned()) {
+                    stream.write_all(&[1, 1]).unwrap();
+                    continue;
+                }
+                stream.write_all(&[1, 0]).unwrap();
+
+                let mut request = [0; 4];
+                stream.read_exact(&mut request).unwrap();
+                // Connect to a domain
+                assert_eq!(request, [5, 1, 0, 3]);
+                let domain = read_field(&mut stream);
+                let mut port = [0; 2];
+                stream.read_exact(&mut port).unwrap();
+                destinations.push((domain, u16::from_be_bytes(port)));
+                stream.write_all(&[5, 0, 0, 1, 127, 0, 0, 1, 0, 0]).unwrap();
+            }
+            destinations
+        });
+
+        let mut settings = ProxySettings {
+            kind: ProxyKind::Socks5,
+            host: "127.0.0.1".to_owned(),
+            port,
+            username: "user".to_owned(),
+            password: "pass".to_owned(),
+            ..Default::default()
+        };
+        assert_eq!(proxy::check(&settings, "tracker.example.org", 6969), Ok(()));
+        settings.password = "wrong".to_owned();
+        assert_eq!(
+            proxy::check(&settings, "tracker.example.org", 6969),
+            Err("Proxy refused the credentials".to_owned())
+        );
+        assert_eq!(
+            server.join().unwrap(),
+            vec![("tracker.example.org".to_owned(), 6969)]
+        );
+
+        settings.proxy_peer_connections = false;
+        assert_eq!(
+            settings.proxied(),
+            vec!["tracker connections", "host name lookups"]
+        );
+        settings.kind = ProxyKind::None;
+        assert!(settings.proxied().is_empty());
+        assert!(proxy::check(&settings, "tracker.example.org", 6969).is_err());
+    }
+
+    #[test]
+    fn test_network_interface() {
+        let interface = |name: &str, address: &str, is_up: bool| NetInterface {
+            name: name.to_owned(),
+            address: address.parse().unwrap(),
+            is_up,
+        };
+        let mut interfaces = vec![
+            interface("eth0", "192.168.1.2", true),
+            interface("tun0", "10.8.0.2", true),
+            interface("tun0", "fe80::1", true),
+            interface("tun0", "fd00::2", true),
+        ];
+        let addresses = |interfaces: &[NetInterface], name: &str| -> Vec<String> {
+            network::addresses(interfaces, name)
+                .iter()
+                .map(|a| a.to_string())
+                .collect()
+        };
+        // Link-local addresses don't count
+        assert_eq!(addresses(&interfaces, "tun0"), vec!["10.8.0.2", "fd00::2"]);
+        assert_eq!(addresses(&interfaces, " 10.8.0.2 "), vec!["10.8.0.2"]);
+        assert!(addresses(&interfaces, "wg0").is_empty());
+
+        // Down, then gone, then without its address
+        interfaces[1].is_up = false;
+        interfaces[3].is_up = false;
+        assert!(addresses(&interfaces, "tun0").is_empty());
+        interfaces.truncate(1);
+        let mut status = NetworkStatus {
+            interface: "tun0".to_owned(),
+            addresses: network::addresses(&interfaces, "tun0"),
+            ..Default::default()
+        };
+        assert!(status.is_down());
+        interfaces.push(interface("tun0", "fe80::1", true));
+        assert!(addresses(&interfaces, "tun0").is_empty());
+        status.interface = "".to_owned();
+        assert!(!status.is_down());
+
+        assert_eq!(network::listen_interfaces(""), "0.0.0.0:6881,[::]:6881");
+        assert_eq!(network::listen_interfaces("tun0"), "tun0:6881");
+        assert_eq!(network::listen_interfaces("10.8.0.2"), "10.8.0.2:6881");
+        assert_eq!(network::listen_interfaces("fd00::2"), "[fd00::2]:6881");
+    }
 }
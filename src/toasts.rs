@@ -7,8 +7,14 @@ fn add_toast<I>(toasts: &mut Toasts, message: I, kind: ToastKind)
 where
     I: Into<WidgetText>,
 {
+    // Also logged, for when no window shows the toasts
+    let text = message.into();
+    match kind {
+        ToastKind::Error => log::warn!("{}", text.text()),
+        _ => log::info!("{}", text.text()),
+    }
     toasts.add(Toast {
-        text: message.into(),
+        text,
         kind,
         options: ToastOptions::default()
             .duration(Duration::from_secs(5))
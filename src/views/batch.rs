@@ -0,0 +1,192 @@
+use std::sync::mpsc::Sender;
+
+use egui::{DragValue, Response, TextEdit, Ui, Widget};
+use rfd::FileDialog;
+
+use crate::models::{
+    category::Category,
+    message::{BatchAction, Message, QueueMove},
+};
+
+/// The highest limit libtorrent's bytes per second can hold.
+const MAX_LIMIT_KB: u32 = i32::MAX as u32 / 1000;
+
+/// Inputs of the batch actions kept between frames.
+#[derive(Default)]
+pub struct BatchDraft {
+    pub new_tag: String,
+    /// KB/s, 0 for unlimited.
+    pub download_limit: u32,
+    pub upload_limit: u32,
+}
+
+pub struct BatchWidget<'a> {
+    pub selection: Vec<usize>,
+    pub num_visible: usize,
+    pub categories: &'a [Category],
+    pub tags: &'a [String],
+    pub draft: &'a mut BatchDraft,
+    pub channel_tx: &'a Sender<Message>,
+    pub select_all: &'a mut bool,
+    pub clear_selection: &'a mut bool,
+}
+
+impl<'a> BatchWidget<'a> {
+    fn send(&self, action: BatchAction) {
+        self.channel_tx
+            .send(Message::Batch(self.selection.clone(), action))
+            .unwrap();
+    }
+}
+
+impl<'a> Widget for BatchWidget<'a> {
+    fn ui(self, ui: &mut Ui) -> Response {
+        ui.horizontal_wrapped(|ui| {
+            ui.label(format!("{} selected", self.selection.len()));
+            if self.selection.len() < self.num_visible && ui.button("Select all").clicked() {
+                *self.select_all = true;
+            }
+            if ui.button("Clear").clicked() {
+                *self.clear_selection = true;
+            }
+            ui.separator();
+
+            if ui.button("▶").on_hover_text("Resume").clicked() {
+                self.send(BatchAction::Resume);
+            }
+            if ui.button("⏸").on_hover_text("Pause").clicked() {
+                self.send(BatchAction::Pause);
+            }
+            ui.menu_button("✖", |ui| {
+                if ui.button("Remove").clicked() {
+                    self.send(BatchAction::Remove {
+                        delete_files: false,
+                    });
+                    *self.clear_selection = true;
+                    ui.close_menu();
+                }
+                if ui.button("Remove with files").clicked() {
+                    self.send(BatchAction::Remove { delete_files: true });
+                    *self.clear_selection = true;
+                    ui.close_menu();
+                }
+            })
+            .response
+            .on_hover_text("Remove");
+
+            ui.menu_button("🔖", |ui| {
+                let mut names = vec!["".to_owned()];
+                names.extend(self.categories.iter().map(|c| c.name.clone()));
+                for name in names {
+                    let label = if name.is_empty() {
+                        "Uncategorized"
+                    } else {
+                        &name
+                    };
+                    if ui.button(label).clicked() {
+                        self.send(BatchAction::SetCategory(name.clone()));
+                        ui.close_menu();
+                    }
+                }
+            })
+            .response
+            .on_hover_text("Category");
+
+            ui.menu_button("🏷", |ui| {
+                for tag in self.tags {
+                    ui.horizontal(|ui| {
+                        if ui.small_button("➕").on_hover_text("Add").clicked() {
+                            self.send(BatchAction::AddTag(tag.clone()));
+                        }
+                        if ui.small_button("➖").on_hover_text("Remove").clicked() {
+                            self.send(BatchAction::RemoveTag(tag.clone()));
+                        }
+                        ui.label(tag);
+                    });
+                }
+                ui.horizontal(|ui| {
+                    ui.add(
+                        TextEdit::singleline(&mut self.draft.new_tag)
+                            .hint_text("New tag")
+                            .desired_width(120.0),
+                    );
+                    let tag = self.draft.new_tag.trim().to_owned();
+                    if ui
+                        .add_enabled(!tag.is_empty(), egui::Button::new("Add"))
+                        .clicked()
+                    {
+                        self.send(BatchAction::AddTag(tag));
+                        self.draft.new_tag.clear();
+                    }
+                });
+            })
+            .response
+            .on_hover_text("Tags");
+
+            ui.menu_button("Limits", |ui| {
+                ui.horizontal(|ui| {
+                    ui.label("⬇");
+                    ui.add(
+                        DragValue::new(&mut self.draft.download_limit)
+                            .range(0..=MAX_LIMIT_KB)
+                            .suffix(" KB/s"),
+                    );
+                });
+                ui.horizontal(|ui| {
+                    ui.label("⬆");
+                    ui.add(
+                        DragValue::new(&mut self.draft.upload_limit)
+                            .range(0..=MAX_LIMIT_KB)
+                            .suffix(" KB/s"),
+                    );
+                });
+                ui.small("0 for unlimited");
+                if ui.button("Apply").clicked() {
+                    let to_bytes =
+                        |kb: u32| i32::try_from(kb).unwrap_or(i32::MAX).saturating_mul(1000);
+                    self.send(BatchAction::SetLimits(
+                        to_bytes(self.draft.download_limit),
+                        to_bytes(self.draft.upload_limit),
+                    ));
+                    ui.close_menu();
+                }
+            });
+
+            ui.menu_button("Queue", |ui| {
+                for (direction, label) in [
+                    (QueueMove::Top, "Move to top"),
+                    (QueueMove::Up, "Move up"),
+                    (QueueMove::Down, "Move down"),
+                    (QueueMove::Bottom, "Move to bottom"),
+                ] {
+                    if ui.button(label).clicked() {
+                        self.send(BatchAction::Queue(direction));
+                        ui.close_menu();
+                    }
+                }
+            });
+
+            ui.menu_button("More", |ui| {
+                if ui.button("Force recheck").clicked() {
+                    self.send(BatchAction::Recheck);
+                    ui.close_menu();
+                }
+                if ui.button("Force reannounce").clicked() {
+                    self.send(BatchAction::Reannounce);
+                    ui.close_menu();
+                }
+                if ui.button("Move storage…").clicked() {
+                    if let Some(path) = FileDialog::new().pick_folder() {
+                        self.send(BatchAction::MoveStorage(
+                            path.to_str()
+                                .expect("Failed to convert path to str")
+                                .to_string(),
+                        ));
+                    }
+                    ui.close_menu();
+                }
+            });
+        });
+        ui.response()
+    }
+}
@@ -0,0 +1,53 @@
+use egui::{DragValue, Grid, Response, RichText, TextEdit, Ui, Widget};
+
+use crate::models::category::Category;
+
+/// A category being created (`None`) or edited under its original name.
+pub type CategoryDraft = (Option<String>, Category);
+
+pub struct CategoryEditorWidget<'a> {
+    pub category: &'a mut Category,
+}
+
+impl<'a> Widget for CategoryEditorWidget<'a> {
+    fn ui(self, ui: &mut Ui) -> Response {
+        Grid::new("category_editor").num_columns(2).show(ui, |ui| {
+            ui.label("Name: ");
+            ui.text_edit_singleline(&mut self.category.name);
+            ui.end_row();
+
+            ui.label("Save path: ");
+            ui.add(
+                TextEdit::singleline(&mut self.category.save_path)
+                    .hint_text("Downloads")
+                    .desired_width(300.0),
+            );
+            ui.end_row();
+
+            ui.label("Ratio limit: ");
+            ui.horizontal(|ui| {
+                let mut is_limited = self.category.ratio_limit.is_some();
+                ui.checkbox(&mut is_limited, "");
+                let mut limit = self.category.ratio_limit.unwrap_or(2.0);
+                ui.add_enabled(
+                    is_limited,
+                    DragValue::new(&mut limit).speed(0.05).range(0.0..=100.0),
+                );
+                self.category.ratio_limit = is_limited.then_some(limit);
+            });
+            ui.end_row();
+
+            ui.label("Seeding time limit: ");
+            ui.horizontal(|ui| {
+                let mut is_limited = self.category.seeding_time_limit_mins.is_some();
+                ui.checkbox(&mut is_limited, "");
+                let mut limit = self.category.seeding_time_limit_mins.unwrap_or(1440);
+                ui.add_enabled(is_limited, DragValue::new(&mut limit).suffix(" min"));
+                self.category.seeding_time_limit_mins = is_limited.then_some(limit);
+            });
+            ui.end_row();
+        });
+        ui.label(RichText::new("Seeding torrents are paused once they reach a limit.").small());
+        ui.response()
+    }
+}
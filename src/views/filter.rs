@@ -0,0 +1,157 @@
+use std::{collections::BTreeSet, sync::mpsc::Sender};
+
+use egui::{CollapsingHeader, Response, ScrollArea, Ui, Widget};
+
+use crate::models::{
+    category::{Category, CategoryFilter},
+    filter::{StateFilter, TagFilter, TorrentFilter, TrackerFilter},
+    message::Message,
+    torrent::Torrent,
+};
+
+use super::categories::CategoryDraft;
+
+pub struct FilterWidget<'a> {
+    pub filter: &'a mut TorrentFilter,
+    pub torrents: &'a [Torrent],
+    pub categories: &'a [Category],
+    pub category_draft: &'a mut Option<CategoryDraft>,
+    pub channel_tx: &'a Sender<Message>,
+}
+
+fn filter_label(ui: &mut Ui, is_selected: bool, label: &str, count: usize) -> Response {
+    ui.selectable_label(is_selected, format!("{} ({})", label, count))
+}
+
+impl<'a> FilterWidget<'a> {
+    fn count<F>(&self, f: F) -> usize
+    where
+        F: Fn(&Torrent) -> bool,
+    {
+        self.torrents.iter().filter(|t| f(t)).count()
+    }
+
+    fn states(&mut self, ui: &mut Ui) {
+        for state in StateFilter::ALL {
+            let count = self.count(|t| state.matches(t));
+            if filter_label(ui, self.filter.state == state, state.label(), count).clicked() {
+                self.filter.state = state;
+            }
+        }
+    }
+
+    fn categories(&mut self, ui: &mut Ui) {
+        let mut filters = vec![
+            (CategoryFilter::All, "All".to_owned()),
+            (CategoryFilter::Uncategorized, "Uncategorized".to_owned()),
+        ];
+        for category in self.categories {
+            filters.push((
+                CategoryFilter::Category(category.name.clone()),
+                category.name.clone(),
+            ));
+        }
+        for (filter, label) in filters {
+            let count = self.count(|t| filter.matches(t));
+            let res = filter_label(ui, self.filter.category == filter, &label, count);
+            if res.clicked() {
+                self.filter.category = filter.clone();
+            }
+            if let CategoryFilter::Category(name) = &filter {
+                res.context_menu(|ui| {
+                    if ui.button("Edit…").clicked() {
+                        let category = self.categories.iter().find(|c| &c.name == name);
+                        if let Some(category) = category {
+                            *self.category_draft = Some((Some(name.clone()), category.clone()));
+                        }
+                        ui.close_menu();
+                    }
+                    if ui.button("Remove").clicked() {
+                        if self.filter.category == filter {
+                            self.filter.category = CategoryFilter::All;
+                        }
+                        self.channel_tx
+                            .send(Message::RemoveCategory(name.clone()))
+                            .unwrap();
+                        ui.close_menu();
+                    }
+                });
+            }
+        }
+        if ui.small_button("➕ Add category").clicked() {
+            *self.category_draft = Some((None, Category::default()));
+        }
+    }
+
+    fn tags(&mut self, ui: &mut Ui) {
+        // The selected tag stays listed while the torrents are still loading
+        let mut tags: BTreeSet<String> = self
+            .torrents
+            .iter()
+            .flat_map(|t| t.tags.iter().cloned())
+            .collect();
+        if let TagFilter::Tag(tag) = &self.filter.tag {
+            tags.insert(tag.clone());
+        }
+        let mut filters = vec![
+            (TagFilter::All, "All".to_owned()),
+            (TagFilter::Untagged, "Untagged".to_owned()),
+        ];
+        filters.extend(
+            tags.into_iter()
+                .map(|tag| (TagFilter::Tag(tag.clone()), tag)),
+        );
+        for (filter, label) in filters {
+            let count = self.count(|t| filter.matches(t));
+            if filter_label(ui, self.filter.tag == filter, &label, count).clicked() {
+                self.filter.tag = filter;
+            }
+        }
+    }
+
+    fn trackers(&mut self, ui: &mut Ui) {
+        let mut hosts: BTreeSet<String> = self
+            .torrents
+            .iter()
+            .flat_map(|t| t.trackers.iter().filter_map(|tracker| tracker.host()))
+            .collect();
+        if let TrackerFilter::Host(host) = &self.filter.tracker {
+            hosts.insert(host.clone());
+        }
+        let mut filters = vec![
+            (TrackerFilter::All, "All".to_owned()),
+            (TrackerFilter::Trackerless, "Trackerless".to_owned()),
+        ];
+        filters.extend(
+            hosts
+                .into_iter()
+                .map(|host| (TrackerFilter::Host(host.clone()), host)),
+        );
+        for (filter, label) in filters {
+            let count = self.count(|t| filter.matches(t));
+            if filter_label(ui, self.filter.tracker == filter, &label, count).clicked() {
+                self.filter.tracker = filter;
+            }
+        }
+    }
+}
+
+impl<'a> Widget for FilterWidget<'a> {
+    fn ui(mut self, ui: &mut Ui) -> Response {
+        ScrollArea::vertical().show(ui, |ui| {
+            CollapsingHeader::new("Status")
+                .default_open(true)
+                .show(ui, |ui| self.states(ui));
+            CollapsingHeader::new("Categories")
+                .default_open(true)
+                .show(ui, |ui| self.categories(ui));
+            CollapsingHeader::new("Tags")
+                .default_open(true)
+                .show(ui, |ui| self.tags(ui));
+            CollapsingHeader::new("Trackers")
+                .default_open(true)
+                .show(ui, |ui| self.trackers(ui));
+        });
+        ui.response()
+    }
+}
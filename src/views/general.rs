@@ -39,6 +39,21 @@ impl<'a> Widget for GeneralWidget<'a> {
             ));
             ui.end_row();
 
+            ui.label("Speed Limits: ");
+            let format_limit = |limit: i32| {
+                if limit > 0 {
+                    format_bytes!(limit as i64, "/s")
+                } else {
+                    "∞".to_owned()
+                }
+            };
+            ui.label(format!(
+                "⬇ {} • ⬆ {}",
+                format_limit(self.torrent.download_limit),
+                format_limit(self.torrent.upload_limit)
+            ));
+            ui.end_row();
+
             // Reannounce In
             ui.label("Reannounce In: ");
             ui.label(format_duration(self.torrent.next_announce));
@@ -49,6 +64,16 @@ impl<'a> Widget for GeneralWidget<'a> {
             ui.label(self.torrent.save_path.clone());
             ui.end_row();
 
+            // Category
+            ui.label("Category: ");
+            ui.label(self.torrent.category.clone());
+            ui.end_row();
+
+            // Tags
+            ui.label("Tags: ");
+            ui.label(self.torrent.tags.join(", "));
+            ui.end_row();
+
             // Hash
             ui.label("Hash: ");
             ui.label(self.torrent.hash.clone());
@@ -0,0 +1,82 @@
+use egui::{
+    Color32, ComboBox, Label, Response, RichText, ScrollArea, TextEdit, TextStyle, Ui, Widget,
+};
+
+use crate::models::log::{Log, LogFilter, LogLevel, LogSource};
+
+pub struct LogWidget<'a> {
+    pub log: &'a mut Log,
+    pub filter: &'a mut LogFilter,
+}
+
+fn level_color(level: LogLevel) -> Option<Color32> {
+    match level {
+        LogLevel::Debug => Some(Color32::GRAY),
+        LogLevel::Info => None,
+        LogLevel::Warning => Some(Color32::from_rgb(230, 160, 40)),
+        LogLevel::Error => Some(Color32::RED),
+    }
+}
+
+impl<'a> Widget for LogWidget<'a> {
+    fn ui(self, ui: &mut Ui) -> Response {
+        ui.horizontal(|ui| {
+            ComboBox::from_id_salt("log_level")
+                .selected_text(self.filter.min_level.label())
+                .show_ui(ui, |ui| {
+                    for level in LogLevel::ALL {
+                        ui.selectable_value(&mut self.filter.min_level, level, level.label());
+                    }
+                })
+                .response
+                .on_hover_text("Minimum severity");
+            ComboBox::from_id_salt("log_source")
+                .selected_text(self.filter.source.map_or("All sources", |s| s.label()))
+                .show_ui(ui, |ui| {
+                    ui.selectable_value(&mut self.filter.source, None, "All sources");
+                    for source in LogSource::ALL {
+                        ui.selectable_value(&mut self.filter.source, Some(source), source.label());
+                    }
+                });
+            ui.label("🔍");
+            ui.add(TextEdit::singleline(&mut self.filter.search.text).desired_width(200.0));
+            ui.checkbox(&mut self.filter.search.is_regex, ".*")
+                .on_hover_text("Regular expression");
+            if ui.button("Clear").clicked() {
+                self.log.clear();
+            }
+        });
+
+        let matcher = match self.filter.search.matcher() {
+            Ok(matcher) => matcher,
+            Err(e) => {
+                ui.label(RichText::new(e).color(Color32::RED));
+                return ui.response();
+            }
+        };
+        ui.separator();
+        let entries: Vec<_> = self
+            .log
+            .entries()
+            .filter(|e| self.filter.matches(e, &matcher))
+            .collect();
+        let row_height = ui.text_style_height(&TextStyle::Body);
+        ScrollArea::both()
+            .auto_shrink(false)
+            .stick_to_bottom(true)
+            .show_rows(ui, row_height, entries.len(), |ui, rows| {
+                for entry in &entries[rows] {
+                    ui.horizontal(|ui| {
+                        ui.label(RichText::new(entry.time_of_day()).monospace());
+                        ui.label(RichText::new(entry.source.label()).weak());
+                        let mut text = RichText::new(&entry.message);
+                        if let Some(color) = level_color(entry.level) {
+                            text = text.color(color);
+                        }
+                        ui.add(Label::new(text).extend());
+                    });
+                }
+            });
+        ui.response()
+    }
+}
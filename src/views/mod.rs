@@ -1,7 +1,18 @@
 pub mod add_torrent;
+pub mod batch;
+pub mod categories;
 pub mod files;
+pub mod filter;
 pub mod general;
+pub mod log;
 pub mod peers;
 pub mod progress_bar;
+pub mod rss;
+pub mod search;
+pub mod settings;
+pub mod speed_graph;
+pub mod stats;
 pub mod tab;
 pub mod torrent;
+pub mod torrent_table;
+pub mod trackers;
@@ -1,64 +1,194 @@
-use egui::{Response, RichText, Ui, Widget};
+use std::sync::mpsc::Sender;
+
+use egui::{Color32, Label, ProgressBar, Response, RichText, Sense, TextEdit, Ui, Widget};
 use egui_extras::{Column, TableBuilder};
 
-use crate::{format_bytes, models::peer::Peer};
+use crate::{
+    format_bytes,
+    models::{
+        column::{PeerColumn, PeerTableLayout},
+        message::Message,
+        peer::{self, Peer},
+    },
+};
+
+/// Peers being added to the torrent at the index, as `ip:port` lines.
+pub type PeersDraft = (usize, String);
 
 pub struct PeersWidget<'a> {
     peers: &'a Vec<Peer>,
+    channel_tx: &'a Sender<Message>,
+    torrent_index: usize,
+    layout: &'a mut PeerTableLayout,
+    draft: &'a mut Option<PeersDraft>,
 }
 
 impl<'a> PeersWidget<'a> {
-    pub fn new(peers: &'a Vec<Peer>) -> Self {
-        Self { peers }
+    pub fn new(
+        peers: &'a Vec<Peer>,
+        channel_tx: &'a Sender<Message>,
+        index: usize,
+        layout: &'a mut PeerTableLayout,
+        draft: &'a mut Option<PeersDraft>,
+    ) -> Self {
+        Self {
+            peers,
+            channel_tx,
+            torrent_index: index,
+            layout,
+            draft,
+        }
+    }
+}
+
+pub struct AddPeersWidget<'a> {
+    pub text: &'a mut String,
+}
+
+impl<'a> Widget for AddPeersWidget<'a> {
+    fn ui(self, ui: &mut Ui) -> Response {
+        ui.label("One address per line, e.g. 192.168.1.2:6881 or [2001:db8::1]:6881");
+        ui.add(
+            TextEdit::multiline(self.text)
+                .hint_text("ip:port")
+                .desired_rows(6)
+                .desired_width(350.0),
+        );
+        let (endpoints, invalid) = peer::parse_endpoints(self.text);
+        ui.label(format!("{} valid addresses", endpoints.len()));
+        for line in invalid {
+            ui.colored_label(Color32::RED, format!("Invalid: {}", line));
+        }
+        ui.response()
     }
 }
 
+fn cell(ui: &mut Ui, column: PeerColumn, peer: &Peer) {
+    let text = match column {
+        PeerColumn::Address => peer.address(),
+        PeerColumn::Client => peer.client.clone(),
+        PeerColumn::Connection => peer.connection.label().to_owned(),
+        PeerColumn::Encrypted => if peer.is_encrypted { "✔" } else { "" }.to_owned(),
+        PeerColumn::Flags => {
+            let flags = peer.flags.abbreviations();
+            let text: String = flags.iter().map(|(c, _)| c).collect();
+            let meanings: Vec<String> = flags
+                .iter()
+                .map(|(c, meaning)| format!("{}: {}", c, meaning))
+                .collect();
+            let res = ui.add(Label::new(text).truncate());
+            if !meanings.is_empty() {
+                res.on_hover_text(meanings.join("\n"));
+            }
+            return;
+        }
+        PeerColumn::Source => peer
+            .sources
+            .iter()
+            .map(|s| s.label())
+            .collect::<Vec<_>>()
+            .join(", "),
+        PeerColumn::Progress => {
+            ui.add(ProgressBar::new(peer.progress).text(format!("{:.1}%", peer.progress * 100.0)));
+            return;
+        }
+        PeerColumn::DownloadRate => format_bytes!(peer.download_rate, "/s"),
+        PeerColumn::UploadRate => format_bytes!(peer.upload_rate, "/s"),
+        PeerColumn::Downloaded => format_bytes!(peer.total_download),
+        PeerColumn::Uploaded => format_bytes!(peer.total_upload),
+        PeerColumn::Relevance => format!("{:.0}%", peer.relevance * 100.0),
+        PeerColumn::Rtt => format!("{} ms", peer.rtt),
+    };
+    ui.add(Label::new(text).truncate());
+}
+
 impl<'a> Widget for PeersWidget<'a> {
     fn ui(self, ui: &mut Ui) -> Response {
-        TableBuilder::new(ui)
+        ui.horizontal(|ui| {
+            ui.label(format!("{} peers", self.peers.len()));
+            ui.menu_button("Columns", |ui| {
+                for (column, is_visible) in self.layout.columns.iter_mut() {
+                    // The address is always shown
+                    ui.add_enabled(
+                        *column != PeerColumn::Address,
+                        egui::Checkbox::new(is_visible, column.label()),
+                    );
+                }
+                if ui.button("Reset").clicked() {
+                    *self.layout = PeerTableLayout::default();
+                }
+            });
+            if ui.button("Add peers…").clicked() {
+                *self.draft = Some((self.torrent_index, "".to_owned()));
+            }
+        });
+
+        let columns = self.layout.visible();
+        let indices = self.layout.sorted(self.peers);
+        let mut table = TableBuilder::new(ui)
+            .id_salt(("peer_table", &columns))
             .striped(true)
             .auto_shrink(true)
             .vscroll(false)
-            .column(Column::remainder().resizable(true))
-            .column(Column::auto().resizable(true))
-            .column(Column::auto().resizable(true))
-            .column(Column::auto().resizable(true).at_least(100.0))
-            .column(Column::auto().resizable(true))
+            .sense(Sense::click());
+        for column in &columns {
+            table = table.column(
+                Column::initial(column.initial_width())
+                    .at_least(30.0)
+                    .resizable(true)
+                    .clip(true),
+            );
+        }
+        table
             .header(20.0, |mut header| {
-                header.col(|ui| {
-                    ui.label(RichText::new("IP Address").strong());
-                });
-                header.col(|ui| {
-                    ui.label(RichText::new("Download Rate").strong());
-                });
-                header.col(|ui| {
-                    ui.label(RichText::new("Upload Rate").strong());
-                });
-                header.col(|ui| {
-                    ui.label(RichText::new("Client").strong());
-                });
-                header.col(|ui| {
-                    ui.label(RichText::new("Progress").strong());
-                });
+                for column in &columns {
+                    header.col(|ui| {
+                        let mut text = column.label().to_owned();
+                        if *column == self.layout.sort_column {
+                            text.push_str(if self.layout.sort_descending {
+                                " ⬇"
+                            } else {
+                                " ⬆"
+                            });
+                        }
+                        let res = ui.add(
+                            Label::new(RichText::new(text).strong())
+                                .selectable(false)
+                                .sense(Sense::click()),
+                        );
+                        if res.clicked() {
+                            self.layout.sort_by(*column);
+                        }
+                    });
+                }
             })
-            .body(|mut body| {
-                self.peers.iter().for_each(|p| {
-                    body.row(30.0, |mut row| {
-                        row.col(|ui| {
-                            ui.label(p.ip_address.clone());
-                        });
-                        row.col(|ui| {
-                            ui.label(format_bytes!(p.download_rate, "/s"));
-                        });
-                        row.col(|ui| {
-                            ui.label(format_bytes!(p.upload_rate, "/s"));
-                        });
-                        row.col(|ui| {
-                            ui.label(p.client.clone());
-                        });
-                        row.col(|ui| {
-                            ui.label(format!("{:.2}%", p.progress * 100.0));
-                        });
+            .body(|body| {
+                body.rows(22.0, indices.len(), |mut row| {
+                    let p = &self.peers[indices[row.index()]];
+                    for column in &columns {
+                        row.col(|ui| cell(ui, *column, p));
+                    }
+                    row.response().context_menu(|ui| {
+                        if ui.button("Disconnect").clicked() {
+                            self.channel_tx
+                                .send(Message::DisconnectPeer(
+                                    self.torrent_index,
+                                    p.ip_address.clone(),
+                                    p.port,
+                                ))
+                                .unwrap();
+                            ui.close_menu();
+                        }
+                        if ui.button("Ban").clicked() {
+                            self.channel_tx
+                                .send(Message::BanPeer(p.ip_address.clone()))
+                                .unwrap();
+                            ui.close_menu();
+                        }
+                        if ui.button("Copy address").clicked() {
+                            ui.ctx().copy_text(p.address());
+                            ui.close_menu();
+                        }
                     });
                 });
             });
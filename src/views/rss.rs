@@ -0,0 +1,58 @@
+use std::sync::mpsc::Sender;
+
+use egui::{CollapsingHeader, Color32, Label, Response, RichText, Ui, Widget};
+
+use crate::{
+    format_bytes,
+    models::{feed::Feed, message::Message},
+};
+
+pub struct RssWidget<'a> {
+    pub feeds: &'a [Feed],
+    pub channel_tx: &'a Sender<Message>,
+}
+
+impl<'a> Widget for RssWidget<'a> {
+    fn ui(self, ui: &mut Ui) -> Response {
+        ui.horizontal(|ui| {
+            if ui.button("🔄 Refresh").clicked() {
+                self.channel_tx.send(Message::RefreshFeeds).unwrap();
+            }
+        });
+        ui.add_space(5.0);
+
+        if self.feeds.is_empty() {
+            ui.label("Subscribe to feeds in the settings.");
+        }
+        egui::ScrollArea::vertical().show(ui, |ui| {
+            for feed in self.feeds {
+                let title = if feed.title.is_empty() {
+                    &feed.url
+                } else {
+                    &feed.title
+                };
+                CollapsingHeader::new(format!("{} ({})", title, feed.items.len()))
+                    .id_salt(&feed.url)
+                    .show(ui, |ui| {
+                        if let Some(error) = &feed.error {
+                            ui.label(RichText::new(error).color(Color32::RED));
+                        }
+                        for item in &feed.items {
+                            ui.horizontal(|ui| {
+                                if ui.button("⬇").on_hover_text("Download").clicked() {
+                                    self.channel_tx
+                                        .send(Message::DownloadFeedItem(item.clone()))
+                                        .unwrap();
+                                }
+                                if let Some(size) = item.size {
+                                    ui.label(format_bytes!(size));
+                                }
+                                ui.add(Label::new(&item.title).truncate());
+                            });
+                        }
+                    });
+            }
+        });
+        ui.response()
+    }
+}
@@ -0,0 +1,97 @@
+use egui::{Color32, ComboBox, Response, RichText, TextEdit, Ui, Widget};
+
+use crate::models::{
+    column::{ListMode, TableLayout, TorrentColumn},
+    filter::Search,
+    sort::{SortKey, SortOrder},
+};
+
+pub struct SearchWidget<'a> {
+    pub search: &'a mut Search,
+    pub sort: &'a mut SortOrder,
+    /// Error of an invalid regular expression.
+    pub error: Option<&'a str>,
+    pub mode: &'a mut ListMode,
+    pub layout: &'a mut TableLayout,
+}
+
+impl<'a> Widget for SearchWidget<'a> {
+    fn ui(self, ui: &mut Ui) -> Response {
+        ui.horizontal(|ui| {
+            ui.label("🔍");
+            let res = ui.add(
+                TextEdit::singleline(&mut self.search.text)
+                    .hint_text("Search name, hash, comment or tracker")
+                    .desired_width(250.0),
+            );
+            if let Some(error) = self.error {
+                res.on_hover_text(RichText::new(error).color(Color32::RED));
+                ui.label(RichText::new("Invalid expression").color(Color32::RED));
+            }
+            ui.checkbox(&mut self.search.is_regex, ".*")
+                .on_hover_text("Regular expression");
+
+            ui.separator();
+            ui.label("Sort by: ");
+            ComboBox::from_id_salt("sort_key")
+                .selected_text(self.sort.key.label())
+                .show_ui(ui, |ui| {
+                    for key in SortKey::ALL {
+                        ui.selectable_value(&mut self.sort.key, key, key.label());
+                    }
+                });
+            let text = if self.sort.descending { "⬇" } else { "⬆" };
+            if ui
+                .button(text)
+                .on_hover_text("Ascending/Descending")
+                .clicked()
+            {
+                self.sort.descending = !self.sort.descending;
+            }
+
+            ui.separator();
+            ui.selectable_value(self.mode, ListMode::Cards, "🗂")
+                .on_hover_text("Cards");
+            ui.selectable_value(self.mode, ListMode::Table, "☰")
+                .on_hover_text("Table");
+            if *self.mode == ListMode::Table {
+                ui.menu_button("Columns", |ui| {
+                    let mut moved = None;
+                    let num_columns = self.layout.columns.len();
+                    for (index, (column, is_visible)) in self.layout.columns.iter_mut().enumerate()
+                    {
+                        ui.horizontal(|ui| {
+                            if ui
+                                .add_enabled(index > 0, egui::Button::new("⬆").small())
+                                .clicked()
+                            {
+                                moved = Some((index, -1));
+                            }
+                            if ui
+                                .add_enabled(
+                                    index + 1 < num_columns,
+                                    egui::Button::new("⬇").small(),
+                                )
+                                .clicked()
+                            {
+                                moved = Some((index, 1));
+                            }
+                            // The name is always shown
+                            ui.add_enabled(
+                                *column != TorrentColumn::Name,
+                                egui::Checkbox::new(is_visible, column.label()),
+                            );
+                        });
+                    }
+                    if let Some((index, offset)) = moved {
+                        self.layout.move_column(index, offset);
+                    }
+                    if ui.button("Reset").clicked() {
+                        *self.layout = TableLayout::default();
+                    }
+                });
+            }
+        });
+        ui.response()
+    }
+}
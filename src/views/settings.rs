@@ -0,0 +1,528 @@
+use std::{collections::BTreeSet, sync::mpsc::Sender};
+
+use egui::{CollapsingHeader, Color32, DragValue, Grid, Response, RichText, TextEdit, Ui, Widget};
+use rfd::FileDialog;
+
+use crate::models::{
+    ip_filter::IpFilterStatus,
+    log::AlertCategory,
+    message::Message,
+    network::NetworkStatus,
+    settings::{ProxyKind, ProxySettings, RssRule, Settings, WatchedFolder},
+};
+
+pub struct SettingsWidget<'a> {
+    pub settings: &'a mut Settings,
+    pub ip_filter: &'a IpFilterStatus,
+    pub network: &'a NetworkStatus,
+    /// The live ban list, changed right away rather than on saving.
+    pub banned_ips: &'a [String],
+    pub channel_tx: &'a Sender<Message>,
+}
+
+impl<'a> Widget for SettingsWidget<'a> {
+    fn ui(self, ui: &mut Ui) -> Response {
+        CollapsingHeader::new("Speed Limits").show(ui, |ui| {
+            let limits = &mut self.settings.speed_limits;
+            Grid::new("speed_limits").num_columns(2).show(ui, |ui| {
+                for (label, limit) in [
+                    ("Download: ", &mut limits.download),
+                    ("Upload: ", &mut limits.upload),
+                ] {
+                    ui.label(label);
+                    let mut kbs = *limit / 1000;
+                    if ui
+                        .add(
+                            DragValue::new(&mut kbs)
+                                .range(0..=1_000_000)
+                                .suffix(" KB/s"),
+                        )
+                        .changed()
+                    {
+                        *limit = kbs * 1000;
+                    }
+                    ui.end_row();
+                }
+            });
+            ui.label(RichText::new("0 for unlimited").small());
+        });
+
+        CollapsingHeader::new("Run External Program")
+            .default_open(true)
+            .show(ui, |ui| {
+                let hooks = &mut self.settings.hooks;
+                Grid::new("hook_settings").num_columns(2).show(ui, |ui| {
+                    ui.label("On torrent added: ");
+                    ui.add(TextEdit::singleline(&mut hooks.on_added).desired_width(300.0));
+                    ui.end_row();
+
+                    ui.label("On torrent finished: ");
+                    ui.add(TextEdit::singleline(&mut hooks.on_finished).desired_width(300.0));
+                    ui.end_row();
+
+                    ui.label("Timeout: ");
+                    ui.add(
+                        DragValue::new(&mut hooks.timeout_secs)
+                            .range(1..=3600)
+                            .suffix(" s"),
+                    );
+                    ui.end_row();
+
+                    ui.label("Run through shell: ");
+                    ui.checkbox(&mut hooks.use_shell, "");
+                    ui.end_row();
+                });
+                ui.label(
+                    RichText::new(
+                        "%N name, %I hash, %D save path, %F content path, \
+                        %C file count, %Z size, %L category",
+                    )
+                    .small(),
+                );
+            });
+
+        CollapsingHeader::new("Watched Folders").show(ui, |ui| {
+            let folders = &mut self.settings.watched_folders;
+            let mut removed = None;
+            for (index, folder) in folders.iter_mut().enumerate() {
+                ui.horizontal(|ui| {
+                    ui.label(RichText::new(&folder.path).strong());
+                    if ui.button("✖").on_hover_text("Stop watching").clicked() {
+                        removed = Some(index);
+                    }
+                });
+                Grid::new(("watched_folder", index))
+                    .num_columns(2)
+                    .show(ui, |ui| {
+                        ui.label("Save path: ");
+                        ui.add(
+                            TextEdit::singleline(&mut folder.save_path)
+                                .hint_text("Downloads")
+                                .desired_width(300.0),
+                        );
+                        ui.end_row();
+
+                        ui.label("Category: ");
+                        ui.add(TextEdit::singleline(&mut folder.category).desired_width(300.0));
+                        ui.end_row();
+                    });
+                ui.checkbox(&mut folder.start_paused, "Start paused");
+                ui.checkbox(&mut folder.delete_processed, "Delete added files");
+                ui.checkbox(&mut folder.use_polling, "Poll instead of watching");
+                ui.separator();
+            }
+            if let Some(index) = removed {
+                folders.remove(index);
+            }
+            if ui.button("Add folder…").clicked() {
+                if let Some(path) = FileDialog::new().pick_folder() {
+                    folders.push(WatchedFolder {
+                        path: path
+                            .to_str()
+                            .expect("Failed to convert path to str")
+                            .to_string(),
+                        ..Default::default()
+                    });
+                }
+            }
+        });
+
+        CollapsingHeader::new("RSS").show(ui, |ui| {
+            let rss = &mut self.settings.rss;
+            ui.horizontal(|ui| {
+                ui.label("Refresh every: ");
+                ui.add(
+                    DragValue::new(&mut rss.refresh_interval_mins)
+                        .range(1..=1440)
+                        .suffix(" min"),
+                );
+            });
+
+            ui.label(RichText::new("Feeds").strong());
+            let mut removed = None;
+            for (index, url) in rss.feeds.iter_mut().enumerate() {
+                ui.horizontal(|ui| {
+                    ui.add(
+                        TextEdit::singleline(url)
+                            .hint_text("https://")
+                            .desired_width(350.0),
+                    );
+                    if ui.button("✖").on_hover_text("Unsubscribe").clicked() {
+                        removed = Some(index);
+                    }
+                });
+            }
+            if let Some(index) = removed {
+                rss.feeds.remove(index);
+            }
+            if ui.button("Add feed").clicked() {
+                rss.feeds.push("".to_owned());
+            }
+            ui.add_space(5.0);
+
+            ui.label(RichText::new("Download Rules").strong());
+            let mut removed = None;
+            for (index, rule) in rss.rules.iter_mut().enumerate() {
+                ui.horizontal(|ui| {
+                    ui.checkbox(&mut rule.enabled, "");
+                    CollapsingHeader::new(&rule.name)
+                        .id_salt(("rss_rule", index))
+                        .show(ui, |ui| {
+                            Grid::new(("rss_rule_grid", index))
+                                .num_columns(2)
+                                .show(ui, |ui| {
+                                    ui.label("Name: ");
+                                    ui.text_edit_singleline(&mut rule.name);
+                                    ui.end_row();
+
+                                    ui.label("Must match: ");
+                                    ui.text_edit_singleline(&mut rule.include);
+                                    ui.end_row();
+
+                                    ui.label("Must not match: ");
+                                    ui.text_edit_singleline(&mut rule.exclude);
+                                    ui.end_row();
+
+                                    ui.label("Episodes: ");
+                                    ui.add(
+                                        TextEdit::singleline(&mut rule.episode_filter)
+                                            .hint_text("S01E01-"),
+                                    );
+                                    ui.end_row();
+
+                                    ui.label("Size: ");
+                                    ui.horizontal(|ui| {
+                                        ui.add(
+                                            DragValue::new(&mut rule.min_size_mb)
+                                                .prefix("min ")
+                                                .suffix(" MB"),
+                                        );
+                                        ui.add(
+                                            DragValue::new(&mut rule.max_size_mb)
+                                                .prefix("max ")
+                                                .suffix(" MB"),
+                                        );
+                                    });
+                                    ui.end_row();
+
+                                    ui.label("Category: ");
+                                    ui.text_edit_singleline(&mut rule.category);
+                                    ui.end_row();
+
+                                    ui.label("Save path: ");
+                                    ui.add(
+                                        TextEdit::singleline(&mut rule.save_path)
+                                            .hint_text("Downloads"),
+                                    );
+                                    ui.end_row();
+                                });
+                            ui.checkbox(&mut rule.start_paused, "Start paused");
+
+                            ui.label("Apply to (all feeds if none selected):");
+                            for url in &rss.feeds {
+                                let mut is_applied = rule.feeds.contains(url);
+                                if ui.checkbox(&mut is_applied, url).changed() {
+                                    if is_applied {
+                                        rule.feeds.push(url.clone());
+                                    } else {
+                                        rule.feeds.retain(|f| f != url);
+                                    }
+                                }
+                            }
+                            if ui.button("Remove rule").clicked() {
+                                removed = Some(index);
+                            }
+                        });
+                });
+            }
+            if let Some(index) = removed {
+                rss.rules.remove(index);
+            }
+            if ui.button("Add rule").clicked() {
+                rss.rules.push(RssRule {
+                    name: format!("Rule {}", rss.rules.len() + 1),
+                    ..Default::default()
+                });
+            }
+        });
+
+        CollapsingHeader::new("IP Filter").show(ui, |ui| {
+            let ip_filter = &mut self.settings.ip_filter;
+            ui.checkbox(&mut ip_filter.enabled, "Block the ranges of the lists");
+            ui.add_enabled_ui(ip_filter.enabled, |ui| {
+                let mut removed = None;
+                for (index, source) in ip_filter.sources.iter_mut().enumerate() {
+                    ui.horizontal(|ui| {
+                        ui.add(
+                            TextEdit::singleline(source)
+                                .hint_text("https:// or path")
+                                .desired_width(300.0),
+                        );
+                        if ui.button("Browse").clicked() {
+                            if let Some(file) = FileDialog::new().pick_file() {
+                                *source = file.to_string_lossy().to_string();
+                            }
+                        }
+                        if ui.button("✖").on_hover_text("Remove the list").clicked() {
+                            removed = Some(index);
+                        }
+                    });
+                }
+                if let Some(index) = removed {
+                    ip_filter.sources.remove(index);
+                }
+                if ui.button("Add list").clicked() {
+                    ip_filter.sources.push("".to_owned());
+                }
+                ui.label(
+                    RichText::new("eMule ipfilter.dat, PeerGuardian P2P or CIDR, may be gzipped")
+                        .small(),
+                );
+                ui.horizontal(|ui| {
+                    ui.label("Refresh every: ");
+                    ui.add(
+                        DragValue::new(&mut ip_filter.refresh_interval_hours)
+                            .range(1..=720)
+                            .suffix(" h"),
+                    );
+                    if ui
+                        .button("Refresh now")
+                        .on_hover_text("Reloads the saved lists")
+                        .clicked()
+                    {
+                        self.channel_tx.send(Message::RefreshIpFilter).unwrap();
+                    }
+                });
+            });
+            ui.label(format!(
+                "{} ranges blocked, {} connections refused",
+                self.ip_filter.num_ranges, self.ip_filter.blocked_peers
+            ));
+            for error in &self.ip_filter.errors {
+                ui.label(RichText::new(error).small().color(Color32::RED));
+            }
+        });
+
+        CollapsingHeader::new("Banned Peers").show(ui, |ui| {
+            if self.banned_ips.is_empty() {
+                ui.label("Peers banned from the Peers tab are listed here.");
+            }
+            for ip in self.banned_ips {
+                ui.horizontal(|ui| {
+                    ui.label(ip);
+                    if ui.button("✖").on_hover_text("Unban").clicked() {
+                        self.channel_tx
+                            .send(Message::UnbanPeer(ip.clone()))
+                            .unwrap();
+                    }
+                });
+            }
+        });
+
+        CollapsingHeader::new("Network Interface").show(ui, |ui| {
+            let network = &mut self.settings.network;
+            ui.horizontal(|ui| {
+                ui.label("Interface: ");
+                let selected = match network.interface.as_str() {
+                    "" => "Any".to_owned(),
+                    interface => interface.to_owned(),
+                };
+                egui::ComboBox::from_id_salt("network_interface")
+                    .selected_text(selected)
+                    .show_ui(ui, |ui| {
+                        ui.selectable_value(&mut network.interface, "".to_owned(), "Any");
+                        let names: BTreeSet<&str> = self
+                            .network
+                            .interfaces
+                            .iter()
+                            .map(|i| i.name.as_str())
+                            .collect();
+                        for name in names {
+                            ui.selectable_value(&mut network.interface, name.to_owned(), name);
+                        }
+                        for i in &self.network.interfaces {
+                            let address = i.address.to_string();
+                            let label = format!("{} ({})", address, i.name);
+                            ui.selectable_value(&mut network.interface, address, label);
+                        }
+                    });
+            });
+            ui.label(
+                RichText::new(
+                    "Peers and trackers only use this interface. The session is paused while \
+                     it's down or has no address.",
+                )
+                .small(),
+            );
+            let status = self.network;
+            if status.is_paused {
+                ui.label(
+                    RichText::new(format!(
+                        "{} is down, the session is paused.",
+                        status.interface
+                    ))
+                    .color(Color32::RED),
+                );
+            } else if !status.interface.is_empty() {
+                let addresses: Vec<String> =
+                    status.addresses.iter().map(|a| a.to_string()).collect();
+                ui.label(format!(
+                    "Bound to {}: {}",
+                    status.interface,
+                    addresses.join(", ")
+                ));
+            }
+        });
+
+        CollapsingHeader::new("Proxy").show(ui, |ui| {
+            let proxy = &mut self.settings.proxy;
+            Grid::new("proxy_settings").num_columns(2).show(ui, |ui| {
+                ui.label("Type: ");
+                egui::ComboBox::from_id_salt("proxy_kind")
+                    .selected_text(proxy.kind.label())
+                    .show_ui(ui, |ui| {
+                        for kind in ProxyKind::ALL {
+                            ui.selectable_value(&mut proxy.kind, kind, kind.label());
+                        }
+                    });
+                ui.end_row();
+
+                let is_enabled = proxy.kind != ProxyKind::None;
+                ui.label("Host: ");
+                ui.add_enabled_ui(is_enabled, |ui| {
+                    ui.horizontal(|ui| {
+                        ui.add(TextEdit::singleline(&mut proxy.host).desired_width(150.0));
+                        ui.label("Port: ");
+                        ui.add(DragValue::new(&mut proxy.port).range(1..=65535));
+                    });
+                });
+                ui.end_row();
+
+                ui.label("Username: ");
+                ui.add_enabled(
+                    is_enabled,
+                    TextEdit::singleline(&mut proxy.username).desired_width(150.0),
+                );
+                ui.end_row();
+
+                ui.label("Password: ");
+                ui.add_enabled(
+                    is_enabled && proxy.kind != ProxyKind::Socks4,
+                    TextEdit::singleline(&mut proxy.password)
+                        .password(true)
+                        .desired_width(150.0),
+                );
+                ui.end_row();
+            });
+            ui.add_enabled_ui(proxy.kind != ProxyKind::None, |ui| {
+                ui.checkbox(
+                    &mut proxy.proxy_hostnames,
+                    "Resolve host names through the proxy",
+                );
+                ui.checkbox(&mut proxy.proxy_peer_connections, "Proxy peer connections");
+                ui.checkbox(
+                    &mut proxy.proxy_tracker_connections,
+                    "Proxy tracker connections",
+                );
+                if ui
+                    .add_enabled(proxy.is_enabled(), egui::Button::new("Test"))
+                    .on_hover_text("Connects through the proxy with these settings")
+                    .clicked()
+                {
+                    self.channel_tx
+                        .send(Message::CheckProxy(proxy.clone()))
+                        .unwrap();
+                }
+            });
+            if proxy.kind != ProxyKind::None && !proxy.proxy_peer_connections {
+                ui.label(RichText::new("Peers see your address.").small());
+            }
+            if proxy.kind != ProxyKind::None {
+                let direct = format!("{} are downloaded directly.", ProxySettings::DIRECT);
+                ui.label(RichText::new(direct).small());
+            }
+        });
+
+        CollapsingHeader::new("Log").show(ui, |ui| {
+            let log = &mut self.settings.log;
+            ui.horizontal(|ui| {
+                ui.label("Entries kept: ");
+                ui.add(DragValue::new(&mut log.max_entries).range(100..=100000));
+            });
+            ui.label("libtorrent alerts logged:");
+            for category in AlertCategory::ALL {
+                let mut is_logged = log.alert_categories.contains(&category);
+                if ui.checkbox(&mut is_logged, category.label()).changed() {
+                    if is_logged {
+                        log.alert_categories.push(category);
+                    } else {
+                        log.alert_categories.retain(|c| *c != category);
+                    }
+                }
+            }
+        });
+
+        CollapsingHeader::new("Web UI").show(ui, |ui| {
+            let web = &mut self.settings.web;
+            ui.checkbox(&mut web.enabled, "Enable the web UI and REST API");
+            ui.add_enabled_ui(web.enabled, |ui| {
+                Grid::new("web_settings").num_columns(2).show(ui, |ui| {
+                    ui.label("Address: ");
+                    ui.horizontal(|ui| {
+                        ui.add(TextEdit::singleline(&mut web.bind_address).desired_width(150.0));
+                        ui.label("Port: ");
+                        ui.add(DragValue::new(&mut web.port).range(1..=65535));
+                    });
+                    ui.end_row();
+
+                    ui.label("Username: ");
+                    ui.add(TextEdit::singleline(&mut web.username).desired_width(150.0));
+                    ui.end_row();
+
+                    ui.label("Password: ");
+                    ui.add(
+                        TextEdit::singleline(&mut web.password)
+                            .password(true)
+                            .desired_width(150.0),
+                    );
+                    ui.end_row();
+
+                    ui.label("Use HTTPS: ");
+                    ui.checkbox(&mut web.use_tls, "");
+                    ui.end_row();
+
+                    for (label, path) in [
+                        ("Certificate: ", &mut web.cert_path),
+                        ("Private key: ", &mut web.key_path),
+                    ] {
+                        ui.label(label);
+                        ui.add_enabled_ui(web.use_tls, |ui| {
+                            ui.horizontal(|ui| {
+                                ui.add(TextEdit::singleline(path).desired_width(300.0));
+                                if ui.button("Browse").clicked() {
+                                    if let Some(file) = FileDialog::new().pick_file() {
+                                        *path = file.to_string_lossy().to_string();
+                                    }
+                                }
+                            });
+                        });
+                        ui.end_row();
+                    }
+                });
+                ui.checkbox(
+                    &mut web.transmission_rpc,
+                    "Transmission RPC at /transmission/rpc",
+                );
+                ui.checkbox(&mut web.qbittorrent_api, "qBittorrent Web API at /api/v2/");
+                ui.checkbox(&mut web.metrics, "Prometheus metrics at /metrics");
+                if web.username.is_empty() {
+                    ui.label(
+                        RichText::new("Anyone reaching the address can control the client.")
+                            .small(),
+                    );
+                }
+            });
+        });
+        ui.response()
+    }
+}
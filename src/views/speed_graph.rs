@@ -0,0 +1,119 @@
+use std::time::Instant;
+
+use egui::{
+    Align2, Color32, FontId, Pos2, Response, RichText, Sense, Shape, Stroke, Ui, Vec2, Widget,
+};
+
+use crate::{
+    format_bytes,
+    models::history::{GraphRange, SpeedHistory},
+};
+
+const DOWNLOAD_COLOR: Color32 = Color32::from_rgb(90, 200, 90);
+const UPLOAD_COLOR: Color32 = Color32::from_rgb(90, 150, 230);
+
+pub struct SpeedGraphWidget<'a> {
+    pub history: &'a SpeedHistory,
+    pub range: &'a mut GraphRange,
+    /// The longest range the history covers.
+    pub max_range: GraphRange,
+    /// Download and upload limits in bytes per second.
+    pub download_limit: Option<i64>,
+    pub upload_limit: Option<i64>,
+    pub height: f32,
+}
+
+impl<'a> Widget for SpeedGraphWidget<'a> {
+    fn ui(self, ui: &mut Ui) -> Response {
+        let now = Instant::now();
+        let range = self.range.duration();
+        let samples: Vec<_> = self.history.since(now, range).collect();
+
+        ui.horizontal(|ui| {
+            for range in GraphRange::ALL {
+                if range.duration() <= self.max_range.duration() {
+                    ui.selectable_value(self.range, range, range.label());
+                }
+            }
+            ui.separator();
+            if let Some(last) = samples.last() {
+                ui.label(
+                    RichText::new(format!("⬇ {}", format_bytes!(last.download, "/s")))
+                        .color(DOWNLOAD_COLOR),
+                );
+                ui.label(
+                    RichText::new(format!("⬆ {}", format_bytes!(last.upload, "/s")))
+                        .color(UPLOAD_COLOR),
+                );
+            }
+        });
+
+        let (rect, res) =
+            ui.allocate_exact_size(Vec2::new(ui.available_width(), self.height), Sense::hover());
+        let painter = ui.painter_at(rect);
+        let visuals = ui.visuals();
+        painter.rect_stroke(rect, 2.0, visuals.widgets.noninteractive.bg_stroke);
+
+        let max_rate = samples
+            .iter()
+            .map(|s| s.download.max(s.upload))
+            .chain(self.download_limit)
+            .chain(self.upload_limit)
+            .max()
+            .unwrap_or(0)
+            .max(1000) as f32
+            * 1.1;
+        let to_y = |rate: i64| rect.bottom() - rate as f32 / max_rate * rect.height();
+        let to_x = |time: Instant| {
+            let secs_ago = now.duration_since(time).as_secs_f32();
+            rect.right() - secs_ago / range.as_secs_f32() * rect.width()
+        };
+
+        // Limits
+        for (limit, color) in [
+            (self.download_limit, DOWNLOAD_COLOR),
+            (self.upload_limit, UPLOAD_COLOR),
+        ] {
+            if let Some(limit) = limit {
+                let y = to_y(limit);
+                painter.extend(Shape::dashed_line(
+                    &[Pos2::new(rect.left(), y), Pos2::new(rect.right(), y)],
+                    Stroke::new(1.0, color.gamma_multiply(0.6)),
+                    4.0,
+                    4.0,
+                ));
+            }
+        }
+
+        // Rates
+        if samples.len() > 1 {
+            let download = samples
+                .iter()
+                .map(|s| Pos2::new(to_x(s.time), to_y(s.download)))
+                .collect();
+            let upload = samples
+                .iter()
+                .map(|s| Pos2::new(to_x(s.time), to_y(s.upload)))
+                .collect();
+            painter.add(Shape::line(download, Stroke::new(1.5, DOWNLOAD_COLOR)));
+            painter.add(Shape::line(upload, Stroke::new(1.5, UPLOAD_COLOR)));
+        }
+
+        let text_color = visuals.weak_text_color();
+        painter.text(
+            rect.left_top() + Vec2::new(4.0, 2.0),
+            Align2::LEFT_TOP,
+            format_bytes!(max_rate as i64, "/s"),
+            FontId::proportional(10.0),
+            text_color,
+        );
+        painter.text(
+            rect.left_bottom() + Vec2::new(4.0, -2.0),
+            Align2::LEFT_BOTTOM,
+            format!("{} ago", self.range.label()),
+            FontId::proportional(10.0),
+            text_color,
+        );
+        res
+    }
+}
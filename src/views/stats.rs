@@ -0,0 +1,116 @@
+use egui::{CollapsingHeader, Grid, Response, Ui, Widget};
+
+use crate::{format_bytes, models::stats::SessionStats};
+
+const NOT_AVAILABLE: &str = "N/A";
+
+pub struct StatsWidget<'a> {
+    pub stats: &'a SessionStats,
+}
+
+impl<'a> StatsWidget<'a> {
+    fn count(&self, ui: &mut Ui, label: &str, names: &[&str]) {
+        ui.label(label);
+        match self.stats.sum(names) {
+            Some(value) => ui.label(value.to_string()),
+            None => ui.label(NOT_AVAILABLE),
+        };
+        ui.end_row();
+    }
+
+    fn bytes(&self, ui: &mut Ui, label: &str, names: &[&str]) {
+        ui.label(label);
+        match self.stats.sum(names) {
+            Some(value) => ui.label(format_bytes!(value)),
+            None => ui.label(NOT_AVAILABLE),
+        };
+        ui.end_row();
+    }
+
+    /// Total bytes followed by the current rate.
+    fn transfer(&self, ui: &mut Ui, label: &str, names: &[&str]) {
+        ui.label(label);
+        match self.stats.sum(names) {
+            Some(value) => ui.label(format_bytes!(value)),
+            None => ui.label(NOT_AVAILABLE),
+        };
+        match self.stats.sum_rate(names) {
+            Some(rate) => ui.label(format_bytes!(rate as i64, "/s")),
+            None => ui.label(""),
+        };
+        ui.end_row();
+    }
+}
+
+impl<'a> Widget for StatsWidget<'a> {
+    fn ui(self, ui: &mut Ui) -> Response {
+        if self.stats.is_empty() {
+            ui.label("Waiting for the session…");
+            return ui.response();
+        }
+
+        CollapsingHeader::new("Transfer")
+            .default_open(true)
+            .show(ui, |ui| {
+                Grid::new("stats_transfer").num_columns(3).show(ui, |ui| {
+                    self.transfer(ui, "Payload downloaded: ", &["net.recv_payload_bytes"]);
+                    self.transfer(ui, "Payload uploaded: ", &["net.sent_payload_bytes"]);
+                    // Protocol bytes include the payload
+                    self.transfer(
+                        ui,
+                        "Total downloaded: ",
+                        &["net.recv_bytes", "net.recv_ip_overhead_bytes"],
+                    );
+                    self.transfer(
+                        ui,
+                        "Total uploaded: ",
+                        &["net.sent_bytes", "net.sent_ip_overhead_bytes"],
+                    );
+                    self.transfer(
+                        ui,
+                        "Tracker traffic: ",
+                        &["net.recv_tracker_bytes", "net.sent_tracker_bytes"],
+                    );
+                    self.transfer(ui, "Wasted (redundant): ", &["net.recv_redundant_bytes"]);
+                    self.transfer(ui, "Wasted (hash failed): ", &["net.recv_failed_bytes"]);
+                });
+            });
+
+        CollapsingHeader::new("Connections")
+            .default_open(true)
+            .show(ui, |ui| {
+                Grid::new("stats_connections")
+                    .num_columns(2)
+                    .show(ui, |ui| {
+                        self.count(ui, "Connected peers: ", &["peer.num_peers_connected"]);
+                        self.count(ui, "Half-open: ", &["peer.num_peers_half_open"]);
+                        self.count(ui, "TCP: ", &["peer.num_tcp_peers"]);
+                        self.count(ui, "uTP: ", &["peer.num_utp_peers"]);
+                        self.count(
+                            ui,
+                            "SSL: ",
+                            &["peer.num_ssl_peers", "peer.num_ssl_utp_peers"],
+                        );
+                        self.count(ui, "SOCKS5: ", &["peer.num_socks5_peers"]);
+                        self.count(ui, "HTTP proxy: ", &["peer.num_http_proxy_peers"]);
+                        self.count(ui, "DHT nodes: ", &["dht.dht_nodes"]);
+                    });
+            });
+
+        CollapsingHeader::new("Disk")
+            .default_open(true)
+            .show(ui, |ui| {
+                Grid::new("stats_disk").num_columns(2).show(ui, |ui| {
+                    self.count(ui, "Queued jobs: ", &["disk.queued_disk_jobs"]);
+                    self.bytes(ui, "Queued writes: ", &["disk.queued_write_bytes"]);
+                    self.count(ui, "Buffers in use: ", &["disk.disk_blocks_in_use"]);
+                    self.count(ui, "Read operations: ", &["disk.num_read_ops"]);
+                    self.count(ui, "Write operations: ", &["disk.num_write_ops"]);
+                    self.count(ui, "Blocks read: ", &["disk.num_blocks_read"]);
+                    self.count(ui, "Blocks written: ", &["disk.num_blocks_written"]);
+                    self.count(ui, "Cache hits: ", &["disk.num_blocks_cache_hits"]);
+                });
+            });
+        ui.response()
+    }
+}
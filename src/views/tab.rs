@@ -3,18 +3,30 @@ use std::sync::mpsc::Sender;
 use egui::{Align, Layout, RichText, Sense, Vec2, Widget};
 
 use crate::models::{
+    column::PeerTableLayout,
+    history::{GraphRange, SpeedHistory},
     message::Message,
     tab::{Tab, TabView},
     torrent::Torrent,
 };
 
-use super::{files::FilesWidget, general::GeneralWidget, peers::PeersWidget};
+use super::{
+    files::FilesWidget,
+    general::GeneralWidget,
+    peers::{PeersDraft, PeersWidget},
+    speed_graph::SpeedGraphWidget,
+    trackers::TrackersWidget,
+};
 
 pub struct TabWidget<'a> {
     pub tab_view: &'a mut TabView,
     pub channel_tx: &'a Sender<Message>,
     pub torrent: &'a Torrent,
     pub index: usize,
+    pub history: Option<&'a SpeedHistory>,
+    pub graph_range: &'a mut GraphRange,
+    pub peer_layout: &'a mut PeerTableLayout,
+    pub peers_draft: &'a mut Option<PeersDraft>,
 }
 
 impl<'a> Widget for TabWidget<'a> {
@@ -86,7 +98,29 @@ impl<'a> Widget for TabWidget<'a> {
                         .send(Message::FetchPeers(self.index))
                         .unwrap();
 
-                    ui.add(PeersWidget::new(&self.torrent.peers));
+                    ui.add(PeersWidget::new(
+                        &self.torrent.peers,
+                        self.channel_tx,
+                        self.index,
+                        self.peer_layout,
+                        self.peers_draft,
+                    ));
+                }
+                Tab::Trackers => {
+                    ui.add(TrackersWidget::new(&self.torrent.trackers));
+                }
+                Tab::Speed => {
+                    if let Some(history) = self.history {
+                        let limit = |limit: i32| (limit > 0).then_some(limit as i64);
+                        ui.add(SpeedGraphWidget {
+                            history,
+                            range: self.graph_range,
+                            max_range: GraphRange::OneHour,
+                            download_limit: limit(self.torrent.download_limit),
+                            upload_limit: limit(self.torrent.upload_limit),
+                            height: 150.0,
+                        });
+                    }
                 }
             }
         });
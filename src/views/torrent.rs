@@ -1,10 +1,11 @@
 use std::sync::mpsc::Sender;
 
-use egui::{Color32, Label, RichText, Widget};
+use egui::{Color32, Id, Label, RichText, Rounding, Sense, Shape, TextEdit, Widget};
 
 use crate::{
     format_bytes,
     models::{
+        category::Category,
         message::Message,
         torrent::{Torrent, TorrentState},
     },
@@ -12,11 +13,17 @@ use crate::{
 
 use super::progress_bar::CompoundProgressBar;
 
+/// Shows a torrent, returning the response of its title, which is clicked to
+/// select the torrent.
 pub struct TorrentWidget<'a> {
     pub torrent: &'a Torrent,
+    pub is_selected: bool,
     pub sel_torrent: Option<usize>,
     pub index: usize,
     pub channel_tx: &'a Sender<Message>,
+    pub categories: &'a [Category],
+    /// Tags of all torrents, offered in the tags menu.
+    pub tags: &'a [String],
 }
 
 impl<'a> Widget for TorrentWidget<'a> {
@@ -28,9 +35,15 @@ impl<'a> Widget for TorrentWidget<'a> {
                 &self.torrent.name
             };
             let rich_text = RichText::new(name).size(14.0).strong();
-            Label::new(rich_text).truncate().halign(egui::Align::LEFT)
+            Label::new(rich_text)
+                .truncate()
+                .halign(egui::Align::LEFT)
+                .sense(Sense::click())
         };
-        ui.vertical(|ui| {
+        // Selection highlight, painted once the size is known
+        let background = ui.painter().add(Shape::Noop);
+        let mut title_res = None;
+        let res = ui.vertical(|ui| {
             // Title and controls
             ui.with_layout(egui::Layout::right_to_left(egui::Align::TOP), |ui| {
                 // Remove torrent
@@ -66,6 +79,78 @@ impl<'a> Widget for TorrentWidget<'a> {
                         .unwrap();
                 }
 
+                // Category
+                ui.menu_button("🔖", |ui| {
+                    let mut names = vec!["".to_owned()];
+                    names.extend(self.categories.iter().map(|c| c.name.clone()));
+                    for name in names {
+                        let label = if name.is_empty() {
+                            "Uncategorized"
+                        } else {
+                            &name
+                        };
+                        if ui
+                            .selectable_label(self.torrent.category == name, label)
+                            .clicked()
+                        {
+                            self.channel_tx
+                                .send(Message::SetCategory(self.index, name.clone()))
+                                .unwrap();
+                            ui.close_menu();
+                        }
+                    }
+                })
+                .response
+                .on_hover_text("Category");
+
+                // Tags
+                ui.menu_button("🏷", |ui| {
+                    let mut tags = self.torrent.tags.clone();
+                    let mut has_changed = false;
+                    for tag in self.tags {
+                        let mut is_tagged = tags.contains(tag);
+                        if ui.checkbox(&mut is_tagged, tag).changed() {
+                            has_changed = true;
+                            if is_tagged {
+                                tags.push(tag.clone());
+                            } else {
+                                tags.retain(|t| t != tag);
+                            }
+                        }
+                    }
+
+                    let id = Id::new(("new_tag", self.index));
+                    let mut new_tag: String = ui.data_mut(|d| d.get_temp(id).unwrap_or_default());
+                    let res = ui.add(
+                        TextEdit::singleline(&mut new_tag)
+                            .hint_text("New tag")
+                            .desired_width(120.0),
+                    );
+                    let trimmed = new_tag.trim().to_owned();
+                    if res.lost_focus()
+                        && ui.input(|i| i.key_pressed(egui::Key::Enter))
+                        && !trimmed.is_empty()
+                    {
+                        if !tags.contains(&trimmed) {
+                            tags.push(trimmed);
+                            has_changed = true;
+                        }
+                        ui.data_mut(|d| d.remove::<String>(id));
+                    } else {
+                        ui.data_mut(|d| d.insert_temp(id, new_tag));
+                    }
+
+                    // Sent once, the torrent shows the tags from the next refresh
+                    if has_changed {
+                        tags.sort();
+                        self.channel_tx
+                            .send(Message::SetTags(self.index, tags))
+                            .unwrap();
+                    }
+                })
+                .response
+                .on_hover_text("Tags");
+
                 // Info button
                 let is_selected = Some(self.index + 1) == self.sel_torrent;
                 let text = if is_selected {
@@ -105,7 +190,7 @@ impl<'a> Widget for TorrentWidget<'a> {
                 }
 
                 ui.with_layout(egui::Layout::left_to_right(egui::Align::TOP), |ui| {
-                    ui.add(torrent_title);
+                    title_res = Some(ui.add(torrent_title));
                 })
             });
 
@@ -151,6 +236,9 @@ impl<'a> Widget for TorrentWidget<'a> {
                     self.torrent.num_seeds
                 ));
             });
+            if !self.torrent.error.is_empty() {
+                ui.label(RichText::new(&self.torrent.error).color(Color32::RED));
+            }
 
             // Compound progress bar
             if self.torrent.state == TorrentState::DownloadingMetaData
@@ -160,6 +248,14 @@ impl<'a> Widget for TorrentWidget<'a> {
                 ui.add(CompoundProgressBar::new(self.torrent));
             }
         });
-        ui.response()
+        if self.is_selected {
+            let fill = ui.visuals().selection.bg_fill.gamma_multiply(0.3);
+            let rect = res.response.rect.expand(3.0);
+            ui.painter().set(
+                background,
+                Shape::rect_filled(rect, Rounding::same(2.5), fill),
+            );
+        }
+        title_res.unwrap_or(res.response)
     }
 }
@@ -0,0 +1,142 @@
+use std::{
+    sync::mpsc::Sender,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use egui::{Label, ProgressBar, Response, RichText, Sense, Ui, Widget};
+use egui_extras::{Column, TableBuilder};
+
+use crate::{
+    duration::format_duration,
+    format_bytes,
+    models::{
+        column::{TableLayout, TorrentColumn},
+        message::Message,
+        selection::Selection,
+        sort::SortOrder,
+        torrent::Torrent,
+    },
+};
+
+/// The compact alternative to listing `TorrentWidget`s.
+pub struct TorrentTableWidget<'a> {
+    pub torrents: &'a [Torrent],
+    /// The listed torrents, in order.
+    pub indices: &'a [usize],
+    pub selection: &'a Selection,
+    pub layout: &'a TableLayout,
+    pub sort: &'a mut SortOrder,
+    /// Set to the index of the torrent whose row was clicked.
+    pub clicked: &'a mut Option<usize>,
+    pub channel_tx: &'a Sender<Message>,
+}
+
+fn cell(ui: &mut Ui, column: TorrentColumn, torrent: &Torrent, now: i64) {
+    let text = match column {
+        TorrentColumn::Name => {
+            let name = if torrent.name.trim().is_empty() {
+                &torrent.hash
+            } else {
+                &torrent.name
+            };
+            ui.add(Label::new(name).truncate());
+            return;
+        }
+        TorrentColumn::Progress => {
+            ui.add(
+                ProgressBar::new(torrent.progress)
+                    .text(format!("{:.1}%", torrent.progress * 100.0)),
+            );
+            return;
+        }
+        TorrentColumn::Size => format_bytes!(torrent.total_size),
+        TorrentColumn::State => torrent.state.to_string(),
+        TorrentColumn::Seeds => torrent.num_seeds.to_string(),
+        TorrentColumn::Peers => torrent.num_peers.to_string(),
+        TorrentColumn::DownloadRate => format_bytes!(torrent.download_rate, "/s"),
+        TorrentColumn::UploadRate => format_bytes!(torrent.upload_rate, "/s"),
+        TorrentColumn::Eta => format_duration(torrent.eta),
+        TorrentColumn::Ratio => format!("{:.2}", torrent.ratio()),
+        TorrentColumn::Added => format!("{} ago", format_duration(now - torrent.added_time)),
+        TorrentColumn::Category => torrent.category.clone(),
+        TorrentColumn::Tracker => torrent
+            .trackers
+            .iter()
+            .find_map(|t| t.host())
+            .unwrap_or_default(),
+        TorrentColumn::SavePath => torrent.save_path.clone(),
+    };
+    ui.add(Label::new(text).truncate());
+}
+
+impl<'a> Widget for TorrentTableWidget<'a> {
+    fn ui(self, ui: &mut Ui) -> Response {
+        let columns = self.layout.visible();
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_secs() as i64)
+            .unwrap_or(0);
+
+        let mut table = TableBuilder::new(ui)
+            // The widths are remembered per arrangement of the columns
+            .id_salt(("torrent_table", &columns))
+            .striped(true)
+            .auto_shrink(true)
+            .vscroll(false)
+            .sense(Sense::click());
+        for column in &columns {
+            table = table.column(
+                Column::initial(column.initial_width())
+                    .at_least(30.0)
+                    .resizable(true)
+                    .clip(true),
+            );
+        }
+        table
+            .header(20.0, |mut header| {
+                for column in &columns {
+                    header.col(|ui| {
+                        let mut text = column.label().to_owned();
+                        if column.sort_key() == Some(self.sort.key) {
+                            text.push_str(if self.sort.descending { " ⬇" } else { " ⬆" });
+                        }
+                        let res = ui.add(
+                            Label::new(RichText::new(text).strong())
+                                .selectable(false)
+                                .sense(Sense::click()),
+                        );
+                        if let Some(key) = column.sort_key() {
+                            if res.clicked() {
+                                if self.sort.key == key {
+                                    self.sort.descending = !self.sort.descending;
+                                } else {
+                                    self.sort.key = key;
+                                    self.sort.descending = false;
+                                }
+                            }
+                        }
+                    });
+                }
+            })
+            .body(|body| {
+                body.rows(22.0, self.indices.len(), |mut row| {
+                    let index = self.indices[row.index()];
+                    let torrent = &self.torrents[index];
+                    row.set_selected(self.selection.indices.contains(&index));
+                    for column in &columns {
+                        row.col(|ui| cell(ui, *column, torrent, now));
+                    }
+                    let res = row.response();
+                    if res.clicked() {
+                        *self.clicked = Some(index);
+                    }
+                    if res.double_clicked() {
+                        self.channel_tx
+                            .send(Message::UpdateSelTorrent(Some(index + 1)))
+                            .unwrap();
+                    }
+                });
+            });
+        ui.response()
+    }
+}
@@ -0,0 +1,79 @@
+use egui::{Response, RichText, Ui, Widget};
+use egui_extras::{Column, TableBuilder};
+
+use crate::models::tracker::{Tracker, TrackerStatus};
+
+pub struct TrackersWidget<'a> {
+    trackers: &'a Vec<Tracker>,
+}
+
+impl<'a> TrackersWidget<'a> {
+    pub fn new(trackers: &'a Vec<Tracker>) -> Self {
+        Self { trackers }
+    }
+}
+
+impl<'a> Widget for TrackersWidget<'a> {
+    fn ui(self, ui: &mut Ui) -> Response {
+        TableBuilder::new(ui)
+            .striped(true)
+            .auto_shrink(true)
+            .vscroll(false)
+            .column(Column::auto().resizable(true))
+            .column(Column::remainder().resizable(true))
+            .column(Column::auto().resizable(true))
+            .column(Column::auto().resizable(true))
+            .column(Column::auto().resizable(true))
+            .column(Column::auto().resizable(true))
+            .header(20.0, |mut header| {
+                header.col(|ui| {
+                    ui.label(RichText::new("Tier").strong());
+                });
+                header.col(|ui| {
+                    ui.label(RichText::new("URL").strong());
+                });
+                header.col(|ui| {
+                    ui.label(RichText::new("Status").strong());
+                });
+                header.col(|ui| {
+                    ui.label(RichText::new("Seeds").strong());
+                });
+                header.col(|ui| {
+                    ui.label(RichText::new("Peers").strong());
+                });
+                header.col(|ui| {
+                    ui.label(RichText::new("Message").strong());
+                });
+            })
+            .body(|mut body| {
+                self.trackers.iter().for_each(|t| {
+                    body.row(30.0, |mut row| {
+                        row.col(|ui| {
+                            ui.label(t.tier.to_string());
+                        });
+                        row.col(|ui| {
+                            ui.label(t.url.clone());
+                        });
+                        row.col(|ui| {
+                            ui.label(match t.status {
+                                TrackerStatus::NotContacted => "Not contacted",
+                                TrackerStatus::Working => "Working",
+                                TrackerStatus::Updating => "Updating",
+                                TrackerStatus::Error => "Error",
+                            });
+                        });
+                        row.col(|ui| {
+                            ui.label(t.num_seeds.to_string());
+                        });
+                        row.col(|ui| {
+                            ui.label(t.num_peers.to_string());
+                        });
+                        row.col(|ui| {
+                            ui.label(t.message.clone());
+                        });
+                    });
+                });
+            });
+        ui.response()
+    }
+}
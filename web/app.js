@@ -0,0 +1,302 @@
+"use strict";
+
+const REFRESH_INTERVAL_MS = 2000;
+const PRIORITIES = ["skip", "low", "default", "high"];
+
+let selected = null;
+let tab = "general";
+let categories = [];
+
+async function api(method, path, body, contentType) {
+  const options = { method, headers: {} };
+  if (body !== undefined) {
+    options.headers["Content-Type"] = contentType || "application/json";
+    options.body = contentType ? body : JSON.stringify(body);
+  }
+  const response = await fetch("/api/" + path, options);
+  const json = await response.json();
+  if (!json.ok) {
+    throw new Error(json.error || response.statusText);
+  }
+  return json.data;
+}
+
+function showError(e) {
+  const error = document.getElementById("error");
+  error.textContent = e.message;
+  error.hidden = false;
+  setTimeout(() => (error.hidden = true), 5000);
+}
+
+function formatBytes(bytes) {
+  const units = ["B", "KB", "MB", "GB", "TB"];
+  let i = 0;
+  while (bytes >= 1000 && i < units.length - 1) {
+    bytes /= 1000;
+    i++;
+  }
+  return bytes.toFixed(i === 0 ? 0 : 1) + " " + units[i];
+}
+
+function formatRate(rate) {
+  return rate > 0 ? formatBytes(rate) + "/s" : "";
+}
+
+function cell(row, content) {
+  const td = row.insertCell();
+  if (content instanceof Node) {
+    td.appendChild(content);
+  } else {
+    td.textContent = content;
+  }
+  return td;
+}
+
+function progressBar(progress) {
+  const bar = document.createElement("progress");
+  bar.max = 1;
+  bar.value = progress;
+  bar.title = (progress * 100).toFixed(1) + "%";
+  return bar;
+}
+
+function fillCategories(select, value) {
+  select.replaceChildren();
+  for (const name of [""].concat(categories.map((c) => c.name))) {
+    const option = new Option(name || "No category", name);
+    option.selected = name === value;
+    select.add(option);
+  }
+}
+
+async function refreshTorrents() {
+  const torrents = await api("GET", "torrents");
+  const tbody = document.querySelector("#torrents tbody");
+  tbody.replaceChildren();
+  for (const t of torrents) {
+    const row = tbody.insertRow();
+    row.classList.toggle("selected", t.hash === selected);
+    row.onclick = () => select(t.hash);
+    cell(row, t.name);
+    const state = cell(row, t.error ? "Error: " + t.error : t.state);
+    state.classList.toggle("error", !!t.error);
+    cell(row, progressBar(t.progress));
+    cell(row, formatBytes(t.total_size));
+    cell(row, formatRate(t.download_rate));
+    cell(row, formatRate(t.upload_rate));
+    cell(row, t.num_peers + " (" + t.num_seeds + ")");
+    cell(row, t.ratio.toFixed(2));
+    cell(row, t.category);
+  }
+  if (selected && !torrents.some((t) => t.hash === selected)) {
+    select(null);
+  }
+}
+
+function renderGeneral(info) {
+  const dl = document.createElement("dl");
+  const fields = [
+    ["Name", info.name],
+    ["Hash", info.hash],
+    ["Save path", info.save_path],
+    ["Size", formatBytes(info.total_size)],
+    ["Files", info.num_files],
+    ["Downloaded", formatBytes(info.total_download)],
+    ["Uploaded", formatBytes(info.total_upload)],
+    ["Tags", info.tags.join(", ")],
+    ["Added", new Date(info.added_time * 1000).toLocaleString()],
+    ["Comment", info.comment],
+  ];
+  for (const [name, value] of fields) {
+    dl.appendChild(document.createElement("dt")).textContent = name;
+    dl.appendChild(document.createElement("dd")).textContent = value;
+  }
+  fillCategories(document.getElementById("category"), info.category);
+  return dl;
+}
+
+function renderTable(headers, rows) {
+  const table = document.createElement("table");
+  const head = table.createTHead().insertRow();
+  for (const header of headers) {
+    head.appendChild(document.createElement("th")).textContent = header;
+  }
+  const body = table.createTBody();
+  for (const row of rows) {
+    const tr = body.insertRow();
+    for (const content of row) {
+      cell(tr, content);
+    }
+  }
+  return table;
+}
+
+function renderFiles(files) {
+  return renderTable(
+    ["Path", "Priority"],
+    files.map((f, index) => {
+      const priority = document.createElement("select");
+      for (const p of PRIORITIES) {
+        const option = new Option(p, p);
+        option.selected = p === f.priority;
+        priority.add(option);
+      }
+      priority.onchange = () =>
+        command("set-file-priority", { index, priority: priority.value });
+      return [f.path, priority];
+    })
+  );
+}
+
+function peerAddress(peer) {
+  const ip = peer.ip_address.includes(":") ? "[" + peer.ip_address + "]" : peer.ip_address;
+  return ip + ":" + peer.port;
+}
+
+function renderPeers(peers) {
+  return renderTable(
+    ["Address", "Client", "Progress", "Down", "Up"],
+    peers.map((p) => [
+      peerAddress(p),
+      p.client,
+      progressBar(p.progress),
+      formatRate(p.download_rate),
+      formatRate(p.upload_rate),
+    ])
+  );
+}
+
+function renderTrackers(trackers) {
+  return renderTable(
+    ["Tier", "URL", "Status", "Peers", "Seeds", "Message"],
+    trackers.map((t) => [t.tier, t.url, t.status, t.num_peers, t.num_seeds, t.message])
+  );
+}
+
+async function refreshDetails() {
+  if (!selected) {
+    return;
+  }
+  const renderers = {
+    general: ["", renderGeneral],
+    files: ["/files", renderFiles],
+    peers: ["/peers", renderPeers],
+    trackers: ["/trackers", renderTrackers],
+  };
+  const [suffix, render] = renderers[tab];
+  const data = await api("GET", "torrents/" + selected + suffix);
+  // Don't replace a file priority being picked
+  if (tab === "files" && document.activeElement.tagName === "SELECT") {
+    return;
+  }
+  document.getElementById("tab").replaceChildren(render(data));
+}
+
+async function refresh() {
+  try {
+    await refreshTorrents();
+    await refreshDetails();
+  } catch (e) {
+    showError(e);
+  }
+}
+
+function select(hash) {
+  selected = hash;
+  document.getElementById("details").hidden = !hash;
+  document.getElementById("tab").replaceChildren();
+  refresh();
+}
+
+async function command(name, body) {
+  try {
+    await api("POST", "torrents/" + selected + "/" + name, body || {});
+    await refresh();
+  } catch (e) {
+    showError(e);
+  }
+}
+
+async function remove(deleteFiles) {
+  if (!confirm(deleteFiles ? "Remove the torrent and its files?" : "Remove the torrent?")) {
+    return;
+  }
+  try {
+    await api("DELETE", "torrents/" + selected + "?delete_files=" + deleteFiles);
+    select(null);
+  } catch (e) {
+    showError(e);
+  }
+}
+
+async function loadCategories() {
+  try {
+    categories = await api("GET", "categories");
+    fillCategories(document.getElementById("add-category"), "");
+  } catch (e) {
+    showError(e);
+  }
+}
+
+document.getElementById("add-magnet").onsubmit = async (event) => {
+  event.preventDefault();
+  const magnet = document.getElementById("magnet");
+  try {
+    await api("POST", "torrents", {
+      source: magnet.value.trim(),
+      category: document.getElementById("add-category").value,
+      paused: document.getElementById("add-paused").checked,
+    });
+    magnet.value = "";
+    await refresh();
+  } catch (e) {
+    showError(e);
+  }
+};
+
+document.getElementById("upload").onchange = async (event) => {
+  const params = new URLSearchParams({
+    category: document.getElementById("add-category").value,
+    paused: document.getElementById("add-paused").checked,
+  });
+  try {
+    for (const file of event.target.files) {
+      await api("POST", "torrents?" + params, file, "application/x-bittorrent");
+    }
+    await refresh();
+  } catch (e) {
+    showError(e);
+  }
+  event.target.value = "";
+};
+
+for (const button of document.querySelectorAll("[data-command]")) {
+  button.onclick = () => command(button.dataset.command);
+}
+
+for (const button of document.querySelectorAll("[data-tab]")) {
+  button.onclick = () => {
+    tab = button.dataset.tab;
+    for (const other of document.querySelectorAll("[data-tab]")) {
+      other.classList.toggle("active", other === button);
+    }
+    document.getElementById("tab").replaceChildren();
+    refresh();
+  };
+}
+
+document.getElementById("move").onclick = () => {
+  const path = prompt("Move to:");
+  if (path) {
+    command("move", { path });
+  }
+};
+
+document.getElementById("category").onchange = (event) =>
+  command("set-category", { category: event.target.value });
+document.getElementById("remove").onclick = () => remove(false);
+document.getElementById("remove-files").onclick = () => remove(true);
+
+loadCategories();
+refresh();
+setInterval(refresh, REFRESH_INTERVAL_MS);